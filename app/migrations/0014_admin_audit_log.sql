@@ -0,0 +1,10 @@
+-- Durable record of actions performed through the admin endpoints
+CREATE TABLE admin_audit_log (
+    audit_id BIGSERIAL PRIMARY KEY,
+    action TEXT NOT NULL,
+    parameters JSONB NOT NULL DEFAULT '{}'::jsonb,
+    admin_identity TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_admin_audit_log_created_at ON admin_audit_log (created_at DESC);
@@ -0,0 +1,16 @@
+-- Discourse accounts claimed by SSO users, verified with a one-time code placed in the Discourse bio
+CREATE TABLE user_discourse_links (
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    discourse_id TEXT NOT NULL,
+    discourse_user_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    verification_code TEXT NOT NULL,
+    verified_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, discourse_id)
+);
+
+-- A Discourse account can only be claimed by a single SSO user
+CREATE UNIQUE INDEX idx_user_discourse_links_verified
+    ON user_discourse_links (discourse_id, discourse_user_id)
+    WHERE verified_at IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Forum topics attached to a workshop chat, injected as context on every turn
+CREATE TABLE workshop_chat_contexts (
+    chat_id UUID NOT NULL REFERENCES workshop_chats(chat_id) ON DELETE CASCADE,
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    context TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (chat_id, discourse_id, topic_id)
+);
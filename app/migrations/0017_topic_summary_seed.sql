@@ -0,0 +1,2 @@
+-- Seed the summary was generated with, so it can be reproduced
+ALTER TABLE topic_summaries ADD COLUMN seed BIGINT;
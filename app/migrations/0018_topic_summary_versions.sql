@@ -0,0 +1,21 @@
+-- Every generated summary of a topic, the newest one is the current summary
+CREATE TABLE topic_summary_versions (
+    version_id BIGSERIAL PRIMARY KEY,
+    summary_id INT REFERENCES topic_summaries(summary_id) ON DELETE SET NULL,
+    discourse_id TEXT NOT NULL,
+    topic_id INT NOT NULL,
+    summary_text TEXT NOT NULL,
+    model TEXT,
+    seed BIGINT,
+    prompt_tokens INT,
+    completion_tokens INT,
+    total_tokens INT,
+    post_count INT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_topic_summary_versions_topic ON topic_summary_versions (discourse_id, topic_id, created_at DESC);
+
+-- Summaries generated before versioning keep their text but have no generation details
+INSERT INTO topic_summary_versions (summary_id, discourse_id, topic_id, summary_text, seed, created_at)
+SELECT summary_id, discourse_id, topic_id, summary_text, seed, created_at FROM topic_summaries;
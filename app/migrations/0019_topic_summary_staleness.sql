@@ -0,0 +1,3 @@
+-- Track how large a topic was when summarized, so growth can mark the summary stale
+ALTER TABLE topic_summaries ADD COLUMN post_count INT;
+ALTER TABLE topic_summaries ADD COLUMN stale BOOLEAN NOT NULL DEFAULT FALSE;
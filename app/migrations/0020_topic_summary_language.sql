@@ -0,0 +1,3 @@
+-- Dominant language of the topic when summarized, as an ISO 639-3 code
+ALTER TABLE topic_summaries ADD COLUMN language TEXT;
+ALTER TABLE topic_summary_versions ADD COLUMN language TEXT;
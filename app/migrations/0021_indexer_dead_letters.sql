@@ -0,0 +1,13 @@
+-- Topic pages the indexer gave up on after repeated fetch failures
+CREATE TABLE indexer_dead_letters (
+    dead_letter_id BIGSERIAL PRIMARY KEY,
+    discourse_id TEXT NOT NULL,
+    topic_id INT NOT NULL,
+    page INT NOT NULL,
+    attempts INT NOT NULL,
+    last_error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (discourse_id, topic_id, page)
+);
+
+CREATE INDEX idx_indexer_dead_letters_created_at ON indexer_dead_letters (created_at DESC);
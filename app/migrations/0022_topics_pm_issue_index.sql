@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS idx_topics_pm_issue ON topics (pm_issue) WHERE pm_issue IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Topics watched by users, new posts are delivered to the subscription's webhook
+CREATE TABLE topic_subscriptions (
+    subscription_id BIGSERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    webhook_url TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, discourse_id, topic_id)
+);
+
+CREATE INDEX idx_topic_subscriptions_topic ON topic_subscriptions (discourse_id, topic_id);
@@ -0,0 +1,12 @@
+-- EIP numbers, addresses and ENS names mentioned in posts, for cross-linking topics
+CREATE TABLE topic_entities (
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    post_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (discourse_id, post_id, kind, value)
+);
+
+CREATE INDEX idx_topic_entities_value ON topic_entities (kind, value);
@@ -0,0 +1,9 @@
+-- Topics that must not be served or indexed (spam, doxxing), their rows are kept for unquarantining
+CREATE TABLE quarantined_topics (
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    reason TEXT,
+    quarantined_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (discourse_id, topic_id)
+);
@@ -0,0 +1,8 @@
+-- Hash of the indexed content of each post, posts whose hash didn't change are not rewritten
+CREATE TABLE post_content_hashes (
+    discourse_id TEXT NOT NULL,
+    post_id INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (discourse_id, post_id)
+);
@@ -0,0 +1,8 @@
+-- Topics merged or moved into another topic on Discourse, their posts now live under moved_to
+CREATE TABLE topic_redirects (
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    moved_to INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (discourse_id, topic_id)
+);
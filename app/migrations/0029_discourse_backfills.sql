@@ -0,0 +1,9 @@
+-- Progress of the full fetch of an instance's topics, so a restart resumes where it left off
+CREATE TABLE discourse_backfills (
+    discourse_id TEXT PRIMARY KEY,
+    -- page of /latest.json to fetch next, NULL before the first page
+    next_url TEXT,
+    pages_fetched INTEGER NOT NULL DEFAULT 0,
+    completed_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
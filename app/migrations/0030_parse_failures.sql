@@ -0,0 +1,10 @@
+-- Discourse responses that failed to parse, kept whole (up to a size limit) to debug schema drift
+CREATE TABLE parse_failures (
+    failure_id BIGSERIAL PRIMARY KEY,
+    discourse_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    error TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    payload_bytes INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
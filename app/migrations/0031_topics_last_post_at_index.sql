@@ -0,0 +1,2 @@
+-- Range scans and ordering on topic activity (latest sort, trending)
+CREATE INDEX IF NOT EXISTS idx_topics_last_post_at ON topics (last_post_at);
@@ -0,0 +1,12 @@
+-- Summaries of consecutive windows of a topic's posts, the map step of map-reduce summaries
+-- A window is reused as long as the hash of its posts is unchanged
+CREATE TABLE topic_summary_chunks (
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    first_post_number INTEGER NOT NULL,
+    last_post_number INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    summary_text TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (discourse_id, topic_id, first_post_number)
+);
@@ -0,0 +1,74 @@
+-- When topics and posts were last written here, the cursor of /topics/changes
+-- Discourse's own timestamps can't be used for that: rows indexed late (backfills, retries,
+-- dead letter replays) carry timestamps older than what clients already synced past.
+CREATE TABLE topic_modifications (
+    discourse_id TEXT NOT NULL,
+    topic_id INTEGER NOT NULL,
+    modified_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+    PRIMARY KEY (discourse_id, topic_id)
+);
+CREATE INDEX idx_topic_modifications_modified_at ON topic_modifications (modified_at);
+
+CREATE TABLE post_modifications (
+    discourse_id TEXT NOT NULL,
+    post_id INTEGER NOT NULL,
+    modified_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+    PRIMARY KEY (discourse_id, post_id)
+);
+CREATE INDEX idx_post_modifications_modified_at ON post_modifications (modified_at);
+
+-- existing rows keep their place in the feed clients already follow
+INSERT INTO topic_modifications (discourse_id, topic_id, modified_at)
+SELECT discourse_id, topic_id, COALESCE(bumped_at, NOW()) FROM topics
+ON CONFLICT DO NOTHING;
+INSERT INTO post_modifications (discourse_id, post_id, modified_at)
+SELECT discourse_id, post_id, COALESCE(updated_at, NOW()) FROM posts
+ON CONFLICT DO NOTHING;
+
+CREATE FUNCTION touch_topic_modification() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO topic_modifications (discourse_id, topic_id) VALUES (NEW.discourse_id, NEW.topic_id)
+    ON CONFLICT (discourse_id, topic_id) DO UPDATE SET modified_at = clock_timestamp();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER topics_touch_modification AFTER INSERT OR UPDATE ON topics
+FOR EACH ROW EXECUTE FUNCTION touch_topic_modification();
+
+CREATE FUNCTION touch_post_modification() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO post_modifications (discourse_id, post_id) VALUES (NEW.discourse_id, NEW.post_id)
+    ON CONFLICT (discourse_id, post_id) DO UPDATE SET modified_at = clock_timestamp();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER posts_touch_modification AFTER INSERT OR UPDATE ON posts
+FOR EACH ROW EXECUTE FUNCTION touch_post_modification();
+
+-- quarantining and unquarantining change what clients should have, an unquarantined topic
+-- and its posts are delivered again
+CREATE FUNCTION touch_quarantine_modification() RETURNS trigger AS $$
+DECLARE
+    changed quarantined_topics%ROWTYPE;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+    ELSE
+        changed := NEW;
+    END IF;
+
+    INSERT INTO topic_modifications (discourse_id, topic_id) VALUES (changed.discourse_id, changed.topic_id)
+    ON CONFLICT (discourse_id, topic_id) DO UPDATE SET modified_at = clock_timestamp();
+
+    INSERT INTO post_modifications (discourse_id, post_id)
+    SELECT discourse_id, post_id FROM posts WHERE discourse_id = changed.discourse_id AND topic_id = changed.topic_id
+    ON CONFLICT (discourse_id, post_id) DO UPDATE SET modified_at = clock_timestamp();
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER quarantined_topics_touch_modification AFTER INSERT OR DELETE ON quarantined_topics
+FOR EACH ROW EXECUTE FUNCTION touch_quarantine_modification();
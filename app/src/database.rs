@@ -1,4 +1,11 @@
+use std::future::Future;
+
+use poem_openapi::{
+    Object,
+    types::{ParseFromJSON, ToJSON},
+};
 use sqlx::PgPool;
+use crate::models::topics::Topic;
 use crate::state::DatabaseConfig;
 
 pub struct Database {
@@ -18,3 +25,64 @@ impl Database {
         sqlx::migrate!("./migrations").run(&self.pool).await.unwrap();
     }
 }
+
+/// One page of a larger result set
+#[derive(Debug, Clone, Object)]
+#[oai(concretes(name = "PaginatedTopic", params(Topic)))]
+pub struct Paginated<T: ParseFromJSON + ToJSON> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+}
+
+impl<T: ParseFromJSON + ToJSON> Default for Paginated<T> {
+    fn default() -> Self {
+        Self::new(Vec::new(), 0, 0, 0)
+    }
+}
+
+impl<T: ParseFromJSON + ToJSON> Paginated<T> {
+    pub fn new(items: Vec<T>, total: i64, limit: i64, offset: i64) -> Self {
+        Self {
+            has_more: offset + (items.len() as i64) < total,
+            items,
+            total,
+            limit,
+            offset,
+        }
+    }
+
+    /// Run the count query and the page query concurrently
+    pub async fn fetch(
+        limit: i64,
+        offset: i64,
+        count: impl Future<Output = Result<i64, sqlx::Error>>,
+        items: impl Future<Output = Result<Vec<T>, sqlx::Error>>,
+    ) -> Result<Self, sqlx::Error> {
+        let (total, items) = futures::try_join!(count, items)?;
+
+        Ok(Self::new(items, total, limit, offset))
+    }
+}
+
+/// Offset of a page, pages start at 1
+pub fn page_offset(page: i64, size: i64) -> i64 {
+    (page - 1).max(0) * size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginated_has_more() {
+        assert!(Paginated::new(vec![1, 2], 5, 2, 0).has_more);
+        assert!(Paginated::new(vec![3, 4], 5, 2, 2).has_more);
+        assert!(!Paginated::new(vec![5], 5, 2, 4).has_more);
+        assert!(!Paginated::<i32>::new(vec![], 0, 2, 0).has_more);
+        assert_eq!(page_offset(3, 20), 40);
+        assert_eq!(page_offset(0, 20), 0);
+    }
+}
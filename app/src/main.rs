@@ -24,6 +24,17 @@ pub async fn main() -> Result<(), Error> {
         sleep(Duration::from_secs(5)).await;
         discourse_state.clone().discourse.start_all_indexers(discourse_state).await;
     });
+
+    let warmup = modules::workshop::warmup::SummaryWarmup::load();
+    if warmup.enabled {
+        let warmup_state = state.clone();
+        async_std::task::spawn(async move {
+            // let the indexers catch up first
+            sleep(Duration::from_secs(60)).await;
+            warmup.run(warmup_state).await;
+        });
+    }
+
     let server_handle = async_std::task::spawn(server::start_http(state));
 
     join!(server_handle, discourse_handle);
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query_as, query_scalar};
+
+use crate::state::AppState;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct AdminAuditLog {
+    pub audit_id: i64,
+    pub action: String,
+    pub parameters: serde_json::Value,
+    pub admin_identity: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AdminAuditLog {
+    /// Record an admin action
+    pub async fn record(
+        admin_identity: &str,
+        action: &str,
+        parameters: serde_json::Value,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as("INSERT INTO admin_audit_log (action, parameters, admin_identity) VALUES ($1, $2, $3) RETURNING *")
+            .bind(action)
+            .bind(parameters)
+            .bind(admin_identity)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    /// List audit entries, newest first
+    pub async fn list(page: i64, size: i64, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM admin_audit_log ORDER BY created_at DESC, audit_id DESC LIMIT $1 OFFSET $2")
+            .bind(size)
+            .bind((page - 1).max(0) * size)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// Total number of audit entries
+    pub async fn count(state: &AppState) -> Result<i64, sqlx::Error> {
+        query_scalar("SELECT COUNT(*) FROM admin_audit_log")
+            .fetch_one(&state.database.pool)
+            .await
+    }
+}
@@ -39,6 +39,17 @@ pub struct DiscourseTopicResponse {
     pub extra: serde_json::Value, // unknown
 }
 
+impl DiscourseTopicResponse {
+    /// Whether the topic is a private message rather than a public topic
+    pub fn is_private_message(&self) -> bool {
+        self.extra.get("archetype").and_then(|archetype| archetype.as_str()) == Some("private_message")
+    }
+
+    pub fn category_id(&self) -> Option<i64> {
+        self.extra.get("category_id").and_then(|category_id| category_id.as_i64())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiscourseTopicPostStream {
     pub posts: Vec<DiscourseTopicPost>,
@@ -80,8 +80,9 @@ pub struct DiscourseBadgeType {
 pub struct DiscourseDetailedUser {
     pub id: i32,
     pub username: String,
-    name: Option<String>,
-    avatar_template: Option<String>,
+    pub bio_raw: Option<String>,
+    pub name: Option<String>,
+    pub avatar_template: Option<String>,
     last_posted_at: Option<String>,
     last_seen_at: Option<String>,
     created_at: Option<String>,
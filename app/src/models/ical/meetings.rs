@@ -1,9 +1,10 @@
 use anyhow::Result;
 use icalendar::{Event, EventLike};
-use poem_openapi::{Object, Union};
+use poem_openapi::{Enum, Object, Union};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
+use url::Url;
 
 #[derive(Debug, Serialize, Deserialize, Union, Clone, PartialEq, Eq)]
 #[oai(discriminator_name = "type")]
@@ -11,6 +12,17 @@ pub enum Meeting {
     Zoom(ZoomMeetingData),
     Google(GoogleMeetingData),
     Youtube(YoutubeMeetingData),
+    Teams(TeamsMeetingData),
+    Other(OtherMeetingData),
+}
+
+#[derive(Debug, Serialize, Deserialize, Enum, Clone, Copy, PartialEq, Eq)]
+pub enum MeetingPlatform {
+    Zoom,
+    GoogleMeet,
+    Teams,
+    YouTube,
+    Other,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Object, PartialEq, Eq)]
@@ -30,6 +42,125 @@ pub struct YoutubeMeetingData {
     pub link: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Object, PartialEq, Eq)]
+pub struct TeamsMeetingData {
+    pub link: String,
+    pub meeting_id: Option<String>,
+    pub passcode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Object, PartialEq, Eq)]
+pub struct OtherMeetingData {
+    pub link: String,
+}
+
+impl Meeting {
+    /// Classify a meeting link by its host, extracting the meeting id and passcode where the url carries them
+    pub fn from_link(link: &str) -> Self {
+        let link = link.to_string();
+        let Ok(url) = Url::parse(&link) else {
+            return Meeting::Other(OtherMeetingData { link });
+        };
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+
+        match MeetingPlatform::from_host(&host) {
+            MeetingPlatform::Zoom => {
+                // https://ethereumfoundation.zoom.us/j/87569210985?pwd=...
+                let meeting_id = match segments.as_slice() {
+                    ["j" | "w" | "s", id, ..] => Some(id.to_string()),
+                    _ => None,
+                };
+                let passcode = query("pwd");
+                Meeting::Zoom(ZoomMeetingData {
+                    link,
+                    meeting_id,
+                    passcode,
+                })
+            }
+            MeetingPlatform::GoogleMeet => Meeting::Google(GoogleMeetingData { link }),
+            MeetingPlatform::YouTube => Meeting::Youtube(YoutubeMeetingData { link }),
+            MeetingPlatform::Teams => {
+                // https://teams.microsoft.com/meet/2334567890123?p=AbCdEf
+                // https://teams.microsoft.com/l/meetup-join/... carries no readable id
+                let meeting_id = match segments.as_slice() {
+                    ["meet", id, ..] => Some(id.to_string()),
+                    _ => None,
+                };
+                let passcode = query("p");
+                Meeting::Teams(TeamsMeetingData {
+                    link,
+                    meeting_id,
+                    passcode,
+                })
+            }
+            MeetingPlatform::Other => Meeting::Other(OtherMeetingData { link }),
+        }
+    }
+
+    pub fn platform(&self) -> MeetingPlatform {
+        match self {
+            Meeting::Zoom(_) => MeetingPlatform::Zoom,
+            Meeting::Google(_) => MeetingPlatform::GoogleMeet,
+            Meeting::Youtube(_) => MeetingPlatform::YouTube,
+            Meeting::Teams(_) => MeetingPlatform::Teams,
+            Meeting::Other(_) => MeetingPlatform::Other,
+        }
+    }
+
+    pub fn link(&self) -> &str {
+        match self {
+            Meeting::Zoom(data) => &data.link,
+            Meeting::Google(data) => &data.link,
+            Meeting::Youtube(data) => &data.link,
+            Meeting::Teams(data) => &data.link,
+            Meeting::Other(data) => &data.link,
+        }
+    }
+
+    pub fn meeting_id(&self) -> Option<&str> {
+        match self {
+            Meeting::Zoom(data) => data.meeting_id.as_deref(),
+            Meeting::Teams(data) => data.meeting_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn passcode(&self) -> Option<&str> {
+        match self {
+            Meeting::Zoom(data) => data.passcode.as_deref(),
+            Meeting::Teams(data) => data.passcode.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl MeetingPlatform {
+    pub fn from_host(host: &str) -> Self {
+        let is = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+
+        if is("zoom.us") {
+            MeetingPlatform::Zoom
+        } else if host == "meet.google.com" {
+            MeetingPlatform::GoogleMeet
+        } else if is("teams.microsoft.com") || is("teams.live.com") {
+            MeetingPlatform::Teams
+        } else if is("youtube.com") || host == "youtu.be" {
+            MeetingPlatform::YouTube
+        } else {
+            MeetingPlatform::Other
+        }
+    }
+}
+
 pub fn try_parse_meeting(event: &Event, body: &str) -> Result<(String, Vec<Meeting>)> {
     let location = event.get_location();
     let mut meetings = vec![];
@@ -60,6 +191,10 @@ pub fn try_parse_meeting(event: &Event, body: &str) -> Result<(String, Vec<Meeti
             let link = captures[0].to_string();
             meetings.push(Meeting::Google(GoogleMeetingData { link }));
         }
+
+        if let Some(captures) = teams_regex().captures(location) {
+            meetings.push(Meeting::from_link(&captures[0]));
+        }
     }
 
     // ACDbot-style descriptions: "Meeting: https://...zoom.us/j/<id>?pwd=<pwd>\n\nIssue: ..."
@@ -90,6 +225,10 @@ pub fn try_parse_meeting(event: &Event, body: &str) -> Result<(String, Vec<Meeti
         }));
     }
 
+    if let Some(captures) = teams_regex().captures(&new_body) {
+        meetings.push(Meeting::from_link(&captures[0]));
+    }
+
     // 'Join Zoom Meeting'
     // 'is inviting you to a scheduled Zoom meeting.'
     // 'is inviting you to a scheduled Zoom meeting.'
@@ -217,7 +356,7 @@ pub fn try_parse_meeting(event: &Event, body: &str) -> Result<(String, Vec<Meeti
     new_body = meeting_line_regex.replace_all(&new_body, "").to_string();
 
     let bare_link_line_regex = Regex::new(
-        r#"(?im)^[ \t]*https?://(?:(?:[\w-]+\.)?zoom\.us|meet\.google\.com|(?:www\.)?youtube\.com|youtu\.be)\S*[ \t]*$"#,
+        r#"(?im)^[ \t]*https?://(?:(?:[\w-]+\.)?zoom\.us|meet\.google\.com|(?:www\.)?youtube\.com|youtu\.be|teams\.(?:microsoft|live)\.com)\S*[ \t]*$"#,
     )
     .unwrap();
     new_body = bare_link_line_regex.replace_all(&new_body, "").to_string();
@@ -230,3 +369,67 @@ pub fn try_parse_meeting(event: &Event, body: &str) -> Result<(String, Vec<Meeti
         Ok((new_body, meetings))
     }
 }
+
+fn teams_regex() -> Regex {
+    Regex::new(r#"https://teams\.(?:microsoft|live)\.com/(?:l/meetup-join|meet)/[^\s<"]+"#).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_link() {
+        let meeting = Meeting::from_link(
+            "https://ethereumfoundation.zoom.us/j/87569210985?pwd=3Cv1hDh7If4cq9IMXvNln1CtqQ72MR.1",
+        );
+        assert_eq!(meeting.platform(), MeetingPlatform::Zoom);
+        assert_eq!(meeting.meeting_id(), Some("87569210985"));
+        assert_eq!(meeting.passcode(), Some("3Cv1hDh7If4cq9IMXvNln1CtqQ72MR.1"));
+
+        let meeting = Meeting::from_link("https://zoom.us/j/1234567890");
+        assert_eq!(meeting.platform(), MeetingPlatform::Zoom);
+        assert_eq!(meeting.meeting_id(), Some("1234567890"));
+        assert_eq!(meeting.passcode(), None);
+    }
+
+    #[test]
+    fn test_google_meet_link() {
+        let meeting = Meeting::from_link("https://meet.google.com/odf-tghm-ttu");
+        assert_eq!(meeting.platform(), MeetingPlatform::GoogleMeet);
+        assert_eq!(meeting.link(), "https://meet.google.com/odf-tghm-ttu");
+    }
+
+    #[test]
+    fn test_teams_link() {
+        let meeting =
+            Meeting::from_link("https://teams.microsoft.com/meet/2334567890123?p=AbCdEfGh");
+        assert_eq!(meeting.platform(), MeetingPlatform::Teams);
+        assert_eq!(meeting.meeting_id(), Some("2334567890123"));
+        assert_eq!(meeting.passcode(), Some("AbCdEfGh"));
+
+        let meeting = Meeting::from_link(
+            "https://teams.microsoft.com/l/meetup-join/19%3ameeting_abc%40thread.v2/0?context=%7b%7d",
+        );
+        assert_eq!(meeting.platform(), MeetingPlatform::Teams);
+        assert_eq!(meeting.meeting_id(), None);
+    }
+
+    #[test]
+    fn test_youtube_link() {
+        for link in [
+            "https://www.youtube.com/live/dQw4w9WgXcQ",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(Meeting::from_link(link).platform(), MeetingPlatform::YouTube);
+        }
+    }
+
+    #[test]
+    fn test_other_link() {
+        let meeting = Meeting::from_link("https://jitsi.example.org/acde");
+        assert_eq!(meeting.platform(), MeetingPlatform::Other);
+        assert_eq!(Meeting::from_link("not a url").platform(), MeetingPlatform::Other);
+    }
+}
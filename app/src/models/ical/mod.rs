@@ -1,4 +1,4 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use icalendar::{CalendarDateTime, Component, DatePerhapsTime, Event};
 use meetings::{try_parse_meeting, Meeting};
 use poem_openapi::{Enum, Object};
@@ -45,6 +45,9 @@ impl CalendarEvent {
         };
 
         if x.contains("RRULE") {
+            // Unfold continuation lines so long EXDATE lists are not cut off
+            let x = x.replace("\r\n ", "").replace("\n ", "");
+
             // Filter out DTSTART, RRULE, RDATE, EXDATE, EXRULE
             let raw_ruleset = x
                 .lines()
@@ -58,9 +61,15 @@ impl CalendarEvent {
                 .collect::<Vec<_>>();
 
             let ruleset: RRuleSet = raw_ruleset.join("\n").parse()?;
+            let exception_dates = ExceptionDates::from_lines(&raw_ruleset);
             let rendered_events = ruleset.all(100);
             for start in rendered_events.dates {
                 // println!("{:?}", event);
+                // cancelled occurrences, in case rrule did not apply the EXDATE itself
+                if exception_dates.contains(&start) {
+                    continue;
+                }
+
                 let start = start.with_timezone(&Utc);
 
                 // occurrences rescheduled by a RECURRENCE-ID override event
@@ -99,6 +108,47 @@ impl CalendarEvent {
     }
 }
 
+/// EXDATE values of an event, grouped by how they should be compared against an occurrence
+#[derive(Debug, Default)]
+struct ExceptionDates {
+    utc: HashSet<DateTime<Utc>>,
+    local: HashSet<NaiveDateTime>,
+    dates: HashSet<NaiveDate>,
+}
+
+impl ExceptionDates {
+    fn from_lines(lines: &[&str]) -> Self {
+        let mut exception_dates = Self::default();
+
+        for line in lines.iter().filter(|line| line.starts_with("EXDATE")) {
+            let Some((_, values)) = line.split_once(':') else {
+                continue;
+            };
+
+            for value in values.split(',').map(str::trim) {
+                if let Some(value) = value.strip_suffix('Z') {
+                    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+                        exception_dates.utc.insert(naive.and_utc());
+                    }
+                } else if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+                    // TZID or floating values are wall-clock times in the event's timezone
+                    exception_dates.local.insert(naive);
+                } else if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+                    exception_dates.dates.insert(date);
+                }
+            }
+        }
+
+        exception_dates
+    }
+
+    fn contains(&self, start: &DateTime<rrule::Tz>) -> bool {
+        self.utc.contains(&start.with_timezone(&Utc))
+            || self.local.contains(&start.naive_local())
+            || self.dates.contains(&start.date_naive())
+    }
+}
+
 pub fn recurrence_id(event: &Event) -> Option<DateTime<Utc>> {
     let value = event.property_value("RECURRENCE-ID")?;
     let naive = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
@@ -121,3 +171,48 @@ fn date_perhaps_time_to_datetime(date_perhaps_time: DatePerhapsTime) -> Option<D
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use icalendar::{Calendar, CalendarComponent};
+
+    use super::*;
+
+    fn parse_event(raw: &str) -> Event {
+        let calendar: Calendar = raw.parse().unwrap();
+        calendar
+            .components
+            .into_iter()
+            .find_map(|component| match component {
+                CalendarComponent::Event(event) => Some(event),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_exdate_excludes_occurrence() {
+        let event = parse_event(
+            "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:acde-call@ethereum.org\r\n\
+SUMMARY:All Core Devs - Execution\r\n\
+DTSTART:20250102T140000Z\r\n\
+RRULE:FREQ=WEEKLY;COUNT=4\r\n\
+EXDATE:20250109T140000Z\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n",
+        );
+
+        let events = CalendarEvent::from_event(event, &HashSet::new()).unwrap();
+        let starts: Vec<_> = events.iter().filter_map(|event| event.start).collect();
+
+        let excluded = NaiveDateTime::parse_from_str("20250109T140000", "%Y%m%dT%H%M%S")
+            .unwrap()
+            .and_utc();
+
+        assert_eq!(starts.len(), 3);
+        assert!(!starts.contains(&excluded));
+    }
+}
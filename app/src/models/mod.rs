@@ -1,6 +1,8 @@
+pub mod admin;
 pub mod discourse;
 pub mod ical;
 pub mod topics;
 pub mod pm;
 pub mod user;
+pub mod webhooks;
 pub mod workshop;
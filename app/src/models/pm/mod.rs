@@ -6,6 +6,8 @@ use serde::{Deserialize, Deserializer, Serialize};
 use tracing::debug;
 use std::collections::HashMap;
 
+use crate::models::topics::Topic;
+
 pub type PMData = HashMap<String, PMMeetingData>;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Union)]
@@ -45,6 +47,33 @@ impl PMMeetingData {
         }
     }
 
+    pub fn meeting_id(&self) -> Option<&str> {
+        match self {
+            PMMeetingData::Recurring(recurring) => recurring.meeting_id.as_deref(),
+            PMMeetingData::OneOff(one_off) => one_off.meeting_id.as_deref(),
+        }
+    }
+
+    pub fn call_series(&self) -> Option<&str> {
+        match self {
+            PMMeetingData::Recurring(recurring) => recurring.call_series.as_deref(),
+            PMMeetingData::OneOff(_) => None,
+        }
+    }
+
+    /// Discourse topic the meeting announced for the given issue
+    pub fn discourse_topic_id(&self, issue_number: u32) -> Option<String> {
+        match self {
+            PMMeetingData::Recurring(recurring) => recurring
+                .occurrences
+                .as_ref()?
+                .iter()
+                .find(|occurrence| occurrence.issue_number == Some(issue_number))
+                .and_then(|occurrence| occurrence.discourse_topic_id.clone()),
+            PMMeetingData::OneOff(one_off) => one_off.discourse_topic_id.clone(),
+        }
+    }
+
     pub fn issue_numbers(&self) -> Vec<u32> {
         match self {
             PMMeetingData::Recurring(recurring) => recurring
@@ -126,6 +155,46 @@ pub struct PMOneOffMeeting {
     pub extra: HashMap<String, serde_json::Value>,
 }
 
+/// A protocol call, correlated with its ethereum/pm issue and the forum topics linking to it
+#[derive(Debug, Serialize, Deserialize, Clone, Object)]
+pub struct PMMeeting {
+    pub title: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    /// UID of the calendar event, absent for calls only known from the ethereum/pm mapping
+    pub calendar_uid: Option<String>,
+    pub meeting_id: Option<String>,
+    pub call_series: Option<String>,
+    pub issue_number: Option<u32>,
+    pub issue_url: Option<String>,
+    pub discourse_topic_id: Option<String>,
+    pub topics: Vec<Topic>,
+}
+
+impl PMMeeting {
+    pub fn new(
+        title: Option<String>,
+        start: Option<DateTime<Utc>>,
+        calendar_uid: Option<String>,
+        meeting_data: Option<&PMMeetingData>,
+        issue_number: Option<u32>,
+    ) -> Self {
+        Self {
+            title,
+            start,
+            calendar_uid,
+            meeting_id: meeting_data.and_then(|data| data.meeting_id().map(String::from)),
+            call_series: meeting_data.and_then(|data| data.call_series().map(String::from)),
+            issue_number,
+            issue_url: issue_number
+                .map(|issue| format!("https://github.com/ethereum/pm/issues/{}", issue)),
+            discourse_topic_id: meeting_data
+                .zip(issue_number)
+                .and_then(|(data, issue)| data.discourse_topic_id(issue)),
+            topics: vec![],
+        }
+    }
+}
+
 // accept either a number or a string and convert to string
 fn deserialize_optional_string_from_string_or_number<'de, D>(
     deserializer: D,
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as};
+
+use crate::state::AppState;
+
+/// Checkpoint of the full fetch of an instance's topics
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct DiscourseBackfill {
+    pub discourse_id: String,
+    /// Page of `/latest.json` to fetch next
+    pub next_url: Option<String>,
+    pub pages_fetched: i32,
+    /// Set once the last page was fetched and its topics indexed
+    pub completed_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DiscourseBackfill {
+    pub async fn get(discourse_id: &str, state: &AppState) -> Result<Option<Self>, sqlx::Error> {
+        query_as("SELECT * FROM discourse_backfills WHERE discourse_id = $1")
+            .bind(discourse_id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+
+    /// Record that every page before `next_url` is done
+    pub async fn save_progress(
+        discourse_id: &str,
+        next_url: &str,
+        pages_fetched: i32,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            "INSERT INTO discourse_backfills (discourse_id, next_url, pages_fetched) VALUES ($1, $2, $3)
+            ON CONFLICT (discourse_id) DO UPDATE SET next_url = $2, pages_fetched = $3, updated_at = NOW()",
+        )
+        .bind(discourse_id)
+        .bind(next_url)
+        .bind(pages_fetched)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn complete(discourse_id: &str, pages_fetched: i32, state: &AppState) -> Result<(), sqlx::Error> {
+        query(
+            "INSERT INTO discourse_backfills (discourse_id, next_url, pages_fetched, completed_at) VALUES ($1, NULL, $2, NOW())
+            ON CONFLICT (discourse_id) DO UPDATE SET next_url = NULL, pages_fetched = $2, completed_at = NOW(), updated_at = NOW()",
+        )
+        .bind(discourse_id)
+        .bind(pages_fetched)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+}
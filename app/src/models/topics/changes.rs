@@ -0,0 +1,253 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query_as};
+
+use super::{Topic, post::Post};
+use crate::state::AppState;
+
+/// A topic clients should drop, currently only quarantined topics
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, Object)]
+pub struct TopicDeletion {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Everything that changed after a cursor, each list ordered oldest change first
+///
+/// The cursor is the time topics and posts were last written here, not their Discourse
+/// timestamps, so topics indexed late, quarantined or unquarantined show up as well. Each list
+/// is paged on its own by (time, instance, id), so any number of changes at the same instant
+/// page through. Continuing from `next_since` never skips a change; a change may be delivered
+/// twice, so clients should upsert.
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicChanges {
+    pub topics: Vec<Topic>,
+    /// Only included with `?include_posts=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub posts: Option<Vec<Post>>,
+    pub deletions: Vec<TopicDeletion>,
+    /// Opaque cursor for the next request
+    pub next_since: String,
+    /// More changes are waiting, request again with `next_since` right away
+    pub has_more: bool,
+}
+
+/// Where a list of changes was left off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Position {
+    at: DateTime<Utc>,
+    discourse_id: String,
+    id: i32,
+}
+
+impl Position {
+    /// Before every change at `at` or later
+    fn start(at: DateTime<Utc>) -> Self {
+        Self {
+            at,
+            discourse_id: String::new(),
+            id: i32::MIN,
+        }
+    }
+}
+
+/// Positions in the topic, post and deletion lists, handed out as `next_since`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangesCursor {
+    topics: Position,
+    posts: Position,
+    deletions: Position,
+}
+
+impl ChangesCursor {
+    /// A timestamp starts with the changes at that time, anything else must be a `next_since`
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+            let start = Position::start(at.with_timezone(&Utc));
+            return Some(Self {
+                topics: start.clone(),
+                posts: start.clone(),
+                deletions: start,
+            });
+        }
+
+        serde_json::from_slice(&hex::decode(value).ok()?).ok()
+    }
+
+    pub fn encode(&self) -> String {
+        hex::encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+#[derive(FromRow)]
+struct ModifiedTopic {
+    #[sqlx(flatten)]
+    topic: Topic,
+    modified_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct ModifiedPost {
+    #[sqlx(flatten)]
+    post: Post,
+    modified_at: DateTime<Utc>,
+}
+
+/// Changes younger than this are left for the next request, a write that started earlier may
+/// still commit with an older modification time
+const SETTLE_SECONDS: i64 = 5;
+
+impl TopicChanges {
+    pub async fn since(
+        cursor: ChangesCursor,
+        include_posts: bool,
+        limit: usize,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        // one extra row tells whether the list was cut
+        let fetch = limit as i64 + 1;
+        let until = Utc::now() - TimeDelta::seconds(SETTLE_SECONDS);
+
+        // the plain comparison on the time lets the modified_at index narrow the scan
+        let topics: Vec<ModifiedTopic> = query_as(
+            "SELECT t.*, m.modified_at FROM topics t
+            JOIN topic_modifications m ON m.discourse_id = t.discourse_id AND m.topic_id = t.topic_id
+            WHERE m.modified_at >= $1 AND (m.modified_at, t.discourse_id, t.topic_id) > ($1, $2, $3) AND m.modified_at <= $4
+            AND NOT EXISTS (SELECT 1 FROM quarantined_topics q WHERE q.discourse_id = t.discourse_id AND q.topic_id = t.topic_id)
+            ORDER BY m.modified_at ASC, t.discourse_id ASC, t.topic_id ASC LIMIT $5",
+        )
+        .bind(cursor.topics.at)
+        .bind(&cursor.topics.discourse_id)
+        .bind(cursor.topics.id)
+        .bind(until)
+        .bind(fetch)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        let posts: Option<Vec<ModifiedPost>> = if include_posts {
+            Some(
+                query_as(
+                    "SELECT p.*, m.modified_at FROM posts p
+                    JOIN post_modifications m ON m.discourse_id = p.discourse_id AND m.post_id = p.post_id
+                    WHERE m.modified_at >= $1 AND (m.modified_at, p.discourse_id, p.post_id) > ($1, $2, $3) AND m.modified_at <= $4
+                    AND NOT EXISTS (SELECT 1 FROM quarantined_topics q WHERE q.discourse_id = p.discourse_id AND q.topic_id = p.topic_id)
+                    ORDER BY m.modified_at ASC, p.discourse_id ASC, p.post_id ASC LIMIT $5",
+                )
+                .bind(cursor.posts.at)
+                .bind(&cursor.posts.discourse_id)
+                .bind(cursor.posts.id)
+                .bind(until)
+                .bind(fetch)
+                .fetch_all(&state.database.pool)
+                .await?,
+            )
+        } else {
+            None
+        };
+
+        let deletions: Vec<TopicDeletion> = query_as(
+            "SELECT discourse_id, topic_id, created_at AS deleted_at FROM quarantined_topics
+            WHERE created_at >= $1 AND (created_at, discourse_id, topic_id) > ($1, $2, $3) AND created_at <= $4
+            ORDER BY created_at ASC, discourse_id ASC, topic_id ASC LIMIT $5",
+        )
+        .bind(cursor.deletions.at)
+        .bind(&cursor.deletions.discourse_id)
+        .bind(cursor.deletions.id)
+        .bind(until)
+        .bind(fetch)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        let (topics, topics_next, topics_more) = page(topics, limit, cursor.topics, |topic| Position {
+            at: topic.modified_at,
+            discourse_id: topic.topic.discourse_id.clone(),
+            id: topic.topic.topic_id,
+        });
+        let (posts, posts_next, posts_more) = match posts {
+            Some(posts) => {
+                let (posts, next, more) = page(posts, limit, cursor.posts, |post| Position {
+                    at: post.modified_at,
+                    discourse_id: post.post.discourse_id.clone(),
+                    id: post.post.post_id,
+                });
+                (Some(posts), next, more)
+            }
+            None => (None, cursor.posts, false),
+        };
+        let (deletions, deletions_next, deletions_more) =
+            page(deletions, limit, cursor.deletions, |deletion| Position {
+                at: deletion.deleted_at,
+                discourse_id: deletion.discourse_id.clone(),
+                id: deletion.topic_id,
+            });
+
+        let next = ChangesCursor {
+            topics: topics_next,
+            posts: posts_next,
+            deletions: deletions_next,
+        };
+
+        Ok(Self {
+            topics: topics.into_iter().map(|topic| topic.topic).collect(),
+            posts: posts.map(|posts| posts.into_iter().map(|post| post.post).collect()),
+            deletions,
+            next_since: next.encode(),
+            has_more: topics_more || posts_more || deletions_more,
+        })
+    }
+}
+
+/// Keep at most `limit` changes, returns them with the position to continue from and whether
+/// more are waiting
+fn page<T>(
+    mut items: Vec<T>,
+    limit: usize,
+    from: Position,
+    position: impl Fn(&T) -> Position,
+) -> (Vec<T>, Position, bool) {
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+
+    let next = items.last().map(position).unwrap_or(from);
+    (items, next, has_more)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_page_continues_within_a_timestamp() {
+        let at = Utc.timestamp_opt(1_750_000_000, 0).unwrap();
+        let position = |id: &i32| Position {
+            at,
+            discourse_id: "magicians".to_string(),
+            id: *id,
+        };
+
+        // all rows share one time, the next page starts after the last id returned
+        let (items, next, more) = page(vec![1, 2, 3, 4], 3, Position::start(at), position);
+        assert_eq!(items, vec![1, 2, 3]);
+        assert_eq!(next, position(&3));
+        assert!(more);
+
+        let (items, next, more) = page(Vec::<i32>::new(), 3, position(&3), position);
+        assert!(items.is_empty());
+        assert_eq!(next, position(&3));
+        assert!(!more);
+    }
+
+    #[test]
+    fn test_cursor_parse() {
+        let at = Utc.timestamp_opt(1_750_000_000, 0).unwrap();
+        let cursor = ChangesCursor::parse("2025-06-15T15:06:40Z").unwrap();
+        assert_eq!(cursor.topics, Position::start(at));
+
+        assert_eq!(ChangesCursor::parse(&cursor.encode()), Some(cursor));
+        assert_eq!(ChangesCursor::parse("not a cursor"), None);
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as};
+
+use crate::state::AppState;
+
+/// Summary of a window of a topic's posts, combined into the topic summary of large topics
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TopicSummaryChunk {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub first_post_number: i32,
+    pub last_post_number: i32,
+    /// Hash of the posts in the window when it was summarized
+    pub content_hash: String,
+    pub summary_text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TopicSummaryChunk {
+    /// Stored chunks of a topic, in post order
+    pub async fn for_topic(
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topic_summary_chunks WHERE discourse_id = $1 AND topic_id = $2 ORDER BY first_post_number ASC",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    pub async fn save(
+        discourse_id: &str,
+        topic_id: i32,
+        first_post_number: i32,
+        last_post_number: i32,
+        content_hash: &str,
+        summary_text: &str,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            "INSERT INTO topic_summary_chunks (discourse_id, topic_id, first_post_number, last_post_number, content_hash, summary_text)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (discourse_id, topic_id, first_post_number) DO UPDATE
+            SET last_post_number = $4, content_hash = $5, summary_text = $6, created_at = NOW()",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(first_post_number)
+        .bind(last_post_number)
+        .bind(content_hash)
+        .bind(summary_text)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drop chunks that no longer start a window, e.g. after the window size changed
+    pub async fn retain(
+        discourse_id: &str,
+        topic_id: i32,
+        first_post_numbers: &[i32],
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            "DELETE FROM topic_summary_chunks WHERE discourse_id = $1 AND topic_id = $2 AND NOT (first_post_number = ANY($3))",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(first_post_numbers)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+}
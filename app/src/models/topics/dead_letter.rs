@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query_as, query_scalar};
+
+use crate::state::AppState;
+
+/// A topic page the indexer failed to fetch too many times
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct IndexerDeadLetter {
+    pub dead_letter_id: i64,
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub page: i32,
+    pub attempts: i32,
+    pub last_error: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IndexerDeadLetter {
+    /// Record a failed topic page, a page that fails again replaces its previous entry
+    pub async fn record(
+        discourse_id: &str,
+        topic_id: i32,
+        page: i32,
+        attempts: i32,
+        last_error: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO indexer_dead_letters (discourse_id, topic_id, page, attempts, last_error) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (discourse_id, topic_id, page) DO UPDATE SET attempts = $4, last_error = $5, created_at = NOW() RETURNING *",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(page)
+        .bind(attempts)
+        .bind(last_error)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// List dead letters, newest first
+    pub async fn list(page: i64, size: i64, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM indexer_dead_letters ORDER BY created_at DESC, dead_letter_id DESC LIMIT $1 OFFSET $2")
+            .bind(size)
+            .bind((page - 1).max(0) * size)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// Total number of dead letters
+    pub async fn count(state: &AppState) -> Result<i64, sqlx::Error> {
+        query_scalar("SELECT COUNT(*) FROM indexer_dead_letters")
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    pub async fn find(dead_letter_id: i64, state: &AppState) -> Result<Option<Self>, sqlx::Error> {
+        query_as("SELECT * FROM indexer_dead_letters WHERE dead_letter_id = $1")
+            .bind(dead_letter_id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+
+    pub async fn delete(dead_letter_id: i64, state: &AppState) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM indexer_dead_letters WHERE dead_letter_id = $1")
+            .bind(dead_letter_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use super::TopicSummaryVersion;
+
+/// Unchanged lines or sentences kept around each hunk
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum DiffGranularity {
+    #[default]
+    Line,
+    Sentence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum DiffChangeKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiffChange {
+    pub kind: DiffChangeKind,
+    /// The line or sentence, including its trailing whitespace
+    pub text: String,
+}
+
+/// A run of changes with some unchanged context, ranges are 0-based and count lines or sentences
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub changes: Vec<DiffChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct SummaryDiff {
+    pub from: TopicSummaryVersion,
+    pub to: TopicSummaryVersion,
+    pub granularity: DiffGranularity,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Empty when both versions are identical
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl SummaryDiff {
+    pub fn new(
+        from: TopicSummaryVersion,
+        to: TopicSummaryVersion,
+        granularity: DiffGranularity,
+    ) -> Self {
+        let hunks = diff_hunks(&from.summary_text, &to.summary_text, granularity);
+
+        let count = |kind| {
+            hunks
+                .iter()
+                .flat_map(|hunk| &hunk.changes)
+                .filter(|change| change.kind == kind)
+                .count()
+        };
+        let additions = count(DiffChangeKind::Insert);
+        let deletions = count(DiffChangeKind::Delete);
+
+        Self {
+            from,
+            to,
+            granularity,
+            additions,
+            deletions,
+            hunks,
+        }
+    }
+}
+
+fn diff_hunks(old: &str, new: &str, granularity: DiffGranularity) -> Vec<DiffHunk> {
+    let old = split(old, granularity);
+    let new = split(new, granularity);
+    let diff = TextDiff::configure().diff_slices(&old, &new);
+
+    diff.grouped_ops(CONTEXT)
+        .iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            let old_start = first.old_range().start;
+            let new_start = first.new_range().start;
+
+            let changes = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffChange {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => DiffChangeKind::Equal,
+                        ChangeTag::Insert => DiffChangeKind::Insert,
+                        ChangeTag::Delete => DiffChangeKind::Delete,
+                    },
+                    text: change.value().to_string(),
+                })
+                .collect();
+
+            Some(DiffHunk {
+                old_start,
+                old_len: last.old_range().end - old_start,
+                new_start,
+                new_len: last.new_range().end - new_start,
+                changes,
+            })
+        })
+        .collect()
+}
+
+/// Split into lines or sentences, each keeping its trailing whitespace so hunks join back up
+fn split(text: &str, granularity: DiffGranularity) -> Vec<&str> {
+    match granularity {
+        DiffGranularity::Line => text.split_inclusive('\n').collect(),
+        DiffGranularity::Sentence => {
+            let mut sentences = Vec::new();
+            let mut start = 0;
+            let mut chars = text.char_indices().peekable();
+
+            while let Some((i, c)) = chars.next() {
+                let boundary = match c {
+                    '\n' => true,
+                    '.' | '!' | '?' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+                    _ => false,
+                };
+                if !boundary {
+                    continue;
+                }
+
+                // take the whitespace after the sentence along with it
+                let mut end = i + c.len_utf8();
+                while let Some((j, next)) = chars.peek().copied() {
+                    if !next.is_whitespace() {
+                        break;
+                    }
+                    end = j + next.len_utf8();
+                    chars.next();
+                }
+
+                sentences.push(&text[start..end]);
+                start = end;
+            }
+
+            if start < text.len() {
+                sentences.push(&text[start..]);
+            }
+
+            sentences
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split("Gas limit goes up. Clients agree!\n\n- Ship it in v1.2", DiffGranularity::Sentence),
+            vec!["Gas limit goes up. ", "Clients agree!\n\n", "- Ship it in v1.2"]
+        );
+    }
+
+    #[test]
+    fn test_diff_hunks() {
+        let old = "Raise the gas limit. Clients agree. Ship in Fusaka.";
+        let new = "Raise the gas limit. Geth disagrees. Ship in Fusaka.";
+
+        let hunks = diff_hunks(old, new, DiffGranularity::Sentence);
+
+        assert_eq!(hunks.len(), 1);
+        let changes: Vec<_> = hunks[0]
+            .changes
+            .iter()
+            .map(|change| (change.kind, change.text.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (DiffChangeKind::Equal, "Raise the gas limit. "),
+                (DiffChangeKind::Delete, "Clients agree. "),
+                (DiffChangeKind::Insert, "Geth disagrees. "),
+                (DiffChangeKind::Equal, "Ship in Fusaka."),
+            ]
+        );
+        assert!(diff_hunks(old, old, DiffGranularity::Line).is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use sqlx::{query, query_as, query_scalar};
+
+use super::Topic;
+use crate::{modules::entities::Entity, state::AppState};
+
+pub struct TopicEntity;
+
+impl TopicEntity {
+    /// Replace the entities recorded for a post with the ones it mentions now
+    pub async fn replace_for_post(
+        discourse_id: &str,
+        topic_id: i32,
+        post_id: i32,
+        entities: &[Entity],
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        query("DELETE FROM topic_entities WHERE discourse_id = $1 AND post_id = $2")
+            .bind(discourse_id)
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if !entities.is_empty() {
+            let kinds: Vec<&str> = entities.iter().map(|entity| entity.kind).collect();
+            let values: Vec<&str> = entities.iter().map(|entity| entity.value.as_str()).collect();
+
+            query(
+                "INSERT INTO topic_entities (discourse_id, topic_id, post_id, kind, value)
+                SELECT $1, $2, $3, kind, value FROM UNNEST($4::text[], $5::text[]) AS e(kind, value)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(discourse_id)
+            .bind(topic_id)
+            .bind(post_id)
+            .bind(&kinds)
+            .bind(&values)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Topics with a post mentioning an entity, most recently active first
+    pub async fn find_topics(
+        kind: &str,
+        value: &str,
+        page: i64,
+        size: i64,
+        state: &AppState,
+    ) -> Result<Vec<Topic>, sqlx::Error> {
+        query_as(
+            "SELECT t.* FROM topics t WHERE EXISTS (
+                SELECT 1 FROM topic_entities e
+                WHERE e.discourse_id = t.discourse_id AND e.topic_id = t.topic_id AND e.kind = $1 AND e.value = $2
+            )
+            ORDER BY t.last_post_at DESC NULLS LAST LIMIT $3 OFFSET $4",
+        )
+        .bind(kind)
+        .bind(value)
+        .bind(size)
+        .bind((page - 1).max(0) * size)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Number of topics with a post mentioning an entity, quarantined topics left out
+    pub async fn count_topics(kind: &str, value: &str, state: &AppState) -> Result<i64, sqlx::Error> {
+        query_scalar(
+            "SELECT COUNT(DISTINCT (e.discourse_id, e.topic_id)) FROM topic_entities e
+            WHERE e.kind = $1 AND e.value = $2
+            AND NOT EXISTS (SELECT 1 FROM quarantined_topics q WHERE q.discourse_id = e.discourse_id AND q.topic_id = e.topic_id)",
+        )
+        .bind(kind)
+        .bind(value)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+}
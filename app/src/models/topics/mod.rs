@@ -1,20 +1,90 @@
+use async_openai::types::CompletionUsage;
 use chrono::{DateTime, Utc};
 use opentelemetry_http::HttpError;
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 use post::Post;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::FromRow, query, query_as};
+use sqlx::{prelude::FromRow, query, query_as, query_scalar};
 use tracing::info;
 
+use crate::database::Paginated;
+use crate::modules::notifications::events;
+use crate::modules::workshop::{SummaryStaleness, prompts::PromptConfig};
 use crate::state::AppState;
 
 use super::discourse::topic::DiscourseTopicResponse;
 
+pub mod backfill;
+pub mod changes;
+pub mod chunk;
+pub mod dead_letter;
+pub mod diff;
+pub mod entity;
+pub mod parse_failure;
 pub mod post;
+pub mod quarantine;
+pub mod redirect;
+pub mod subscription;
 
 const POSTS_PER_PAGE: usize = 100;
 
+/// Order of topic listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum TopicSort {
+    /// Latest activity first
+    #[default]
+    Latest,
+    /// Newest topics first
+    Created,
+    /// Most viewed first
+    Views,
+    /// Most posts first
+    Posts,
+}
+
+impl TopicSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            Self::Latest => "last_post_at DESC",
+            Self::Created => "created_at DESC",
+            Self::Views => "view_count DESC",
+            Self::Posts => "post_count DESC",
+        }
+    }
+}
+
+/// How far back topic activity counts towards trending
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum TrendingWindow {
+    #[oai(rename = "24h")]
+    #[serde(rename = "24h")]
+    Day,
+    #[oai(rename = "7d")]
+    #[serde(rename = "7d")]
+    Week,
+    #[default]
+    #[oai(rename = "14d")]
+    #[serde(rename = "14d")]
+    TwoWeeks,
+    #[oai(rename = "30d")]
+    #[serde(rename = "30d")]
+    Month,
+}
+
+impl TrendingWindow {
+    pub fn hours(self) -> i32 {
+        match self {
+            Self::Day => 24,
+            Self::Week => 7 * 24,
+            Self::TwoWeeks => 14 * 24,
+            Self::Month => 30 * 24,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Object, Clone)]
 pub struct Topic {
     pub discourse_id: String,
@@ -45,6 +115,265 @@ pub struct TopicSummary {
     pub based_on: DateTime<Utc>,
     pub summary_text: String,
     pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Number of posts in the topic when the summary was generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_count: Option<i32>,
+    /// The topic grew significantly since this summary was generated
+    pub stale: bool,
+    /// Detected language of the topic, ISO 639-3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// Whether a topic has a summary worth showing, without the summary itself
+#[derive(Debug, Serialize, Deserialize, FromRow, Object)]
+pub struct TopicSummaryStatus {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub has_summary: bool,
+    /// The summary doesn't cover the latest post or the topic outgrew it, reading it regenerates
+    pub stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<DateTime<Utc>>,
+}
+
+/// How a summary was generated, recorded in its version history
+#[derive(Debug, Clone)]
+pub struct SummaryGeneration {
+    pub model: Option<String>,
+    pub seed: Option<i64>,
+    pub usage: Option<CompletionUsage>,
+    pub post_count: i32,
+    pub language: Option<String>,
+}
+
+impl SummaryGeneration {
+    pub fn new(topic: &Topic, config: &PromptConfig, model: Option<String>, usage: Option<CompletionUsage>) -> Self {
+        Self {
+            model: model.or_else(|| Some(config.model.clone())),
+            seed: config.seed,
+            usage,
+            post_count: topic.post_count,
+            language: None,
+        }
+    }
+
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Object)]
+pub struct TopicSummaryVersion {
+    pub version_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_id: Option<i32>,
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub summary_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<i32>,
+    /// Number of posts in the topic when the summary was generated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_count: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl TopicSummaryVersion {
+    /// Past summaries of a topic, newest first
+    pub async fn list(
+        discourse_id: &str,
+        topic_id: i32,
+        page: i64,
+        size: i64,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topic_summary_versions WHERE discourse_id = $1 AND topic_id = $2 ORDER BY created_at DESC, version_id DESC LIMIT $3 OFFSET $4",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(size)
+        .bind((page - 1).max(0) * size)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    pub async fn get(
+        discourse_id: &str,
+        topic_id: i32,
+        version_id: i64,
+        state: &AppState,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topic_summary_versions WHERE discourse_id = $1 AND topic_id = $2 AND version_id = $3",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(version_id)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+}
+
+impl TopicSummary {
+    /// Most recent summary of a topic, without generating one
+    pub async fn latest(
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topic_summaries WHERE discourse_id = $1 AND topic_id = $2 ORDER BY based_on DESC LIMIT 1",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+
+    /// Summary status of each requested topic in one query, in request order
+    ///
+    /// Topics that don't exist or are quarantined are reported without a summary.
+    pub async fn statuses(
+        ids: &[(String, i32)],
+        state: &AppState,
+    ) -> Result<Vec<TopicSummaryStatus>, sqlx::Error> {
+        let (discourse_ids, topic_ids): (Vec<String>, Vec<i32>) = ids.iter().cloned().unzip();
+
+        // a summary is current when it is based on the topic's latest post, compared to the second
+        query_as(
+            "SELECT r.discourse_id, r.topic_id,
+                s.summary_id IS NOT NULL AS has_summary,
+                COALESCE(s.stale OR t.last_post_at IS NULL
+                    OR date_trunc('second', s.based_on) <> date_trunc('second', t.last_post_at), FALSE) AS stale,
+                s.created_at AS generated_at
+            FROM UNNEST($1::text[], $2::int[]) WITH ORDINALITY AS r(discourse_id, topic_id, position)
+            LEFT JOIN topics t ON t.discourse_id = r.discourse_id AND t.topic_id = r.topic_id
+            LEFT JOIN LATERAL (
+                SELECT * FROM topic_summaries s
+                WHERE s.discourse_id = t.discourse_id AND s.topic_id = t.topic_id
+                AND NOT EXISTS (SELECT 1 FROM quarantined_topics q WHERE q.discourse_id = t.discourse_id AND q.topic_id = t.topic_id)
+                ORDER BY s.based_on DESC LIMIT 1
+            ) s ON TRUE
+            ORDER BY r.position",
+        )
+        .bind(discourse_ids)
+        .bind(topic_ids)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Store a freshly generated summary as the current one and record it in the version history
+    pub async fn create(
+        discourse_id: &str,
+        topic_id: i32,
+        based_on: DateTime<Utc>,
+        summary_text: &str,
+        generation: &SummaryGeneration,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        let summary: Self = query_as(
+            "INSERT INTO topic_summaries (discourse_id, topic_id, based_on, summary_text, seed, post_count, language, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, NOW()) RETURNING *",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(based_on)
+        .bind(summary_text)
+        .bind(generation.seed)
+        .bind(generation.post_count)
+        .bind(&generation.language)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let usage = generation.usage.as_ref();
+        query(
+            "INSERT INTO topic_summary_versions (summary_id, discourse_id, topic_id, summary_text, model, seed, prompt_tokens, completion_tokens, total_tokens, post_count, language, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(summary.summary_id)
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(summary_text)
+        .bind(&generation.model)
+        .bind(generation.seed)
+        .bind(usage.map(|usage| usage.prompt_tokens as i32))
+        .bind(usage.map(|usage| usage.completion_tokens as i32))
+        .bind(usage.map(|usage| usage.total_tokens as i32))
+        .bind(generation.post_count)
+        .bind(&generation.language)
+        .bind(summary.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        events::dispatch(
+            events::SUMMARY_READY,
+            serde_json::json!({
+                "discourse_id": discourse_id,
+                "topic_id": topic_id,
+                "summary_id": summary.summary_id,
+                "summary_text": summary.summary_text,
+                "based_on": summary.based_on,
+            }),
+            state,
+        );
+
+        Ok(summary)
+    }
+
+    /// Mark the current summary of a topic stale when the topic outgrew it, returns whether it was marked
+    pub async fn mark_stale_if_outgrown(
+        topic: &Topic,
+        staleness: &SummaryStaleness,
+        state: &AppState,
+    ) -> Result<bool, sqlx::Error> {
+        let summary: Option<Self> = query_as(
+            "SELECT * FROM topic_summaries WHERE discourse_id = $1 AND topic_id = $2 ORDER BY based_on DESC LIMIT 1",
+        )
+        .bind(&topic.discourse_id)
+        .bind(topic.topic_id)
+        .fetch_optional(&state.database.pool)
+        .await?;
+
+        let Some(summary) = summary else {
+            return Ok(false);
+        };
+        let Some(summarized_post_count) = summary.post_count else {
+            return Ok(false);
+        };
+        if summary.stale || !staleness.is_stale(summarized_post_count, topic.post_count) {
+            return Ok(false);
+        }
+
+        query("UPDATE topic_summaries SET stale = TRUE WHERE summary_id = $1")
+            .bind(summary.summary_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        info!(
+            "Marked summary of topic {} on {} stale ({} -> {} posts)",
+            topic.topic_id, topic.discourse_id, summarized_post_count, topic.post_count
+        );
+
+        Ok(true)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -115,27 +444,69 @@ impl Topic {
         Ok(())
     }
 
-    pub async fn get_by_latest_post_at(state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
-        let topics = query_as!(
-            Self,
-            "SELECT * FROM topics ORDER BY last_post_at DESC LIMIT 20"
-        )
-        .fetch_all(&state.database.pool)
-        .await?;
+    /// Topics of every instance, or of one, in the given order, quarantined topics left out
+    pub async fn list(
+        discourse_id: Option<&str>,
+        sort: TopicSort,
+        min_posts: i32,
+        limit: i64,
+        offset: i64,
+        state: &AppState,
+    ) -> Result<Paginated<Self>, sqlx::Error> {
+        const FILTER: &str = "($1::text IS NULL OR discourse_id = $1) AND post_count >= $2
+            AND NOT EXISTS (SELECT 1 FROM quarantined_topics q WHERE q.discourse_id = t.discourse_id AND q.topic_id = t.topic_id)";
+
+        // the order comes from a fixed set of clauses, never from the request
+        let count_sql = format!("SELECT COUNT(*) FROM topics t WHERE {}", FILTER);
+        let sql = format!(
+            "SELECT * FROM topics t WHERE {} ORDER BY {}, topic_id DESC LIMIT $3 OFFSET $4",
+            FILTER,
+            sort.order_by()
+        );
 
-        Ok(topics)
+        Paginated::fetch(
+            limit,
+            offset,
+            query_scalar(&count_sql)
+                .bind(discourse_id)
+                .bind(min_posts)
+                .fetch_one(&state.database.pool),
+            query_as(&sql)
+                .bind(discourse_id)
+                .bind(min_posts)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(&state.database.pool),
+        )
+        .await
     }
 
-    // order by views and require that last_post_at is within 14 days
-    pub async fn get_by_trending(state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
-        let topics = query_as!(
-            Self,
-            "SELECT * FROM topics WHERE last_post_at > NOW() - INTERVAL '14 days' ORDER BY view_count DESC LIMIT 20"
+    /// Most viewed topics with activity within the window
+    pub async fn get_by_trending(
+        window: TrendingWindow,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topics WHERE last_post_at > NOW() - make_interval(hours => $1) ORDER BY view_count DESC LIMIT 20",
         )
+        .bind(window.hours())
         .fetch_all(&state.database.pool)
-        .await?;
+        .await
+    }
 
-        Ok(topics)
+    /// Trending topics of a single instance
+    pub async fn get_by_trending_in(
+        discourse_id: &str,
+        window: TrendingWindow,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM topics WHERE discourse_id = $1 AND last_post_at > NOW() - make_interval(hours => $2) ORDER BY view_count DESC LIMIT 20",
+        )
+        .bind(discourse_id)
+        .bind(window.hours())
+        .fetch_all(&state.database.pool)
+        .await
     }
 
     pub async fn get_by_topic_id(
@@ -154,6 +525,50 @@ impl Topic {
         Ok(topic)
     }
 
+    /// Topics linked to a protocol meeting issue on ethereum/pm, oldest first
+    pub async fn get_by_pm_issue(
+        issue_number: i32,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM topics WHERE pm_issue = $1 ORDER BY created_at ASC",
+        )
+        .bind(issue_number)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Topics linked to any of the given ethereum/pm issues
+    pub async fn get_by_pm_issues(
+        issue_numbers: &[i32],
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT * FROM topics WHERE pm_issue = ANY($1) ORDER BY created_at ASC",
+        )
+        .bind(issue_numbers)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Fetch many topics at once, topics that don't exist are left out of the result
+    pub async fn get_by_topic_ids(
+        ids: &[(String, i32)],
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let (discourse_ids, topic_ids): (Vec<String>, Vec<i32>) = ids.iter().cloned().unzip();
+
+        let topics = sqlx::query_as::<_, Self>(
+            "SELECT * FROM topics WHERE (discourse_id, topic_id) IN (SELECT * FROM UNNEST($1::text[], $2::int[]))",
+        )
+        .bind(discourse_ids)
+        .bind(topic_ids)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok(topics)
+    }
+
     pub async fn get_first_post(&self, state: &AppState) -> Result<Post, sqlx::Error> {
         let post = query_as!(
             Post,
@@ -171,14 +586,7 @@ impl Topic {
         topic_id: i32,
         state: &AppState,
     ) -> Result<TopicSummary, HttpError> {
-        let summary = query_as!(
-            TopicSummary,
-            "SELECT * FROM topic_summaries WHERE discourse_id = $1 AND topic_id = $2 ORDER BY based_on DESC LIMIT 1",
-            discourse_id,
-            topic_id
-        )
-        .fetch_optional(&state.database.pool)
-        .await?;
+        let summary = TopicSummary::latest(discourse_id, topic_id, state).await?;
 
         let topic = match Topic::get_by_topic_id(discourse_id, topic_id, state).await {
             Ok(topic) => topic,
@@ -239,11 +647,7 @@ impl Topic {
             match ongoing_prompt.await_completion().await {
                 Ok(summary_text) => {
                     // The summary should already be saved by the background task, but let's check
-                    if let Ok(existing_summary) = query_as!(
-                        TopicSummary,
-                        "SELECT * FROM topic_summaries WHERE topic_id = $1 ORDER BY based_on DESC LIMIT 1",
-                        topic_id
-                    ).fetch_optional(&state.database.pool).await {
+                    if let Ok(existing_summary) = TopicSummary::latest(discourse_id, topic_id, state).await {
                         if let Some(summary) = existing_summary {
                             return Ok(summary);
                         }
@@ -258,15 +662,24 @@ impl Topic {
                     let based_on_datetime =
                         DateTime::from_timestamp(based_on as i64, 0).unwrap_or_else(|| Utc::now());
 
-                    let summary = query_as!(
-                        TopicSummary,
-                        "INSERT INTO topic_summaries (discourse_id, topic_id, based_on, summary_text, created_at) VALUES ($1, $2, $3, $4, NOW()) RETURNING *",
+                    let generation = SummaryGeneration::new(
+                        topic,
+                        &PromptConfig::summary(),
+                        ongoing_prompt.get_model_used().await,
+                        ongoing_prompt.get_usage_data().await,
+                    )
+                    .with_language(
+                        crate::modules::workshop::WorkshopService::detect_topic_language(topic, state)
+                            .await,
+                    );
+                    let summary = TopicSummary::create(
                         discourse_id,
                         topic_id,
                         based_on_datetime,
-                        summary_text
+                        &summary_text,
+                        &generation,
+                        state,
                     )
-                    .fetch_one(&state.database.pool)
                     .await?;
 
                     return Ok(summary);
@@ -282,9 +695,13 @@ impl Topic {
         }
 
         // No ongoing prompt or it failed, use direct generation (non-streaming)
-        let summary =
+        let (summary, usage) =
             crate::modules::workshop::WorkshopService::create_workshop_summary(topic, &state)
                 .await?;
+        let generation = SummaryGeneration::new(topic, &PromptConfig::summary(), None, usage)
+            .with_language(
+                crate::modules::workshop::WorkshopService::detect_topic_language(topic, state).await,
+            );
 
         let based_on = topic
             .last_post_at
@@ -294,16 +711,15 @@ impl Topic {
         let based_on_datetime =
             DateTime::from_timestamp(based_on as i64, 0).unwrap_or_else(|| Utc::now());
 
-        let summary = query_as!(
-            TopicSummary,
-            "INSERT INTO topic_summaries (discourse_id, topic_id, based_on, summary_text, created_at) VALUES ($1, $2, $3, $4, NOW()) RETURNING *",
+        let summary = TopicSummary::create(
             discourse_id,
             topic_id,
             based_on_datetime,
-            summary
-            )
-            .fetch_one(&state.database.pool)
-            .await?;
+            &summary,
+            &generation,
+            state,
+        )
+        .await?;
 
         info!(
             "Created new summary for topic_id: {} with summary_id: {}",
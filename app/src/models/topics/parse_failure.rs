@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as, query_scalar};
+
+use crate::{modules::discourse::client::ParseFailure, state::AppState};
+
+/// Largest payload stored, longer ones are cut
+const MAX_PAYLOAD_BYTES: usize = 256 * 1024;
+/// Failures kept, older ones are dropped as new ones come in
+const MAX_FAILURES: i64 = 200;
+/// Payload characters included when listing failures
+const PREVIEW_CHARS: i32 = 200;
+
+/// A Discourse response that failed to parse
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct ResponseParseFailure {
+    pub failure_id: i64,
+    pub discourse_id: String,
+    pub url: String,
+    pub error: String,
+    /// The response body, cut at 256 KiB, only a preview when listing
+    pub payload: String,
+    /// Size of the whole response body
+    pub payload_bytes: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ResponseParseFailure {
+    /// Store a failed response, dropping the oldest failures beyond the limit
+    pub async fn record(
+        discourse_id: &str,
+        failure: &ParseFailure,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        let mut end = failure.body.len().min(MAX_PAYLOAD_BYTES);
+        while !failure.body.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        query(
+            "INSERT INTO parse_failures (discourse_id, url, error, payload, payload_bytes) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(discourse_id)
+        .bind(&failure.url)
+        .bind(failure.error.to_string())
+        .bind(&failure.body[..end])
+        .bind(i32::try_from(failure.body.len()).unwrap_or(i32::MAX))
+        .execute(&state.database.pool)
+        .await?;
+
+        query(
+            "DELETE FROM parse_failures WHERE failure_id NOT IN (
+                SELECT failure_id FROM parse_failures ORDER BY failure_id DESC LIMIT $1
+            )",
+        )
+        .bind(MAX_FAILURES)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List failures newest first, with a preview of each payload
+    pub async fn list(page: i64, size: i64, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT failure_id, discourse_id, url, error, LEFT(payload, $3) AS payload, payload_bytes, created_at
+            FROM parse_failures ORDER BY failure_id DESC LIMIT $1 OFFSET $2",
+        )
+        .bind(size)
+        .bind((page - 1).max(0) * size)
+        .bind(PREVIEW_CHARS)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    pub async fn count(state: &AppState) -> Result<i64, sqlx::Error> {
+        query_scalar("SELECT COUNT(*) FROM parse_failures")
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    pub async fn find(failure_id: i64, state: &AppState) -> Result<Option<Self>, sqlx::Error> {
+        query_as("SELECT * FROM parse_failures WHERE failure_id = $1")
+            .bind(failure_id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+}
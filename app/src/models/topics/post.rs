@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::{prelude::FromRow, query, query_as, query_scalar};
 
 use crate::{
+    database::{Paginated, page_offset},
     models::{discourse::topic::DiscourseTopicPost, topics::POSTS_PER_PAGE},
     state::AppState,
 };
@@ -79,47 +83,209 @@ impl Post {
         }
     }
 
-    pub async fn upsert(&self, state: &AppState) -> Result<(), sqlx::Error> {
-        query!("INSERT INTO posts (discourse_id, post_id, topic_id, user_id, post_number, updated_at, cooked, post_url, extra) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (discourse_id, post_id) DO UPDATE SET discourse_id=$1, post_id=$2, topic_id=$3, user_id=$4, post_number=$5, updated_at = $6, cooked = $7, post_url = $8, extra = $9",
-            self.discourse_id,
-            self.post_id,
-            self.topic_id,
-            self.user_id,
-            self.post_number,
-            self.updated_at,
-            self.cooked,
-            self.post_url,
-            self.extra,
+    /// Hash of everything that ends up in the post's row and search document
+    ///
+    /// Volatile Discourse fields in `extra` (reads, scores) are left out on purpose, a refetch
+    /// that only changed those is not worth a write.
+    pub fn content_hash(&self) -> String {
+        let username = self
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("username"))
+            .and_then(|username| username.as_str());
+
+        let mut hasher = Sha256::new();
+        for field in [
+            self.topic_id.to_string(),
+            self.user_id.to_string(),
+            self.post_number.to_string(),
+            self.updated_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+            self.cooked.clone().unwrap_or_default(),
+            self.post_url.clone().unwrap_or_default(),
+            username.unwrap_or_default().to_string(),
+        ] {
+            hasher.update(field.as_bytes());
+            // separator, so moving text between fields changes the hash
+            hasher.update([0]);
+        }
+
+        hex::encode(hasher.finalize())
+    }
+
+    /// Stored content hashes of the given posts, posts indexed before hashing have none
+    pub async fn content_hashes(
+        discourse_id: &str,
+        post_ids: &[i32],
+        state: &AppState,
+    ) -> Result<HashMap<i32, String>, sqlx::Error> {
+        let hashes: Vec<(i32, String)> = query_as(
+            "SELECT post_id, content_hash FROM post_content_hashes WHERE discourse_id = $1 AND post_id = ANY($2)",
         )
+        .bind(discourse_id)
+        .bind(post_ids)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok(hashes.into_iter().collect())
+    }
+
+    /// Remember the content hashes of posts that were written
+    pub async fn store_content_hashes(
+        discourse_id: &str,
+        hashes: &[(i32, String)],
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+
+        let (post_ids, hashes): (Vec<i32>, Vec<&str>) = hashes
+            .iter()
+            .map(|(post_id, hash)| (*post_id, hash.as_str()))
+            .unzip();
+
+        query(
+            "INSERT INTO post_content_hashes (discourse_id, post_id, content_hash)
+            SELECT $1, post_id, content_hash FROM UNNEST($2::int[], $3::text[]) AS h(post_id, content_hash)
+            ON CONFLICT (discourse_id, post_id) DO UPDATE SET content_hash = EXCLUDED.content_hash, updated_at = NOW()",
+        )
+        .bind(discourse_id)
+        .bind(&post_ids)
+        .bind(&hashes)
         .execute(&state.database.pool)
         .await?;
+
         Ok(())
     }
 
+    /// Insert or update the post, returns whether the post is new
+    pub async fn upsert(&self, state: &AppState) -> Result<bool, sqlx::Error> {
+        // xmax is only set on rows that were updated by the conflict clause
+        query_scalar("INSERT INTO posts (discourse_id, post_id, topic_id, user_id, post_number, updated_at, cooked, post_url, extra) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (discourse_id, post_id) DO UPDATE SET discourse_id=$1, post_id=$2, topic_id=$3, user_id=$4, post_number=$5, updated_at = $6, cooked = $7, post_url = $8, extra = $9 RETURNING (xmax = 0)")
+            .bind(&self.discourse_id)
+            .bind(self.post_id)
+            .bind(self.topic_id)
+            .bind(self.user_id)
+            .bind(self.post_number)
+            .bind(self.updated_at)
+            .bind(&self.cooked)
+            .bind(&self.post_url)
+            .bind(&self.extra)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
     pub async fn find_by_topic_id(
         discourse_id: &str,
         topic_id: i32,
         page: i32,
         size: Option<i32>,
         state: &AppState,
-    ) -> Result<(Vec<Self>, bool), sqlx::Error> {
-        let size = size.unwrap_or(POSTS_PER_PAGE as i32);
-        let offset = (page - 1) * size;
-        let posts = query_as!(
-            Self,
-            "SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2 ORDER BY post_number ASC LIMIT $3 OFFSET $4",
-            discourse_id,
-            topic_id,
-            (size + 1) as i64,
-            offset as i64
+    ) -> Result<Paginated<Self>, sqlx::Error> {
+        let size = size.unwrap_or(POSTS_PER_PAGE as i32) as i64;
+        let offset = page_offset(page as i64, size);
+
+        Paginated::fetch(
+            size,
+            offset,
+            async {
+                Self::count_by_topic_id(discourse_id, topic_id, state)
+                    .await
+                    .map(i64::from)
+            },
+            query_as!(
+                Self,
+                "SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2 ORDER BY post_number ASC LIMIT $3 OFFSET $4",
+                discourse_id,
+                topic_id,
+                size,
+                offset
+            )
+            .fetch_all(&state.database.pool),
         )
-        .fetch_all(&state.database.pool)
-        .await?;
+        .await
+    }
 
-        let has_more = posts.len() == size as usize + 1;
-        let posts = posts.into_iter().take(size as usize).collect();
+    /// A page of a topic's posts counted from its first or its newest post
+    pub async fn find_page_from_end(
+        discourse_id: &str,
+        topic_id: i32,
+        newest_first: bool,
+        limit: i64,
+        offset: i64,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let sql = if newest_first {
+            "SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2 ORDER BY post_number DESC LIMIT $3 OFFSET $4"
+        } else {
+            "SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2 ORDER BY post_number ASC LIMIT $3 OFFSET $4"
+        };
+
+        query_as(sql)
+            .bind(discourse_id)
+            .bind(topic_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&state.database.pool)
+            .await
+    }
 
-        Ok((posts, has_more))
+    /// Case-insensitive substring search over the text of a topic's posts, used when Meilisearch is
+    /// unavailable
+    ///
+    /// Matches the text like `excerpt::plaintext` shows it, tags stripped, entities decoded and
+    /// whitespace collapsed, so markup and link targets never match.
+    pub async fn search_in_topic(
+        discourse_id: &str,
+        topic_id: i32,
+        query: &str,
+        limit: i64,
+        offset: i64,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        query_as(
+            r#"SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2
+            AND regexp_replace(
+                replace(replace(replace(replace(replace(replace(
+                    regexp_replace(cooked, '<[^>]*>', ' ', 'g'),
+                '&nbsp;', ' '), '&lt;', '<'), '&gt;', '>'), '&quot;', '"'), '&#39;', ''''), '&amp;', '&'),
+                '\s+', ' ', 'g'
+            ) ILIKE $3
+            ORDER BY post_number ASC LIMIT $4 OFFSET $5"#,
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(pattern)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Find a post by its position in a topic, as used in Discourse links
+    pub async fn get_by_post_number(
+        discourse_id: &str,
+        topic_id: i32,
+        post_number: i32,
+        state: &AppState,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2 AND post_number = $3",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(post_number)
+        .fetch_optional(&state.database.pool)
+        .await
     }
 
     pub async fn count_by_topic_id(
@@ -138,3 +304,35 @@ impl Post {
         Ok(count.unwrap_or_default() as i32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_ignores_volatile_extra() {
+        let post = Post {
+            discourse_id: "magicians".to_string(),
+            post_id: 1,
+            topic_id: 2,
+            user_id: 3,
+            post_number: 1,
+            updated_at: None,
+            created_at: None,
+            cooked: Some("<p>gm</p>".to_string()),
+            post_url: None,
+            extra: Some(serde_json::json!({ "username": "vitalik", "reads": 10 })),
+        };
+        let mut refetched = Post {
+            extra: Some(serde_json::json!({ "username": "vitalik", "reads": 11 })),
+            ..post
+        };
+        let hash = refetched.content_hash();
+
+        refetched.extra = Some(serde_json::json!({ "username": "vitalik", "reads": 12 }));
+        assert_eq!(refetched.content_hash(), hash);
+
+        refetched.cooked = Some("<p>gn</p>".to_string());
+        assert_ne!(refetched.content_hash(), hash);
+    }
+}
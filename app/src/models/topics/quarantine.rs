@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as, query_scalar};
+
+use crate::state::AppState;
+
+/// A topic hidden from every endpoint and from search, and skipped by the indexer
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct QuarantinedTopic {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub reason: Option<String>,
+    /// Admin that quarantined the topic
+    pub quarantined_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl QuarantinedTopic {
+    /// Quarantine a topic, quarantining it again replaces the reason
+    pub async fn create(
+        discourse_id: &str,
+        topic_id: i32,
+        reason: Option<&str>,
+        quarantined_by: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO quarantined_topics (discourse_id, topic_id, reason, quarantined_by) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (discourse_id, topic_id) DO UPDATE SET reason = EXCLUDED.reason, quarantined_by = EXCLUDED.quarantined_by
+            RETURNING *",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(reason)
+        .bind(quarantined_by)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// Lift a quarantine, returns whether the topic was quarantined
+    pub async fn delete(discourse_id: &str, topic_id: i32, state: &AppState) -> Result<bool, sqlx::Error> {
+        let result = query("DELETE FROM quarantined_topics WHERE discourse_id = $1 AND topic_id = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM quarantined_topics ORDER BY created_at DESC")
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    pub async fn is_quarantined(
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<bool, sqlx::Error> {
+        query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM quarantined_topics WHERE discourse_id = $1 AND topic_id = $2)",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// `(discourse_id, topic_id)` of every quarantined topic, for filtering listings
+    pub async fn keys(state: &AppState) -> Result<HashSet<(String, i32)>, sqlx::Error> {
+        let keys: Vec<(String, i32)> = query_as("SELECT discourse_id, topic_id FROM quarantined_topics")
+            .fetch_all(&state.database.pool)
+            .await?;
+
+        Ok(keys.into_iter().collect())
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_scalar};
+
+use crate::state::AppState;
+
+/// A topic Discourse merged or moved into another one, it now redirects there
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct TopicRedirect {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub moved_to: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TopicRedirect {
+    /// Record a move and hand everything stored under the old topic to the new one
+    ///
+    /// Posts, entities and subscriptions are reassigned and the old topic row is dropped, the
+    /// next index of `moved_to` brings the reassigned posts up to date.
+    pub async fn record(
+        discourse_id: &str,
+        topic_id: i32,
+        moved_to: i32,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        query(
+            "INSERT INTO topic_redirects (discourse_id, topic_id, moved_to) VALUES ($1, $2, $3)
+            ON CONFLICT (discourse_id, topic_id) DO UPDATE SET moved_to = EXCLUDED.moved_to, created_at = NOW()",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(moved_to)
+        .execute(&mut *tx)
+        .await?;
+
+        // topics that were moved into the old topic earlier follow along
+        query("UPDATE topic_redirects SET moved_to = $3 WHERE discourse_id = $1 AND moved_to = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .bind(moved_to)
+            .execute(&mut *tx)
+            .await?;
+
+        for table in ["posts", "topic_entities"] {
+            query(&format!(
+                "UPDATE {} SET topic_id = $3 WHERE discourse_id = $1 AND topic_id = $2",
+                table
+            ))
+            .bind(discourse_id)
+            .bind(topic_id)
+            .bind(moved_to)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // a user already watching the new topic keeps that subscription
+        query(
+            "UPDATE topic_subscriptions s SET topic_id = $3 WHERE discourse_id = $1 AND topic_id = $2
+            AND NOT EXISTS (
+                SELECT 1 FROM topic_subscriptions o
+                WHERE o.user_id = s.user_id AND o.discourse_id = $1 AND o.topic_id = $3
+            )",
+        )
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(moved_to)
+        .execute(&mut *tx)
+        .await?;
+        query("DELETE FROM topic_subscriptions WHERE discourse_id = $1 AND topic_id = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .execute(&mut *tx)
+            .await?;
+
+        query("DELETE FROM topics WHERE discourse_id = $1 AND topic_id = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    /// Topic a moved topic now lives at
+    pub async fn moved_to(
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        query_scalar("SELECT moved_to FROM topic_redirects WHERE discourse_id = $1 AND topic_id = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as};
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// A topic watched by a user, new posts are delivered to `webhook_url`
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct TopicSubscription {
+    pub subscription_id: i64,
+    pub user_id: Uuid,
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub webhook_url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TopicSubscription {
+    /// Subscribe to a topic, subscribing again replaces the webhook URL
+    pub async fn upsert(
+        user_id: Uuid,
+        discourse_id: &str,
+        topic_id: i32,
+        webhook_url: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO topic_subscriptions (user_id, discourse_id, topic_id, webhook_url) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, discourse_id, topic_id) DO UPDATE SET webhook_url = EXCLUDED.webhook_url RETURNING *",
+        )
+        .bind(user_id)
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(webhook_url)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// Remove a subscription, returns whether one existed
+    pub async fn delete(
+        user_id: Uuid,
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<bool, sqlx::Error> {
+        let result = query(
+            "DELETE FROM topic_subscriptions WHERE user_id = $1 AND discourse_id = $2 AND topic_id = $3",
+        )
+        .bind(user_id)
+        .bind(discourse_id)
+        .bind(topic_id)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Subscriptions of a user, newest first
+    pub async fn find_by_user(user_id: Uuid, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM topic_subscriptions WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// Everyone watching a topic
+    pub async fn find_by_topic(
+        discourse_id: &str,
+        topic_id: i32,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM topic_subscriptions WHERE discourse_id = $1 AND topic_id = $2")
+            .bind(discourse_id)
+            .bind(topic_id)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+}
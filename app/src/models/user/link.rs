@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as};
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct UserDiscourseLink {
+    pub user_id: Uuid,
+    pub discourse_id: String,
+    pub discourse_user_id: i32,
+    pub username: String,
+    #[oai(skip)]
+    #[serde(skip)]
+    pub verification_code: String,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserDiscourseLink {
+    pub fn is_verified(&self) -> bool {
+        self.verified_at.is_some()
+    }
+
+    /// Start (or restart) linking a Discourse account, replacing any previous link on that instance
+    pub async fn create_pending(
+        user_id: Uuid,
+        discourse_id: &str,
+        discourse_user_id: i32,
+        username: &str,
+        verification_code: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO user_discourse_links (user_id, discourse_id, discourse_user_id, username, verification_code)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, discourse_id) DO UPDATE SET
+                discourse_user_id = EXCLUDED.discourse_user_id,
+                username = EXCLUDED.username,
+                verification_code = EXCLUDED.verification_code,
+                verified_at = NULL,
+                created_at = NOW()
+            RETURNING *",
+        )
+        .bind(user_id)
+        .bind(discourse_id)
+        .bind(discourse_user_id)
+        .bind(username)
+        .bind(verification_code)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    pub async fn find(
+        user_id: Uuid,
+        discourse_id: &str,
+        state: &AppState,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        query_as("SELECT * FROM user_discourse_links WHERE user_id = $1 AND discourse_id = $2")
+            .bind(user_id)
+            .bind(discourse_id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+
+    /// Verified links of a user
+    pub async fn find_verified_by_user(user_id: Uuid, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM user_discourse_links WHERE user_id = $1 AND verified_at IS NOT NULL ORDER BY discourse_id")
+            .bind(user_id)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// Whether another user already holds a verified link to this Discourse account
+    pub async fn is_claimed_by_other(
+        user_id: Uuid,
+        discourse_id: &str,
+        discourse_user_id: i32,
+        state: &AppState,
+    ) -> Result<bool, sqlx::Error> {
+        let claimed: Option<(Uuid,)> = query_as(
+            "SELECT user_id FROM user_discourse_links
+            WHERE discourse_id = $1 AND discourse_user_id = $2 AND verified_at IS NOT NULL AND user_id != $3",
+        )
+        .bind(discourse_id)
+        .bind(discourse_user_id)
+        .bind(user_id)
+        .fetch_optional(&state.database.pool)
+        .await?;
+
+        Ok(claimed.is_some())
+    }
+
+    /// Mark the link as verified, fails with a unique violation if the account was claimed meanwhile
+    pub async fn mark_verified(&self, state: &AppState) -> Result<Self, sqlx::Error> {
+        query_as(
+            "UPDATE user_discourse_links SET verified_at = NOW()
+            WHERE user_id = $1 AND discourse_id = $2 RETURNING *",
+        )
+        .bind(self.user_id)
+        .bind(&self.discourse_id)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// Remove the link, returns whether a link existed
+    pub async fn delete(user_id: Uuid, discourse_id: &str, state: &AppState) -> Result<bool, sqlx::Error> {
+        let result = query("DELETE FROM user_discourse_links WHERE user_id = $1 AND discourse_id = $2")
+            .bind(user_id)
+            .bind(discourse_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
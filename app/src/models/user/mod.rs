@@ -1,3 +1,5 @@
+pub mod link;
+
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query, query_as, query_scalar};
+
+use crate::state::AppState;
+
+/// An integration receiving signed platform events
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct WebhookEndpoint {
+    pub endpoint_id: i64,
+    pub url: String,
+    /// HMAC-SHA256 key for the `X-Forum-Signature` header, only shown when the endpoint is created
+    #[oai(skip)]
+    #[serde(skip)]
+    pub secret: String,
+    /// Subscribed events, `*` for all of them
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookEndpoint {
+    pub async fn create(
+        url: &str,
+        secret: &str,
+        events: &[String],
+        description: Option<&str>,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO webhook_endpoints (url, secret, events, description) VALUES ($1, $2, $3, $4) RETURNING *",
+        )
+        .bind(url)
+        .bind(secret)
+        .bind(events)
+        .bind(description)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    pub async fn list(state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM webhook_endpoints ORDER BY endpoint_id")
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// Active endpoints subscribed to an event
+    pub async fn find_subscribed(event: &str, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM webhook_endpoints WHERE active AND ($1 = ANY(events) OR '*' = ANY(events))",
+        )
+        .bind(event)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Remove an endpoint and its delivery log, returns whether it existed
+    pub async fn delete(endpoint_id: i64, state: &AppState) -> Result<bool, sqlx::Error> {
+        let result = query("DELETE FROM webhook_endpoints WHERE endpoint_id = $1")
+            .bind(endpoint_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// An event sent (or being sent) to an endpoint
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct WebhookDelivery {
+    pub delivery_id: i64,
+    pub endpoint_id: i64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    /// `pending`, `delivered` or `failed`
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookDelivery {
+    pub async fn create(
+        endpoint_id: i64,
+        event: &str,
+        payload: &serde_json::Value,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as("INSERT INTO webhook_deliveries (endpoint_id, event, payload) VALUES ($1, $2, $3) RETURNING *")
+            .bind(endpoint_id)
+            .bind(event)
+            .bind(payload)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    /// Record the outcome of an attempt, `status` stays `pending` while retries are left
+    pub async fn record_attempt(
+        delivery_id: i64,
+        status: &str,
+        response_status: Option<i32>,
+        error: Option<&str>,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            "UPDATE webhook_deliveries SET status = $2, attempts = attempts + 1, response_status = $3, last_error = $4,
+            delivered_at = CASE WHEN $2 = 'delivered' THEN NOW() ELSE delivered_at END
+            WHERE delivery_id = $1",
+        )
+        .bind(delivery_id)
+        .bind(status)
+        .bind(response_status)
+        .bind(error)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deliveries to an endpoint, newest first
+    pub async fn list_by_endpoint(
+        endpoint_id: i64,
+        page: i64,
+        size: i64,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        query_as(
+            "SELECT * FROM webhook_deliveries WHERE endpoint_id = $1 ORDER BY created_at DESC, delivery_id DESC LIMIT $2 OFFSET $3",
+        )
+        .bind(endpoint_id)
+        .bind(size)
+        .bind((page - 1).max(0) * size)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    pub async fn count_by_endpoint(endpoint_id: i64, state: &AppState) -> Result<i64, sqlx::Error> {
+        query_scalar("SELECT COUNT(*) FROM webhook_deliveries WHERE endpoint_id = $1")
+            .bind(endpoint_id)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, query_as};
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// A forum topic attached to a chat, `context` holds the condensed topic handed to the model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, Object)]
+pub struct WorkshopChatContext {
+    pub chat_id: Uuid,
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub context: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WorkshopChatContext {
+    /// Attach a topic to a chat, re-attaching refreshes the stored context
+    pub async fn attach(
+        chat_id: Uuid,
+        discourse_id: &str,
+        topic_id: i32,
+        context: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        query_as(
+            "INSERT INTO workshop_chat_contexts (chat_id, discourse_id, topic_id, context)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (chat_id, discourse_id, topic_id) DO UPDATE SET context = EXCLUDED.context, created_at = NOW()
+            RETURNING *",
+        )
+        .bind(chat_id)
+        .bind(discourse_id)
+        .bind(topic_id)
+        .bind(context)
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    pub async fn find_by_chat_id(chat_id: Uuid, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        query_as("SELECT * FROM workshop_chat_contexts WHERE chat_id = $1 ORDER BY created_at ASC")
+            .bind(chat_id)
+            .fetch_all(&state.database.pool)
+            .await
+    }
+}
@@ -1,4 +1,5 @@
 pub mod chat;
+pub mod context;
 pub mod message;
 pub mod snapshot;
 pub mod usage;
@@ -1,5 +1,7 @@
+use chrono::{DateTime, Utc};
 use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, query_as};
 use uuid::Uuid;
 
 use crate::state::AppState;
@@ -13,7 +15,7 @@ pub struct UserUsageStats {
     pub message_count: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, Object)]
 pub struct ModelUsage {
     pub model_name: String,
     pub prompt_tokens: i64,
@@ -23,7 +25,7 @@ pub struct ModelUsage {
     pub message_count: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, Object)]
 pub struct DailyUsage {
     pub date: String,
     pub prompt_tokens: i64,
@@ -44,6 +46,27 @@ pub struct UserUsageOverview {
     pub message_count: i64,
 }
 
+/// Tokens spent by a user against their budget
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct UserTokenAllowance {
+    pub used_tokens: i64,
+    pub message_count: i64,
+    /// None when no budget is configured
+    pub token_budget: Option<i64>,
+    pub remaining_tokens: Option<i64>,
+}
+
+impl UserTokenAllowance {
+    pub fn new(stats: &UserUsageStats, token_budget: Option<i64>) -> Self {
+        Self {
+            used_tokens: stats.total_tokens,
+            message_count: stats.message_count,
+            token_budget,
+            remaining_tokens: token_budget.map(|budget| (budget - stats.total_tokens).max(0)),
+        }
+    }
+}
+
 /// Get user's overall usage statistics
 pub async fn get_user_usage_stats(
     user_id: Uuid,
@@ -150,6 +173,70 @@ pub async fn get_user_daily_usage(
         .collect())
 }
 
+/// Get user's usage per day within `[from, to)`, oldest first
+pub async fn get_user_daily_usage_in_range(
+    user_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    state: &AppState,
+) -> Result<Vec<DailyUsage>, sqlx::Error> {
+    query_as(
+        r#"SELECT
+                TO_CHAR(DATE(wm.created_at), 'YYYY-MM-DD') as date,
+                COALESCE(SUM(prompt_tokens), 0)::BIGINT as prompt_tokens,
+                COALESCE(SUM(completion_tokens), 0)::BIGINT as completion_tokens,
+                COALESCE(SUM(total_tokens), 0)::BIGINT as total_tokens,
+                COALESCE(SUM(reasoning_tokens), 0)::BIGINT as reasoning_tokens,
+                COUNT(*) as message_count
+            FROM workshop_messages wm
+            INNER JOIN workshop_chats wc ON wm.chat_id = wc.chat_id
+            WHERE wc.user_id = $1
+                AND wm.sender_role = 'assistant'
+                AND wm.total_tokens IS NOT NULL
+                AND wm.created_at >= $2
+                AND wm.created_at < $3
+            GROUP BY DATE(wm.created_at)
+            ORDER BY DATE(wm.created_at) ASC"#,
+    )
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&state.database.pool)
+    .await
+}
+
+/// Get user's usage by model within `[from, to)`
+pub async fn get_user_usage_by_model_in_range(
+    user_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    state: &AppState,
+) -> Result<Vec<ModelUsage>, sqlx::Error> {
+    query_as(
+        r#"SELECT
+                COALESCE(model_used, 'unknown') as model_name,
+                COALESCE(SUM(prompt_tokens), 0)::BIGINT as prompt_tokens,
+                COALESCE(SUM(completion_tokens), 0)::BIGINT as completion_tokens,
+                COALESCE(SUM(total_tokens), 0)::BIGINT as total_tokens,
+                COALESCE(SUM(reasoning_tokens), 0)::BIGINT as reasoning_tokens,
+                COUNT(*) as message_count
+            FROM workshop_messages wm
+            INNER JOIN workshop_chats wc ON wm.chat_id = wc.chat_id
+            WHERE wc.user_id = $1
+                AND wm.sender_role = 'assistant'
+                AND wm.total_tokens IS NOT NULL
+                AND wm.created_at >= $2
+                AND wm.created_at < $3
+            GROUP BY model_used
+            ORDER BY total_tokens DESC"#,
+    )
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&state.database.pool)
+    .await
+}
+
 /// Get all users' usage overview (admin only)
 pub async fn get_all_users_usage_overview(
     state: &AppState,
@@ -0,0 +1,69 @@
+//! Code in posts
+//!
+//! Stripping cooked HTML for search flattens code into the surrounding prose. The text of code
+//! elements is also collected into the search document's `code` field, so identifiers and
+//! snippets can be searched (or weighted) on their own. The elements are read from
+//! `SEARCH_CODE_TAGS`, a comma separated list of tag names, `code` by default which covers
+//! both inline code and fenced blocks (`<pre><code>`).
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::excerpt;
+
+const DEFAULT_TAGS: &str = "code";
+
+static TAGS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    let tags = std::env::var("SEARCH_CODE_TAGS").unwrap_or_else(|_| DEFAULT_TAGS.to_string());
+    patterns(&tags)
+});
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// A pattern per tag name, names that aren't plain words are ignored
+fn patterns(tags: &str) -> Vec<Regex> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|tag| Regex::new(&format!(r"(?is)<{tag}\b[^>]*>(.*?)</{tag}>")).unwrap())
+        .collect()
+}
+
+/// Text of the code elements in cooked post HTML, one element per line
+pub fn extract(cooked: &str) -> Option<String> {
+    extract_with(cooked, &TAGS)
+}
+
+fn extract_with(cooked: &str, patterns: &[Regex]) -> Option<String> {
+    let code: Vec<String> = patterns
+        .iter()
+        .flat_map(|pattern| pattern.captures_iter(cooked))
+        .map(|captures| excerpt::decode_entities(&TAG.replace_all(&captures[1], "")))
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    (!code.is_empty()).then(|| code.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let cooked = r#"<p>Call <code>eth_getLogs</code> with</p>
+            <pre><code class="lang-rust">let x = a &lt; b &amp;&amp; <span class="hljs-keyword">true</span>;
+</code></pre><p>or press <kbd>Ctrl</kbd></p>"#;
+
+        assert_eq!(
+            extract_with(cooked, &patterns("code")).as_deref(),
+            Some("eth_getLogs\nlet x = a < b && true;")
+        );
+        assert_eq!(
+            extract_with(cooked, &patterns("kbd, <script>")).as_deref(),
+            Some("Ctrl")
+        );
+        assert_eq!(extract_with("<p>No code here</p>", &patterns("code")), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Outbound requests to Discourse instances
+//!
+//! Topic and listing fetches go through a [`DiscourseClient`], so tests can serve canned
+//! responses instead of depending on a live forum.
+//!
+//! During development `DISCOURSE_DEV_CACHE_DIR` keeps every JSON response on disk and serves
+//! it from there on later runs, so parsing changes can be iterated on without refetching.
+//! Release builds ignore it.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+/// The client for this environment, the on-disk cache when enabled in a debug build
+pub fn from_env() -> Arc<dyn DiscourseClient> {
+    let Ok(dir) = std::env::var("DISCOURSE_DEV_CACHE_DIR") else {
+        return Arc::new(HttpDiscourseClient);
+    };
+
+    if !cfg!(debug_assertions) {
+        warn!("DISCOURSE_DEV_CACHE_DIR is ignored in release builds");
+        return Arc::new(HttpDiscourseClient);
+    }
+
+    info!("Serving Discourse responses from the dev cache in {}", dir);
+    Arc::new(DiskCacheDiscourseClient {
+        dir: PathBuf::from(dir),
+        inner: HttpDiscourseClient,
+    })
+}
+
+#[async_trait]
+pub trait DiscourseClient: Send + Sync {
+    /// Body of a successful GET request to `url`, redirects followed, other statuses are errors
+    async fn get(&self, url: &str) -> Result<String>;
+}
+
+/// The client used outside of tests
+#[derive(Debug, Clone, Default)]
+pub struct HttpDiscourseClient;
+
+#[async_trait]
+impl DiscourseClient for HttpDiscourseClient {
+    async fn get(&self, url: &str) -> Result<String> {
+        // error pages and rate limits never reach parsing, they aren't parse failures
+        let response = reqwest::get(url).await?.error_for_status()?;
+        Ok(response.text().await?)
+    }
+}
+
+/// Serves responses from disk, fetching and storing the ones it doesn't have yet
+///
+/// Responses never expire, delete the directory (or a file in it) to refetch.
+#[derive(Debug, Clone)]
+pub struct DiskCacheDiscourseClient {
+    dir: PathBuf,
+    inner: HttpDiscourseClient,
+}
+
+impl DiskCacheDiscourseClient {
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(Sha256::digest(url.as_bytes()))))
+    }
+}
+
+#[async_trait]
+impl DiscourseClient for DiskCacheDiscourseClient {
+    async fn get(&self, url: &str) -> Result<String> {
+        let path = self.path(url);
+        if let Ok(body) = async_std::fs::read_to_string(&path).await {
+            return Ok(body);
+        }
+
+        let body = self.inner.get(url).await?;
+
+        // error pages and rate limit responses are not worth keeping
+        if serde_json::from_str::<serde_json::Value>(&body).is_ok() {
+            async_std::fs::create_dir_all(&self.dir).await?;
+            async_std::fs::write(&path, &body)
+                .await
+                .with_context(|| format!("Writing {} to the dev cache", url))?;
+        }
+
+        Ok(body)
+    }
+}
+
+/// A response that didn't have the expected shape, kept whole to debug schema drift
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub url: String,
+    pub error: serde_json::Error,
+    pub body: String,
+}
+
+impl std::fmt::Display for ParseFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unexpected response from {}: {}", self.url, self.error)
+    }
+}
+
+impl std::error::Error for ParseFailure {}
+
+/// GET and parse a JSON response, errors include the requested URL
+///
+/// A body that doesn't parse fails with a [`ParseFailure`] carrying the body.
+pub async fn get_json<T: DeserializeOwned>(client: &dyn DiscourseClient, url: &str) -> Result<T> {
+    let body = client.get(url).await.with_context(|| format!("GET {}", url))?;
+    serde_json::from_str(&body).map_err(|error| {
+        ParseFailure {
+            url: url.to_string(),
+            error,
+            body,
+        }
+        .into()
+    })
+}
+
+/// Serves fixed bodies by URL, any other URL fails like an unreachable host
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct CannedDiscourseClient {
+    responses: std::collections::HashMap<String, String>,
+}
+
+#[cfg(test)]
+impl CannedDiscourseClient {
+    pub fn with(mut self, url: &str, body: serde_json::Value) -> Self {
+        self.responses.insert(url.to_string(), body.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl DiscourseClient for CannedDiscourseClient {
+    async fn get(&self, url: &str) -> Result<String> {
+        self.responses
+            .get(url)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No canned response for {}", url))
+    }
+}
@@ -3,47 +3,144 @@ use std::{collections::{HashMap, HashSet}, sync::Arc, time::Duration};
 use crate::{
     models::{
         discourse::{
-            latest::DiscourseLatestResponse,
+            latest::{DiscourseLatestResponse, DiscourseLatestTopic},
             topic::DiscourseTopicResponse,
             user::{DiscourseUserProfile, DiscourseUserSummaryResponse},
         },
-        topics::{post::Post, Topic},
+        topics::{
+            backfill::DiscourseBackfill, dead_letter::IndexerDeadLetter, entity::TopicEntity, post::Post,
+            parse_failure::ResponseParseFailure, quarantine::QuarantinedTopic, redirect::TopicRedirect, Topic,
+            TopicSummary,
+        },
+    },
+    modules::{
+        code,
+        entities::{self, Entity},
+        meili,
+        metrics::{CacheMetrics, IndexerMetrics, Metrics},
+        schedule::Schedule,
+        notifications::{self, NewPost, events},
     },
     state::AppState,
 };
 use anyhow::{Error, Result};
 use async_std::{
-    channel::{Receiver, Sender},
-    sync::Mutex,
+    channel::{Receiver, Sender, TrySendError},
+    sync::{Mutex, RwLock},
 };
-use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{StreamExt, TryStreamExt, stream};
 use moka::future::Cache;
 use poem_openapi::types::{ParseFromJSON, ToJSON, Type};
 use serde::{Deserialize, Serialize};
 use strip_tags::strip_tags;
-use tracing::{error, info};
-
-pub async fn fetch_latest_topics(discourse_url: &str) -> Result<DiscourseLatestResponse, Error> {
-    let url = format!("{}/latest.json", discourse_url);
-    let response = reqwest::get(url).await?;
-    let body = response.text().await?;
-    let parsed: DiscourseLatestResponse = serde_json::from_str(&body)?;
-    Ok(parsed)
+use tracing::{error, info, warn};
+use url::Url;
+
+use client::{DiscourseClient, ParseFailure, get_json};
+
+pub mod client;
+
+pub async fn fetch_latest_topics(
+    client: &dyn DiscourseClient,
+    discourse_url: &str,
+) -> Result<DiscourseLatestResponse, Error> {
+    get_json(client, &format!("{}/latest.json", discourse_url)).await
+}
+
+/// Latest topics, following `more_topics_url` for up to `max_pages` pages
+pub async fn fetch_latest_topics_paginated(
+    client: &dyn DiscourseClient,
+    discourse_url: &str,
+    max_pages: u32,
+    page_delay: Duration,
+) -> Result<Vec<DiscourseLatestTopic>, Error> {
+    let mut url = format!("{}/latest.json", discourse_url);
+    let mut topics = Vec::new();
+
+    for page in 0..max_pages.max(1) {
+        if page > 0 {
+            async_std::task::sleep(page_delay).await;
+        }
+
+        let page: DiscourseLatestResponse = get_json(client, &url).await?;
+        let next = match page.topic_list.more_topics_url.as_deref() {
+            Some(more_topics_url) => {
+                let next = latest_page_url(discourse_url, more_topics_url);
+                if next.is_none() {
+                    warn!("Ignoring more_topics_url {:?} from {}", more_topics_url, url);
+                }
+                next
+            }
+            None => None,
+        };
+        topics.extend(page.topic_list.topics);
+
+        match next {
+            Some(next) => url = next,
+            None => break,
+        }
+    }
+
+    Ok(topics)
 }
 
-pub async fn fetch_topic(discourse_url: &str, topic_id: TopicId, page: u32) -> Result<DiscourseTopicResponse, Error> {
+/// JSON URL of the next latest page, `more_topics_url` differs between Discourse versions:
+/// a path (`/latest?page=1`), a path with `.json`, an absolute URL or only a query (`?page=1`)
+fn latest_page_url(discourse_url: &str, more_topics_url: &str) -> Option<String> {
+    let more_topics_url = more_topics_url.trim();
+    if more_topics_url.is_empty() {
+        return None;
+    }
+
+    // trailing slash so relative paths resolve below a subfolder install
+    let base = Url::parse(&format!("{}/", discourse_url.trim_end_matches('/'))).ok()?;
+    let mut url = if more_topics_url.starts_with('?') {
+        base.join(&format!("latest{}", more_topics_url)).ok()?
+    } else {
+        base.join(more_topics_url).ok()?
+    };
+
+    // never follow pagination off the instance
+    if url.origin() != base.origin() {
+        return None;
+    }
+
+    if !url.path().ends_with(".json") {
+        let path = format!("{}.json", url.path().trim_end_matches('/'));
+        url.set_path(&path);
+    }
+
+    Some(url.to_string())
+}
+
+pub async fn fetch_topic(
+    client: &dyn DiscourseClient,
+    discourse_url: &str,
+    topic_id: TopicId,
+    page: u32,
+) -> Result<DiscourseTopicResponse, Error> {
     let url = format!(
         "{}/t/{}.json?page={}",
         discourse_url, topic_id, page
     );
-    let response = reqwest::get(url).await?;
-    let body = response.text().await?;
-    let parsed: DiscourseTopicResponse = serde_json::from_str(&body)?;
-    Ok(parsed)
+    get_json(client, &url).await
 }
 
 pub type TopicId = i32;
 
+/// Ids of every post in the topic, as listed in the post stream of the first page
+fn post_stream_ids(topic: &DiscourseTopicResponse) -> Option<Vec<i32>> {
+    topic
+        .post_stream
+        .extra
+        .get("stream")?
+        .as_array()?
+        .iter()
+        .map(|id| id.as_i64().map(|id| id as i32))
+        .collect()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ForumSearchDocument {
     pub entity_type: String,
@@ -67,6 +164,12 @@ pub struct ForumSearchDocument {
     pub pm_issue: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cooked: Option<String>,
+    /// Text of code elements in a post, also part of `cooked`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Entities mentioned in a post as `kind:value`, e.g. `eip:1559`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<String>>,
     pub entity_id: String,
 }
 
@@ -74,6 +177,8 @@ pub struct ForumSearchDocument {
 pub struct DiscourseTopicIndexRequest {
     pub topic_id: TopicId,
     pub page: u32,
+    /// Failed fetches so far
+    pub attempt: u32,
 }
 
 #[derive(Debug, Clone, poem_openapi::Union)]
@@ -87,34 +192,130 @@ pub struct DiscourseConfig {
     pub discourse_id: String,
     pub url: String,
     pub scrape_interval: String,
+    /// Posts per page of the topic endpoint, Discourse reports it as `chunk_size` in `/t/<id>.json`
+    pub posts_per_page: u32,
+    /// Index private messages and topics in `private_categories`, off by default
+    pub include_private: bool,
+    pub private_categories: Vec<i64>,
+    /// Pages of `/latest.json` checked for new activity on every scrape
+    pub latest_pages: u32,
+    /// Pages fetched per topic at most, guards against responses that never run out of posts
+    pub max_topic_pages: u32,
+    /// Topic pages fetched at the same time
+    pub fetch_concurrency: usize,
+    /// Pause between pages of `/latest.json`
+    pub latest_page_delay: Duration,
+    /// Fetch every topic of the instance once, resuming after restarts until it completes
+    pub backfill: bool,
+}
+
+impl DiscourseConfig {
+    /// Page to fetch after `page` of a topic, if any
+    pub fn next_page(&self, topic: &DiscourseTopicResponse, page: u32) -> Option<u32> {
+        if topic.post_stream.posts.is_empty() {
+            return None;
+        }
+
+        if page >= self.max_topic_pages {
+            warn!(
+                "Topic {:?} on {} reached the limit of {} pages, not fetching further",
+                topic.id, self.discourse_id, self.max_topic_pages
+            );
+            return None;
+        }
+
+        Some(page + 1)
+    }
+
+    /// Whether a topic must be neither stored nor indexed
+    pub fn excludes(&self, topic: &DiscourseTopicResponse) -> bool {
+        if self.include_private {
+            return false;
+        }
+
+        topic.is_private_message()
+            || topic
+                .category_id()
+                .is_some_and(|category_id| self.private_categories.contains(&category_id))
+    }
 }
 
+/// Health of a single indexer
+#[derive(Debug, Clone, Serialize, Deserialize, poem_openapi::Object)]
+pub struct IndexerStatus {
+    pub discourse_id: String,
+    pub url: String,
+    /// Last time a request to the instance succeeded
+    pub last_successful_fetch: Option<DateTime<Utc>>,
+    pub queue_depth: u64,
+    /// Nothing was fetched successfully for several scrape intervals
+    pub stale: bool,
+}
+
+/// Profile requests to an instance in flight at once while fetching a batch of users
+const USER_FETCH_CONCURRENCY: usize = 8;
+
 /// Main service that manages multiple discourse instances
 pub struct DiscourseService {
     indexers: HashMap<String, Arc<DiscourseIndexer>>,
     user_profile_cache: Cache<String, LResult<DiscourseUserProfile>>,
     user_summary_cache: Cache<String, LResult<DiscourseUserSummaryResponse>>,
+    cache_metrics: CacheMetrics,
+}
+
+/// Size and lifetime of a cache, read from `<PREFIX>_CAPACITY` and `<PREFIX>_TTL_SECS`
+#[derive(Debug, Clone, Copy)]
+struct CacheConfig {
+    capacity: u64,
+    ttl: Duration,
+}
+
+impl CacheConfig {
+    fn load(prefix: &str, capacity: u64, ttl: Duration) -> Self {
+        let var = |name: &str| {
+            std::env::var(format!("{}_{}", prefix, name))
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        Self {
+            capacity: var("CAPACITY").unwrap_or(capacity),
+            ttl: var("TTL_SECS").map(Duration::from_secs).unwrap_or(ttl),
+        }
+    }
+
+    fn build<V: Clone + Send + Sync + 'static>(&self) -> Cache<String, V> {
+        Cache::builder()
+            .max_capacity(self.capacity)
+            .time_to_live(self.ttl)
+            .build()
+    }
 }
 
 impl DiscourseService {
-    pub fn new(configs: Vec<DiscourseConfig>) -> Self {
+    pub fn new(configs: Vec<DiscourseConfig>, metrics: &Metrics) -> Self {
         let mut indexers = HashMap::new();
         
         for config in configs {
-            let indexer = Arc::new(DiscourseIndexer::new(config.clone()));
+            let indexer = Arc::new(DiscourseIndexer::new(config.clone(), metrics.indexer.clone()));
             indexers.insert(config.discourse_id.clone(), indexer);
         }
 
         Self {
             indexers,
-            user_profile_cache: Cache::builder()
-                .max_capacity(1000)
-                .time_to_live(Duration::from_secs(60 * 60)) // 1 hour TTL
-                .build(),
-            user_summary_cache: Cache::builder()
-                .max_capacity(1000)
-                .time_to_live(Duration::from_secs(60 * 60)) // 1 hour TTL
-                .build(),
+            user_profile_cache: CacheConfig::load(
+                "DISCOURSE_USER_PROFILE_CACHE",
+                1000,
+                Duration::from_secs(60 * 60), // 1 hour TTL
+            )
+            .build(),
+            user_summary_cache: CacheConfig::load(
+                "DISCOURSE_USER_SUMMARY_CACHE",
+                1000,
+                Duration::from_secs(60 * 60), // 1 hour TTL
+            )
+            .build(),
+            cache_metrics: metrics.cache.clone(),
         }
     }
 
@@ -134,13 +335,35 @@ impl DiscourseService {
 
     pub async fn enqueue(&self, discourse_id: &str, topic_id: TopicId, page: u32) -> Result<(), Error> {
         if let Some(indexer) = self.indexers.get(discourse_id) {
-            indexer.enqueue(topic_id, page).await;
-            Ok(())
+            if indexer.enqueue(topic_id, page).await {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("Indexer queue for '{}' is full", discourse_id))
+            }
         } else {
             Err(anyhow::anyhow!("Discourse instance '{}' not found", discourse_id))
         }
     }
 
+    /// Posts per page of an instance, to find the page a post number is on
+    pub fn get_posts_per_page(&self, discourse_id: &str) -> u32 {
+        self.indexers
+            .get(discourse_id)
+            .map(|indexer| indexer.config.posts_per_page)
+            .unwrap_or(DEFAULT_POSTS_PER_PAGE)
+    }
+
+    /// Status of every indexer, ordered by discourse_id
+    pub async fn indexer_statuses(&self) -> Vec<IndexerStatus> {
+        let mut statuses = Vec::with_capacity(self.indexers.len());
+        for indexer in self.indexers.values() {
+            statuses.push(indexer.status().await);
+        }
+        statuses.sort_by(|a, b| a.discourse_id.cmp(&b.discourse_id));
+
+        statuses
+    }
+
     pub fn get_discourse_url(&self, discourse_id: &str) -> Option<String> {
         self.indexers.get(discourse_id).map(|indexer| indexer.config.url.clone())
     }
@@ -156,14 +379,37 @@ impl DiscourseService {
         let cache_key = format!("{}:{}", discourse_id, username);
         let username = username.to_string();
         
-        Ok(self.user_profile_cache
-            .get_with(cache_key, async move {
+        let entry = self.user_profile_cache
+            .entry(cache_key)
+            .or_insert_with(async move {
                 match Self::fetch_discourse_user(&discourse_url, &username).await {
                     Ok(user) => LResult::Success(user),
                     Err(e) => LResult::Failed(e.to_string()),
                 }
             })
-            .await)
+            .await;
+        self.cache_metrics.record("discourse_user_profile", entry.is_fresh());
+
+        Ok(entry.into_value())
+    }
+
+    /// Cached profiles of several users, missing ones are fetched a few at a time
+    ///
+    /// Results are in the order of `usernames`.
+    pub async fn fetch_discourse_users_cached(
+        &self,
+        discourse_id: &str,
+        usernames: &[String],
+    ) -> Result<Vec<LResult<DiscourseUserProfile>>, Error> {
+        if self.get_discourse_url(discourse_id).is_none() {
+            return Err(anyhow::anyhow!("Discourse instance '{}' not found", discourse_id));
+        }
+
+        stream::iter(usernames)
+            .map(|username| self.fetch_discourse_user_cached(discourse_id, username))
+            .buffered(USER_FETCH_CONCURRENCY)
+            .try_collect()
+            .await
     }
 
     pub async fn fetch_discourse_user_summary_cached(
@@ -177,14 +423,18 @@ impl DiscourseService {
         let cache_key = format!("{}:{}", discourse_id, username);
         let username = username.to_string();
         
-        Ok(self.user_summary_cache
-            .get_with(cache_key, async move {
+        let entry = self.user_summary_cache
+            .entry(cache_key)
+            .or_insert_with(async move {
                 match Self::fetch_discourse_user_summary(&discourse_url, &username).await {
                     Ok(user) => LResult::Success(user),
                     Err(e) => LResult::Failed(e.to_string()),
                 }
             })
-            .await)
+            .await;
+        self.cache_metrics.record("discourse_user_summary", entry.is_fresh());
+
+        Ok(entry.into_value())
     }
 
     pub async fn fetch_discourse_user(discourse_url: &str, username: &str) -> anyhow::Result<DiscourseUserProfile> {
@@ -220,22 +470,96 @@ impl DiscourseService {
     }
 }
 
+/// Fetches of a topic page before it is moved to the dead letters
+const MAX_FETCH_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled for every following one
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+
+/// Topic pages an indexer may have queued before new requests are dropped
+const DEFAULT_QUEUE_CAPACITY: usize = 10_000;
+
+/// Scrape intervals without a successful fetch before an instance is considered stale
+const STALE_AFTER_INTERVALS: i32 = 3;
+
+/// Parse intervals like `30m`, `2h` or `45s`, defaulting to 30 minutes for anything else,
+/// zero and negative intervals included
+fn parse_interval(interval: &str) -> TimeDelta {
+    let interval = interval.trim();
+    let split = interval.char_indices().last().map_or(0, |(index, _)| index);
+    let (value, unit) = interval.split_at(split);
+    let value = value.parse::<i64>().ok();
+
+    let interval = match (value, unit) {
+        (Some(value), "s") => Some(TimeDelta::seconds(value)),
+        (Some(value), "m") => Some(TimeDelta::minutes(value)),
+        (Some(value), "h") => Some(TimeDelta::hours(value)),
+        _ => None,
+    };
+
+    interval
+        .filter(|interval| *interval > TimeDelta::zero())
+        .unwrap_or(TimeDelta::minutes(30))
+}
+
+fn queue_capacity() -> usize {
+    std::env::var("DISCOURSE_QUEUE_CAPACITY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|capacity| *capacity > 0)
+        .unwrap_or(DEFAULT_QUEUE_CAPACITY)
+}
+
 /// Individual indexer for a single discourse instance
 pub struct DiscourseIndexer {
     config: DiscourseConfig,
     topic_tx: Sender<DiscourseTopicIndexRequest>,
     topic_lock: Arc<Mutex<HashSet<(TopicId, u32)>>>,
     topic_rx: Receiver<DiscourseTopicIndexRequest>,
+    metrics: IndexerMetrics,
+    started_at: DateTime<Utc>,
+    last_successful_fetch: RwLock<Option<DateTime<Utc>>>,
+    client: Arc<dyn DiscourseClient>,
 }
 
 impl DiscourseIndexer {
-    pub fn new(config: DiscourseConfig) -> Self {
-        let (topic_tx, topic_rx) = async_std::channel::unbounded();
+    pub fn new(config: DiscourseConfig, metrics: IndexerMetrics) -> Self {
+        Self::with_client(config, metrics, client::from_env())
+    }
+
+    pub fn with_client(
+        config: DiscourseConfig,
+        metrics: IndexerMetrics,
+        client: Arc<dyn DiscourseClient>,
+    ) -> Self {
+        // Bounded so a burst of webhooks or a large fetch can't grow the queue without limit
+        let (topic_tx, topic_rx) = async_std::channel::bounded(queue_capacity());
         Self {
             config,
             topic_tx,
             topic_lock: Arc::new(Mutex::new(HashSet::new())),
             topic_rx,
+            metrics,
+            started_at: Utc::now(),
+            last_successful_fetch: RwLock::new(None),
+            client,
+        }
+    }
+
+    async fn record_successful_fetch(&self) {
+        *self.last_successful_fetch.write().await = Some(Utc::now());
+    }
+
+    pub async fn status(&self) -> IndexerStatus {
+        let last_successful_fetch = *self.last_successful_fetch.read().await;
+        let stale_after = parse_interval(&self.config.scrape_interval) * STALE_AFTER_INTERVALS;
+        let stale = Utc::now() - last_successful_fetch.unwrap_or(self.started_at) > stale_after;
+
+        IndexerStatus {
+            discourse_id: self.config.discourse_id.clone(),
+            url: self.config.url.clone(),
+            last_successful_fetch,
+            queue_depth: self.topic_rx.len() as u64,
+            stale,
         }
     }
 
@@ -247,14 +571,92 @@ impl DiscourseIndexer {
             indexer_clone.fetch_periodically(&state_clone).await;
         });
 
-        info!("Started indexer for {}, awaiting requests", self.config.discourse_id);
+        if self.config.backfill {
+            let state_clone = state.clone();
+            let indexer_clone = Arc::clone(&self);
+            async_std::task::spawn(async move {
+                if let Err(e) = indexer_clone.backfill(&state_clone).await {
+                    indexer_clone.record_parse_failure(&e, &state_clone).await;
+                    error!(
+                        "Backfill of {} stopped, it resumes on the next start: {:?}",
+                        indexer_clone.config.discourse_id, e
+                    );
+                }
+            });
+        }
+
+        info!(
+            "Started indexer for {} with {} workers, awaiting requests",
+            self.config.discourse_id, self.config.fetch_concurrency
+        );
 
-        // Process topic indexing requests
+        // workers share the queue, each takes the next request once it is done with its last
+        for _ in 1..self.config.fetch_concurrency {
+            async_std::task::spawn(Arc::clone(&self).process_requests(state.clone()));
+        }
+        self.process_requests(state).await;
+    }
+
+    /// Process topic indexing requests until the queue closes
+    async fn process_requests(self: Arc<Self>, state: AppState) {
         while let Ok(request) = self.topic_rx.recv().await {
             info!("Processing request for {}: {:?}", self.config.discourse_id, request);
+            let metrics = &self.metrics;
+            metrics.queue_depth(&self.config.discourse_id, self.topic_rx.len());
+
+            match QuarantinedTopic::is_quarantined(&self.config.discourse_id, request.topic_id, &state).await {
+                Ok(false) => {}
+                quarantined => {
+                    if let Err(e) = quarantined {
+                        error!("Error checking quarantine of topic {:?}: {:?}", request.topic_id, e);
+                    }
+                    info!("Topic {:?} on {} is quarantined, skipping", request.topic_id, self.config.discourse_id);
+                    self.topic_lock
+                        .lock()
+                        .await
+                        .remove(&(request.topic_id, request.page));
+                    continue;
+                }
+            }
+
+            let topic = fetch_topic(self.client.as_ref(), &self.config.url, request.topic_id, request.page).await;
+            if let Err(e) = &topic {
+                metrics.fetch_error(&self.config.discourse_id);
+                self.record_parse_failure(e, &state).await;
+                // alternate format keeps the requested URL along with the cause
+                self.retry_or_dead_letter(request, format!("{:#}", e), &state).await;
+                continue;
+            }
+            self.record_successful_fetch().await;
+
+            if topic.as_ref().is_ok_and(|topic| self.config.excludes(topic)) {
+                info!("Topic {:?} on {} is private, skipping", request.topic_id, self.config.discourse_id);
+                self.topic_lock
+                    .lock()
+                    .await
+                    .remove(&(request.topic_id, request.page));
+                continue;
+            }
+
+            // Discourse redirects merged or moved topics to where their posts went
+            if let Some(moved_to) = topic
+                .as_ref()
+                .ok()
+                .map(|topic| topic.id)
+                .filter(|id| *id != request.topic_id)
+            {
+                self.move_topic(request.topic_id, moved_to, &state).await;
+                self.topic_lock
+                    .lock()
+                    .await
+                    .remove(&(request.topic_id, request.page));
+                continue;
+            }
 
-            if let Ok(topic) = fetch_topic(&self.config.url, request.topic_id, request.page).await {
+            if let Ok(topic) = topic {
                 let existing_topic = Topic::get_by_topic_id(&self.config.discourse_id, topic.id, &state).await.ok();
+                // posts are only news to subscribers of a topic that was already indexed
+                let topic_existed = existing_topic.is_some();
                 let existing_messages = if let Some(existing) = &existing_topic {
                     Post::count_by_topic_id(&self.config.discourse_id, existing.topic_id, &state)
                         .await
@@ -290,16 +692,63 @@ impl DiscourseIndexer {
                     );
                 }
 
-                if !topic.post_stream.posts.is_empty() {
-                    self.enqueue(request.topic_id, request.page + 1).await;
+                if let Some(next_page) = self.config.next_page(&topic, request.page) {
+                    self.enqueue(request.topic_id, next_page).await;
                 }
 
                 if request.page == 1 {
+                    // drop search documents of posts that were removed since the last index
+                    if let (Some(meili), Some(post_ids)) = (&state.meili, post_stream_ids(&topic)) {
+                        let forum = meili.index("forum");
+                        if let Err(e) = meili::delete_orphaned_post_documents(
+                            &forum,
+                            &self.config.discourse_id,
+                            topic.id,
+                            &post_ids,
+                        )
+                        .await
+                        {
+                            error!("Error deleting orphaned posts from Meilisearch: {:?}", e);
+                        }
+                    }
+
                     let topic_model = Topic::from_discourse(&self.config.discourse_id, &topic);
 
                     match topic_model.upsert(&state).await {
                         Ok(_) => {
                             info!("Upserted topic: {:?}", topic_model.topic_id);
+                            metrics.topic_indexed(&self.config.discourse_id);
+
+                            if !topic_existed {
+                                events::dispatch(
+                                    events::TOPIC_CREATED,
+                                    serde_json::json!({
+                                        "discourse_id": topic_model.discourse_id,
+                                        "topic_id": topic_model.topic_id,
+                                        "title": topic_model.title,
+                                        "slug": topic_model.slug,
+                                        "url": format!("{}/t/{}/{}", self.config.url, topic_model.slug, topic_model.topic_id),
+                                        "pm_issue": topic_model.pm_issue,
+                                        "created_at": topic_model.created_at,
+                                    }),
+                                    &state,
+                                );
+                            }
+
+                            let staleness = &state.workshop.summary_staleness;
+                            match TopicSummary::mark_stale_if_outgrown(&topic_model, staleness, &state).await {
+                                Ok(true) if staleness.auto_regenerate => {
+                                    let topic = topic_model.clone();
+                                    let state = state.clone();
+                                    async_std::task::spawn(async move {
+                                        if let Err(e) = Topic::get_summary_by_topic_id(&topic.discourse_id, topic.topic_id, &state).await {
+                                            error!("Error regenerating stale summary: {:?}", e);
+                                        }
+                                    });
+                                }
+                                Ok(_) => {}
+                                Err(e) => error!("Error checking summary staleness: {:?}", e),
+                            }
 
                             if let Some(meili) = &state.meili {
                                 let meili_doc = ForumSearchDocument {
@@ -314,6 +763,8 @@ impl DiscourseIndexer {
                                     slug: Some(topic_model.slug.clone()),
                                     pm_issue: topic_model.pm_issue,
                                     cooked: None,
+                                    code: None,
+                                    entities: None,
                                     entity_id: format!("topic_{}", topic_model.topic_id),
                                 };
 
@@ -337,14 +788,52 @@ impl DiscourseIndexer {
                     }
                 }
 
-                // Process posts
+                // Process posts, skipping the ones whose content didn't change since they were last written
+                let post_ids: Vec<i32> = topic.post_stream.posts.iter().map(|post| post.id).collect();
+                let known_hashes = Post::content_hashes(&self.config.discourse_id, &post_ids, &state)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Error loading post content hashes: {:?}", e);
+                        HashMap::new()
+                    });
+                let mut written_hashes = Vec::new();
+                let mut posts_unchanged = 0;
+
                 let mut meili_docs = Vec::new();
+                let mut new_posts = Vec::new();
+                let mut posts_indexed = 0;
                 for discourse_post in topic.post_stream.posts {
                     let username = discourse_post.username.clone();
                     let post = Post::from_discourse(&self.config.discourse_id, discourse_post);
+
+                    let content_hash = post.content_hash();
+                    if known_hashes.get(&post.post_id) == Some(&content_hash) {
+                        posts_unchanged += 1;
+                        continue;
+                    }
+
                     match post.upsert(&state).await {
-                        Ok(_) => {
+                        Ok(inserted) => {
                             info!("Upserted post: {:?}", post.post_id);
+                            posts_indexed += 1;
+                            written_hashes.push((post.post_id, content_hash));
+
+                            if inserted && topic_existed {
+                                new_posts.push(NewPost::from_post(&post, Some(&self.config.url)));
+                            }
+
+                            let post_entities = post.cooked.as_deref().map(entities::extract).unwrap_or_default();
+                            if let Err(e) = TopicEntity::replace_for_post(
+                                &self.config.discourse_id,
+                                post.topic_id,
+                                post.post_id,
+                                &post_entities,
+                                &state,
+                            )
+                            .await
+                            {
+                                error!("Error storing entities of post {}: {:?}", post.post_id, e);
+                            }
 
                             if state.meili.is_some() {
                                 meili_docs.push(ForumSearchDocument {
@@ -359,6 +848,8 @@ impl DiscourseIndexer {
                                     slug: None,
                                     pm_issue: None,
                                     cooked: post.cooked.as_deref().map(strip_tags),
+                                    code: post.cooked.as_deref().and_then(code::extract),
+                                    entities: Some(post_entities.iter().map(Entity::search_key).collect()),
                                     entity_id: format!("post_{}", post.post_id),
                                 });
                             }
@@ -366,7 +857,22 @@ impl DiscourseIndexer {
                         Err(e) => error!("Error upserting post: {:?}", e),
                     }
                 }
+                metrics.posts_indexed(&self.config.discourse_id, posts_indexed);
+                info!(
+                    "Topic {:?} page {} on {}: {} posts written, {} unchanged",
+                    topic.id, request.page, self.config.discourse_id, posts_indexed, posts_unchanged
+                );
+
+                notifications::notify_new_posts(
+                    &self.config.discourse_id,
+                    topic.id,
+                    &topic.title,
+                    &new_posts,
+                    &state,
+                )
+                .await;
 
+                let mut search_updated = true;
                 if let Some(meili) = &state.meili {
                     if !meili_docs.is_empty() {
                         let forum = meili.index("forum");
@@ -381,9 +887,23 @@ impl DiscourseIndexer {
                             })
                         {
                             error!("Error bulk upserting posts to Meilisearch: {:?}", e);
+                            search_updated = false;
                         }
                     }
                 }
+
+                // without the hashes the posts are written again on the next fetch, retrying the search update
+                if search_updated {
+                    if let Err(e) =
+                        Post::store_content_hashes(&self.config.discourse_id, &written_hashes, &state).await
+                    {
+                        error!("Error storing post content hashes: {:?}", e);
+                    }
+                }
+
+                state
+                    .cache
+                    .invalidate_topic_responses(&self.config.discourse_id, request.topic_id);
             }
 
             self.topic_lock
@@ -395,33 +915,207 @@ impl DiscourseIndexer {
         error!("Indexer for {} stopped", self.config.discourse_id);
     }
 
-    pub async fn enqueue(&self, topic_id: TopicId, page: u32) {
+    /// Walk every page of `/latest.json` and index all topics on it
+    ///
+    /// Progress is saved after each page once its topics are indexed, so a restart picks up
+    /// at the first page that wasn't finished. The backfill only counts as complete once the
+    /// last page is done.
+    async fn backfill(&self, state: &AppState) -> anyhow::Result<()> {
+        let discourse_id = &self.config.discourse_id;
+        let progress = DiscourseBackfill::get(discourse_id, state).await?;
+        if progress.as_ref().is_some_and(|progress| progress.completed_at.is_some()) {
+            info!("Backfill of {} already completed", discourse_id);
+            return Ok(());
+        }
+
+        let mut pages_fetched = progress.as_ref().map_or(0, |progress| progress.pages_fetched);
+        let mut url = progress
+            .and_then(|progress| progress.next_url)
+            .unwrap_or_else(|| format!("{}/latest.json", self.config.url));
+        info!("Backfilling {} from page {} ({})", discourse_id, pages_fetched + 1, url);
+
+        loop {
+            let page: DiscourseLatestResponse = get_json(self.client.as_ref(), &url).await?;
+            for topic in &page.topic_list.topics {
+                // a full queue drops the topic, let it drain and try again so the page isn't
+                // saved as done with topics missing
+                while !self.enqueue(topic.id, 1).await {
+                    if self.topic_tx.is_closed() {
+                        return Err(anyhow::anyhow!(
+                            "Indexer queue for {} is closed, backfill stopped on {}",
+                            discourse_id,
+                            url
+                        ));
+                    }
+                    self.wait_until_idle().await;
+                }
+            }
+            self.wait_until_idle().await;
+            pages_fetched += 1;
+
+            let next = page
+                .topic_list
+                .more_topics_url
+                .as_deref()
+                .and_then(|more_topics_url| latest_page_url(&self.config.url, more_topics_url));
+            let Some(next) = next else {
+                DiscourseBackfill::complete(discourse_id, pages_fetched, state).await?;
+                info!("Backfill of {} completed after {} pages", discourse_id, pages_fetched);
+                return Ok(());
+            };
+
+            DiscourseBackfill::save_progress(discourse_id, &next, pages_fetched, state).await?;
+            url = next;
+            async_std::task::sleep(self.config.latest_page_delay).await;
+        }
+    }
+
+    /// Keep the response behind a failed fetch if it failed to parse
+    async fn record_parse_failure(&self, error: &Error, state: &AppState) {
+        let Some(failure) = error.downcast_ref::<ParseFailure>() else {
+            return;
+        };
+
+        if let Err(e) = ResponseParseFailure::record(&self.config.discourse_id, failure, state).await {
+            error!("Error storing unparsable response from {}: {:?}", failure.url, e);
+        }
+    }
+
+    /// Wait until every queued page, follow-up pages and retries included, was processed
+    async fn wait_until_idle(&self) {
+        while !self.topic_lock.lock().await.is_empty() {
+            async_std::task::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Point a topic that now redirects elsewhere at its new topic and index that one instead
+    async fn move_topic(self: &Arc<Self>, topic_id: TopicId, moved_to: TopicId, state: &AppState) {
+        info!(
+            "Topic {:?} on {} was moved to {:?}",
+            topic_id, self.config.discourse_id, moved_to
+        );
+
+        if let Err(e) = TopicRedirect::record(&self.config.discourse_id, topic_id, moved_to, state).await {
+            error!("Error recording move of topic {:?}: {:?}", topic_id, e);
+            return;
+        }
+
+        if let Some(meili) = &state.meili {
+            let forum = meili.index("forum");
+            if let Err(e) = meili::delete_topic_documents(&forum, &self.config.discourse_id, topic_id).await {
+                error!("Error deleting moved topic from Meilisearch: {:?}", e);
+            }
+        }
+
+        state
+            .cache
+            .invalidate_topic_responses(&self.config.discourse_id, topic_id);
+        self.enqueue(moved_to, 1).await;
+    }
+
+    /// Requeue a failed request with backoff, or record it as a dead letter once it ran out of attempts
+    async fn retry_or_dead_letter(
+        self: &Arc<Self>,
+        request: DiscourseTopicIndexRequest,
+        error: String,
+        state: &AppState,
+    ) {
+        let key = (request.topic_id, request.page);
+        let attempts = request.attempt + 1;
+
+        if attempts < MAX_FETCH_ATTEMPTS {
+            let delay = RETRY_BASE_DELAY * 2u32.pow(request.attempt);
+            warn!(
+                "Fetching topic {:?} page {} for {} failed ({}), retry {} in {:?}",
+                request.topic_id, request.page, self.config.discourse_id, error, attempts, delay
+            );
+
+            // The page stays in topic_lock while it waits, so it isn't enqueued twice
+            let indexer = Arc::clone(self);
+            async_std::task::spawn(async move {
+                async_std::task::sleep(delay).await;
+                let retry = DiscourseTopicIndexRequest { attempt: attempts, ..request };
+                if indexer.topic_tx.try_send(retry).is_err() {
+                    warn!("Indexer queue for {} is full, dropping retry of topic {:?} page {}", indexer.config.discourse_id, key.0, key.1);
+                    indexer.metrics.queue_dropped(&indexer.config.discourse_id);
+                    indexer.topic_lock.lock().await.remove(&key);
+                }
+            });
+            return;
+        }
+
+        error!(
+            "Giving up on topic {:?} page {} for {} after {} attempts: {}",
+            request.topic_id, request.page, self.config.discourse_id, attempts, error
+        );
+        if let Err(e) = IndexerDeadLetter::record(
+            &self.config.discourse_id,
+            request.topic_id,
+            request.page as i32,
+            attempts as i32,
+            &error,
+            state,
+        )
+        .await
+        {
+            error!("Error recording dead letter: {:?}", e);
+        }
+        self.topic_lock.lock().await.remove(&key);
+    }
+
+    /// Queue a topic page for indexing, returns false when the queue is full and the page was dropped
+    pub async fn enqueue(&self, topic_id: TopicId, page: u32) -> bool {
         info!("Enqueuing topic {:?} page {} for {}", topic_id, page, self.config.discourse_id);
         let mut set = self.topic_lock.lock().await;
         let key = (topic_id, page);
         if set.insert(key) {
-            let _ = self
-                .topic_tx
-                .send(DiscourseTopicIndexRequest { 
-                    topic_id, 
-                    page 
-                })
-                .await;
-            info!("Enqueued topic {:?} page {} for {}", topic_id, page, self.config.discourse_id);
+            // Never wait for room, the indexer loop enqueues follow-up pages itself
+            match self.topic_tx.try_send(DiscourseTopicIndexRequest { 
+                topic_id, 
+                page,
+                attempt: 0,
+            }) {
+                Ok(()) => {
+                    info!("Enqueued topic {:?} page {} for {}", topic_id, page, self.config.discourse_id);
+                }
+                Err(TrySendError::Full(_)) => {
+                    warn!("Indexer queue for {} is full, dropping topic {:?} page {}", self.config.discourse_id, topic_id, page);
+                    self.metrics.queue_dropped(&self.config.discourse_id);
+                    set.remove(&key);
+                    return false;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    error!("Indexer queue for {} is closed", self.config.discourse_id);
+                    set.remove(&key);
+                    return false;
+                }
+            }
         } else {
             info!("Topic {:?} page {} is already enqueued for {}, skipping", topic_id, page, self.config.discourse_id);
         }
+
+        true
     }
 
     pub async fn fetch_latest(&self, state: &AppState) -> anyhow::Result<()> {
-        let topics = fetch_latest_topics(&self.config.url).await?;
+        let topics = fetch_latest_topics_paginated(
+            self.client.as_ref(),
+            &self.config.url,
+            self.config.latest_pages,
+            self.config.latest_page_delay,
+        )
+        .await?;
+        self.record_successful_fetch().await;
 
-        for topic in topics.topic_list.topics {
+        for topic in topics {
             info!("Topic ({}) for {}: {:?}", topic.id, self.config.discourse_id, topic.title);
             self.enqueue(topic.id, 1).await;
             info!("Queued for {}", self.config.discourse_id);
         }
 
+        self.metrics
+            .queue_depth(&self.config.discourse_id, self.topic_rx.len());
+
         Ok(())
     }
 
@@ -433,45 +1127,379 @@ impl DiscourseIndexer {
                 }
                 Err(e) => {
                     error!("Error fetching latest topics for {}: {:?}", self.config.discourse_id, e);
+                    self.metrics.fetch_error(&self.config.discourse_id);
+                    self.record_parse_failure(&e, state).await;
                 }
             }
 
-            let now = Utc::now();
-            let next = now.duration_round_up(TimeDelta::minutes(30)).unwrap();
-
-            info!("Next fetch for {} at: {:?}", self.config.discourse_id, next);
-
-            let duration = next.signed_duration_since(now);
-            async_std::task::sleep(Duration::from_secs(duration.num_seconds() as u64)).await;
+            let schedule = Schedule::every(parse_interval(&self.config.scrape_interval));
+            info!(
+                "Next fetch for {} at: {:?}",
+                self.config.discourse_id,
+                schedule.next_after(Utc::now())
+            );
+            schedule.sleep().await;
         }
     }
 }
 
+/// Discourse's default page size for topic posts
+pub const DEFAULT_POSTS_PER_PAGE: u32 = 20;
+
+/// Reads `DISCOURSE_<ID>_POSTS_PER_PAGE`, for instances that changed their page size
+fn posts_per_page(discourse_id: &str) -> u32 {
+    std::env::var(format!("DISCOURSE_{}_POSTS_PER_PAGE", discourse_id.to_uppercase()))
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|per_page| *per_page > 0)
+        .unwrap_or(DEFAULT_POSTS_PER_PAGE)
+}
+
+/// Far more than any real topic has, at 20 posts per page
+pub const DEFAULT_MAX_TOPIC_PAGES: u32 = 500;
+
+/// Reads `DISCOURSE_<ID>_MAX_TOPIC_PAGES`
+fn max_topic_pages(discourse_id: &str) -> u32 {
+    std::env::var(format!("DISCOURSE_{}_MAX_TOPIC_PAGES", discourse_id.to_uppercase()))
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|pages| *pages > 0)
+        .unwrap_or(DEFAULT_MAX_TOPIC_PAGES)
+}
+
+/// Reads `DISCOURSE_<ID>_SCRAPE_INTERVAL`, like `30m` or `2h`, every 30 minutes by default
+fn scrape_interval(discourse_id: &str) -> String {
+    std::env::var(format!("DISCOURSE_{}_SCRAPE_INTERVAL", discourse_id.to_uppercase()))
+        .unwrap_or_else(|_| "30m".to_string())
+}
+
+/// Reads `DISCOURSE_<ID>_BACKFILL`
+fn backfill(discourse_id: &str) -> bool {
+    std::env::var(format!("DISCOURSE_{}_BACKFILL", discourse_id.to_uppercase()))
+        .is_ok_and(|value| value == "true" || value == "1")
+}
+
+/// Reads `DISCOURSE_<ID>_FETCH_CONCURRENCY`, one topic page at a time by default
+fn fetch_concurrency(discourse_id: &str) -> usize {
+    std::env::var(format!("DISCOURSE_{}_FETCH_CONCURRENCY", discourse_id.to_uppercase()))
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(1)
+}
+
+/// Reads `DISCOURSE_<ID>_LATEST_PAGE_DELAY_MS`, two seconds by default
+fn latest_page_delay(discourse_id: &str) -> Duration {
+    std::env::var(format!("DISCOURSE_{}_LATEST_PAGE_DELAY_MS", discourse_id.to_uppercase()))
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map_or(Duration::from_secs(2), Duration::from_millis)
+}
+
+/// Reads `DISCOURSE_<ID>_LATEST_PAGES`, only the first page by default
+fn latest_pages(discourse_id: &str) -> u32 {
+    std::env::var(format!("DISCOURSE_{}_LATEST_PAGES", discourse_id.to_uppercase()))
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|pages| *pages > 0)
+        .unwrap_or(1)
+}
+
+/// Reads `DISCOURSE_<ID>_INCLUDE_PRIVATE`
+fn include_private(discourse_id: &str) -> bool {
+    std::env::var(format!("DISCOURSE_{}_INCLUDE_PRIVATE", discourse_id.to_uppercase()))
+        .is_ok_and(|value| value == "true" || value == "1")
+}
+
+/// Reads `DISCOURSE_<ID>_PRIVATE_CATEGORIES`, a comma separated list of category ids
+fn private_categories(discourse_id: &str) -> Vec<i64> {
+    std::env::var(format!("DISCOURSE_{}_PRIVATE_CATEGORIES", discourse_id.to_uppercase()))
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|category_id| category_id.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Helper function to create discourse configs from TOML-like structure
 pub fn create_discourse_configs() -> Vec<DiscourseConfig> {
     vec![
         DiscourseConfig {
             discourse_id: "magicians".to_string(),
             url: "https://ethereum-magicians.org".to_string(),
-            scrape_interval: "30m".to_string(),
+            scrape_interval: scrape_interval("magicians"),
+            posts_per_page: posts_per_page("magicians"),
+            include_private: include_private("magicians"),
+            private_categories: private_categories("magicians"),
+            latest_pages: latest_pages("magicians"),
+            max_topic_pages: max_topic_pages("magicians"),
+            fetch_concurrency: fetch_concurrency("magicians"),
+            latest_page_delay: latest_page_delay("magicians"),
+            backfill: backfill("magicians"),
         },
         DiscourseConfig {
             discourse_id: "research".to_string(),
             url: "https://ethresear.ch".to_string(),
-            scrape_interval: "30m".to_string(),
+            scrape_interval: scrape_interval("research"),
+            posts_per_page: posts_per_page("research"),
+            include_private: include_private("research"),
+            private_categories: private_categories("research"),
+            latest_pages: latest_pages("research"),
+            max_topic_pages: max_topic_pages("research"),
+            fetch_concurrency: fetch_concurrency("research"),
+            latest_page_delay: latest_page_delay("research"),
+            backfill: backfill("research"),
         },
     ]
 }
 
+/// Page of the topic endpoint a post number is on
+pub fn page_for_post_number(post_number: i64, posts_per_page: u32) -> u32 {
+    ((post_number.max(1) - 1) / i64::from(posts_per_page.max(1)) + 1) as u32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::client::{CannedDiscourseClient, HttpDiscourseClient};
+
+    #[test]
+    fn test_private_topics_are_excluded() {
+        let topic = |extra: serde_json::Value| -> DiscourseTopicResponse {
+            let mut topic = serde_json::json!({
+                "post_stream": { "posts": [] },
+                "id": 1,
+                "title": "Private",
+                "slug": "private",
+                "posts_count": 1,
+                "image_url": null,
+                "created_at": "2025-01-01T00:00:00Z",
+                "last_posted_at": "2025-01-01T00:00:00Z",
+                "views": 0,
+                "like_count": 0,
+            });
+            topic.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(topic).unwrap()
+        };
+        let mut config = DiscourseConfig {
+            discourse_id: "magicians".to_string(),
+            url: "https://ethereum-magicians.org".to_string(),
+            scrape_interval: "30m".to_string(),
+            posts_per_page: DEFAULT_POSTS_PER_PAGE,
+            include_private: false,
+            private_categories: vec![7],
+            latest_pages: 1,
+            max_topic_pages: DEFAULT_MAX_TOPIC_PAGES,
+            fetch_concurrency: 1,
+            latest_page_delay: Duration::from_secs(2),
+            backfill: false,
+        };
+
+        let message = topic(serde_json::json!({ "archetype": "private_message" }));
+        let restricted = topic(serde_json::json!({ "archetype": "regular", "category_id": 7 }));
+        let public = topic(serde_json::json!({ "archetype": "regular", "category_id": 9 }));
+
+        assert!(config.excludes(&message));
+        assert!(config.excludes(&restricted));
+        assert!(!config.excludes(&public));
+
+        config.include_private = true;
+        assert!(!config.excludes(&message));
+    }
+
+    #[test]
+    fn test_topic_pages_are_capped() {
+        let config = DiscourseConfig {
+            discourse_id: "magicians".to_string(),
+            url: "https://ethereum-magicians.org".to_string(),
+            scrape_interval: "30m".to_string(),
+            posts_per_page: DEFAULT_POSTS_PER_PAGE,
+            include_private: false,
+            private_categories: vec![],
+            latest_pages: 1,
+            max_topic_pages: 5,
+            fetch_concurrency: 1,
+            latest_page_delay: Duration::from_secs(2),
+            backfill: false,
+        };
+        // a response that never runs out of posts, whatever page is asked for
+        let topic: DiscourseTopicResponse = serde_json::from_value(serde_json::json!({
+            "post_stream": { "posts": [{
+                "id": 1,
+                "username": "vitalik",
+                "created_at": "2025-01-01T00:00:00Z",
+                "updated_at": "2025-01-01T00:00:00Z",
+                "cooked": "<p>Hello</p>",
+                "user_id": 1,
+                "topic_id": 1,
+                "post_url": null,
+                "post_number": 1,
+            }] },
+            "id": 1,
+            "title": "Looping",
+            "slug": "looping",
+            "posts_count": 1,
+            "image_url": null,
+            "created_at": "2025-01-01T00:00:00Z",
+            "last_posted_at": "2025-01-01T00:00:00Z",
+            "views": 0,
+            "like_count": 0,
+        }))
+        .unwrap();
+
+        let mut pages = vec![1];
+        while let Some(next_page) = config.next_page(&topic, *pages.last().unwrap()) {
+            pages.push(next_page);
+        }
+        assert_eq!(pages, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_latest_page_url_variants() {
+        let next = |discourse_url, more_topics_url| latest_page_url(discourse_url, more_topics_url);
+        let magicians = "https://ethereum-magicians.org";
+
+        assert_eq!(
+            next(magicians, "/latest?no_definitions=true&page=1").as_deref(),
+            Some("https://ethereum-magicians.org/latest.json?no_definitions=true&page=1")
+        );
+        assert_eq!(
+            next(magicians, "/latest.json?page=2").as_deref(),
+            Some("https://ethereum-magicians.org/latest.json?page=2")
+        );
+        assert_eq!(
+            next(magicians, "https://ethereum-magicians.org/latest?page=3").as_deref(),
+            Some("https://ethereum-magicians.org/latest.json?page=3")
+        );
+        assert_eq!(
+            next(magicians, "?page=4").as_deref(),
+            Some("https://ethereum-magicians.org/latest.json?page=4")
+        );
+        assert_eq!(
+            next("https://example.org/forum/", "latest?page=5").as_deref(),
+            Some("https://example.org/forum/latest.json?page=5")
+        );
+        assert_eq!(next(magicians, "https://example.org/latest?page=1"), None);
+        assert_eq!(next(magicians, ""), None);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("30m"), TimeDelta::minutes(30));
+        assert_eq!(parse_interval("2h"), TimeDelta::hours(2));
+        assert_eq!(parse_interval("45s"), TimeDelta::seconds(45));
+        assert_eq!(parse_interval("soon"), TimeDelta::minutes(30));
+        assert_eq!(parse_interval("0m"), TimeDelta::minutes(30));
+        assert_eq!(parse_interval("-5m"), TimeDelta::minutes(30));
+    }
+
+    #[test]
+    fn test_page_for_post_number() {
+        assert_eq!(page_for_post_number(1, 20), 1);
+        assert_eq!(page_for_post_number(20, 20), 1);
+        assert_eq!(page_for_post_number(21, 20), 2);
+        assert_eq!(page_for_post_number(21, 50), 1);
+        assert_eq!(page_for_post_number(0, 20), 1);
+    }
+
+    fn latest_page(topic_ids: &[i32], more_topics_url: Option<&str>) -> serde_json::Value {
+        let topics: Vec<_> = topic_ids
+            .iter()
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "title": format!("Topic {}", id),
+                    "fancy_title": format!("Topic {}", id),
+                    "slug": format!("topic-{}", id),
+                    "posts_count": 1,
+                    "reply_count": 0,
+                    "highest_post_number": 1,
+                    "image_url": null,
+                    "pinned": false,
+                    "unpinned": null,
+                    "visible": true,
+                    "closed": false,
+                    "archived": false,
+                    "views": 10,
+                    "like_count": 0,
+                    "category_id": 1,
+                    "featured_link": null,
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "users": [{ "id": 1, "username": "vitalik" }],
+            "topic_list": {
+                "more_topics_url": more_topics_url,
+                "per_page": 30,
+                "topics": topics,
+            },
+        })
+    }
 
     #[async_std::test]
     async fn test_fetch_latest_topics() {
-        let result = fetch_latest_topics("https://ethereum-magicians.org").await.unwrap();
-        // assert!(result.topic_list.topics.len() > 0);
+        let client = CannedDiscourseClient::default()
+            .with("https://ethereum-magicians.org/latest.json", latest_page(&[1, 2], None));
+
+        let result = fetch_latest_topics(&client, "https://ethereum-magicians.org").await.unwrap();
+
+        assert_eq!(result.users.len(), 1);
+        assert_eq!(result.topic_list.topics.len(), 2);
+    }
+
+    #[async_std::test]
+    async fn test_fetch_latest_topics_paginated() {
+        let client = CannedDiscourseClient::default()
+            .with(
+                "https://ethereum-magicians.org/latest.json",
+                latest_page(&[1, 2], Some("/latest?page=1")),
+            )
+            .with(
+                "https://ethereum-magicians.org/latest.json?page=1",
+                latest_page(&[3], Some("/latest?page=2")),
+            );
+
+        let topics =
+            fetch_latest_topics_paginated(&client, "https://ethereum-magicians.org", 2, Duration::ZERO)
+                .await
+                .unwrap();
+        let ids: Vec<_> = topics.iter().map(|topic| topic.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        // the failing URL ends up in the error
+        let error = fetch_latest_topics_paginated(&client, "https://ethereum-magicians.org", 3, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("https://ethereum-magicians.org/latest.json?page=2"));
+    }
+
+    #[async_std::test]
+    async fn test_unparsable_response_is_kept() {
+        let client = CannedDiscourseClient::default().with(
+            "https://ethereum-magicians.org/latest.json",
+            serde_json::json!({ "errors": ["rate limited"] }),
+        );
+
+        let error = fetch_latest_topics(&client, "https://ethereum-magicians.org")
+            .await
+            .unwrap_err();
+        let failure = error.downcast_ref::<ParseFailure>().unwrap();
+        assert_eq!(failure.url, "https://ethereum-magicians.org/latest.json");
+        assert_eq!(failure.body, r#"{"errors":["rate limited"]}"#);
+    }
+
+    #[async_std::test]
+    #[ignore = "hits the live forum, run with --ignored"]
+    async fn test_fetch_latest_topics_live() {
+        let result = fetch_latest_topics(&HttpDiscourseClient, "https://ethereum-magicians.org")
+            .await
+            .unwrap();
 
+        assert!(!result.topic_list.topics.is_empty());
         println!("Active Users: {:?}", result.users.len());
     }
 }
@@ -0,0 +1,335 @@
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use poem::Body;
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sqlx::{FromRow, PgPool, postgres::PgRow};
+use strip_tags::strip_tags;
+
+use crate::{
+    models::topics::{Topic, entity::TopicEntity, post::Post, quarantine::QuarantinedTopic},
+    modules::{code, discourse::ForumSearchDocument, entities},
+    state::AppState,
+};
+
+/// Rows buffered between the database cursor and the response body
+const EXPORT_BUFFER_ROWS: usize = 256;
+/// Rows upserted per batch while importing
+const IMPORT_BATCH_ROWS: usize = 500;
+/// Line errors included in an import report
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Filters shared by the topic and post exports, all optional
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub discourse_id: Option<String>,
+    /// Created at or after
+    pub from: Option<DateTime<Utc>>,
+    /// Created before
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Stream all matching topics as JSON lines
+pub fn export_topics(pool: PgPool, filter: ExportFilter) -> Body {
+    export_jsonl::<Topic>(
+        pool,
+        "SELECT * FROM topics
+        WHERE ($1::TEXT IS NULL OR discourse_id = $1)
+            AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)
+        ORDER BY discourse_id, topic_id",
+        filter,
+    )
+}
+
+/// Stream all matching posts as JSON lines
+pub fn export_posts(pool: PgPool, filter: ExportFilter) -> Body {
+    export_jsonl::<Post>(
+        pool,
+        "SELECT * FROM posts
+        WHERE ($1::TEXT IS NULL OR discourse_id = $1)
+            AND ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)
+        ORDER BY discourse_id, topic_id, post_number",
+        filter,
+    )
+}
+
+/// Rows are read from a database cursor as the client consumes the body,
+/// so memory use stays bounded regardless of the export size
+fn export_jsonl<T>(pool: PgPool, sql: &'static str, filter: ExportFilter) -> Body
+where
+    T: for<'r> FromRow<'r, PgRow> + Serialize + Send + Unpin + 'static,
+{
+    let (tx, rx) = async_std::channel::bounded::<std::io::Result<Vec<u8>>>(EXPORT_BUFFER_ROWS);
+
+    async_std::task::spawn(async move {
+        let mut rows = sqlx::query_as::<_, T>(sql)
+            .bind(filter.discourse_id)
+            .bind(filter.from)
+            .bind(filter.to)
+            .fetch(&pool);
+
+        while let Some(row) = rows.next().await {
+            let line = row.map_err(std::io::Error::other).and_then(|row| {
+                let mut line = serde_json::to_vec(&row)?;
+                line.push(b'\n');
+                Ok(line)
+            });
+            let failed = line.is_err();
+            if let Err(e) = &line {
+                tracing::error!("Export failed: {}", e);
+            }
+
+            // Stop when the client went away or the export broke off
+            if tx.send(line).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    Body::from_bytes_stream(rx)
+}
+
+/// Outcome of an import
+#[derive(Debug, Default, Serialize, Deserialize, Object)]
+pub struct ImportReport {
+    pub inserted: u64,
+    pub updated: u64,
+    pub skipped: u64,
+    /// The first few lines that were skipped and why
+    pub errors: Vec<String>,
+}
+
+impl ImportReport {
+    fn skip(&mut self, line: usize, reason: impl std::fmt::Display) {
+        self.skipped += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(format!("line {}: {}", line, reason));
+        }
+    }
+}
+
+/// A row of a dump, in the shape produced by the exports
+trait ImportRow: DeserializeOwned {
+    fn validate(&self, state: &AppState) -> Result<(), String>;
+    async fn exists(&self, state: &AppState) -> Result<bool, sqlx::Error>;
+    async fn upsert(&self, state: &AppState) -> Result<(), sqlx::Error>;
+    fn search_document(&self) -> ForumSearchDocument;
+}
+
+fn validate_discourse_id(discourse_id: &str, state: &AppState) -> Result<(), String> {
+    match state.discourse.get_discourse_url(discourse_id) {
+        Some(_) => Ok(()),
+        None => Err(format!("unknown discourse_id {:?}", discourse_id)),
+    }
+}
+
+impl ImportRow for Topic {
+    fn validate(&self, state: &AppState) -> Result<(), String> {
+        validate_discourse_id(&self.discourse_id, state)?;
+        if self.topic_id <= 0 {
+            return Err(format!("invalid topic_id {}", self.topic_id));
+        }
+        if self.title.trim().is_empty() {
+            return Err("empty title".to_string());
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, state: &AppState) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM topics WHERE discourse_id = $1 AND topic_id = $2)")
+            .bind(&self.discourse_id)
+            .bind(self.topic_id)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    async fn upsert(&self, state: &AppState) -> Result<(), sqlx::Error> {
+        Topic::upsert(self, state).await
+    }
+
+    fn search_document(&self) -> ForumSearchDocument {
+        ForumSearchDocument {
+            entity_type: "topic".to_string(),
+            discourse_id: Some(self.discourse_id.clone()),
+            topic_id: Some(self.topic_id),
+            post_id: None,
+            post_number: None,
+            user_id: None,
+            username: None,
+            title: Some(self.title.clone()),
+            slug: Some(self.slug.clone()),
+            pm_issue: self.pm_issue,
+            cooked: None,
+            code: None,
+            entities: None,
+            entity_id: format!("topic_{}", self.topic_id),
+        }
+    }
+}
+
+impl ImportRow for Post {
+    fn validate(&self, state: &AppState) -> Result<(), String> {
+        validate_discourse_id(&self.discourse_id, state)?;
+        if self.post_id <= 0 || self.topic_id <= 0 {
+            return Err(format!("invalid post_id {} or topic_id {}", self.post_id, self.topic_id));
+        }
+        if self.post_number <= 0 {
+            return Err(format!("invalid post_number {}", self.post_number));
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, state: &AppState) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM posts WHERE discourse_id = $1 AND post_id = $2)")
+            .bind(&self.discourse_id)
+            .bind(self.post_id)
+            .fetch_one(&state.database.pool)
+            .await
+    }
+
+    async fn upsert(&self, state: &AppState) -> Result<(), sqlx::Error> {
+        Post::upsert(self, state).await?;
+
+        let post_entities = self.cooked.as_deref().map(entities::extract).unwrap_or_default();
+        TopicEntity::replace_for_post(&self.discourse_id, self.topic_id, self.post_id, &post_entities, state).await
+    }
+
+    fn search_document(&self) -> ForumSearchDocument {
+        ForumSearchDocument {
+            entity_type: "post".to_string(),
+            discourse_id: Some(self.discourse_id.clone()),
+            topic_id: Some(self.topic_id),
+            post_id: Some(self.post_id),
+            post_number: Some(self.post_number),
+            user_id: Some(self.user_id),
+            username: None,
+            title: None,
+            slug: None,
+            pm_issue: None,
+            cooked: self.cooked.as_deref().map(strip_tags),
+            code: self.cooked.as_deref().and_then(code::extract),
+            entities: self.cooked.as_deref().map(entities::search_keys),
+            entity_id: format!("post_{}", self.post_id),
+        }
+    }
+}
+
+/// Import topics from JSON lines as produced by [`export_topics`]
+pub async fn import_topics(body: Body, index: bool, state: &AppState) -> std::io::Result<ImportReport> {
+    import_jsonl::<Topic>(body, index, state).await
+}
+
+/// Import posts from JSON lines as produced by [`export_posts`]
+pub async fn import_posts(body: Body, index: bool, state: &AppState) -> std::io::Result<ImportReport> {
+    import_jsonl::<Post>(body, index, state).await
+}
+
+/// Reads the body line by line, so a dump never has to fit in memory
+async fn import_jsonl<T: ImportRow>(body: Body, index: bool, state: &AppState) -> std::io::Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut chunks = body.into_bytes_stream();
+    let mut buffer = Vec::new();
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_ROWS);
+    let mut line_number = 0;
+
+    loop {
+        let chunk = chunks.next().await.transpose()?;
+        let finished = chunk.is_none();
+        if let Some(chunk) = chunk {
+            buffer.extend_from_slice(&chunk);
+        } else if !buffer.is_empty() {
+            // last line without a trailing newline
+            buffer.push(b'\n');
+        }
+
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            line_number += 1;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            match serde_json::from_slice::<T>(&line) {
+                Ok(row) => match row.validate(state) {
+                    Ok(()) => batch.push((line_number, row)),
+                    Err(reason) => report.skip(line_number, reason),
+                },
+                Err(e) => report.skip(line_number, e),
+            }
+
+            if batch.len() >= IMPORT_BATCH_ROWS {
+                import_batch(std::mem::take(&mut batch), index, state, &mut report).await;
+            }
+        }
+
+        if finished {
+            break;
+        }
+    }
+
+    import_batch(batch, index, state, &mut report).await;
+
+    Ok(report)
+}
+
+async fn import_batch<T: ImportRow>(
+    batch: Vec<(usize, T)>,
+    index: bool,
+    state: &AppState,
+    report: &mut ImportReport,
+) {
+    let mut documents = Vec::new();
+
+    for (line_number, row) in batch {
+        let existed = match row.exists(state).await {
+            Ok(existed) => existed,
+            Err(e) => {
+                report.skip(line_number, e);
+                continue;
+            }
+        };
+
+        if let Err(e) = row.upsert(state).await {
+            report.skip(line_number, e);
+            continue;
+        }
+
+        if existed {
+            report.updated += 1;
+        } else {
+            report.inserted += 1;
+        }
+
+        if index {
+            documents.push(row.search_document());
+        }
+    }
+
+    // quarantined topics stay out of search even when their rows are imported
+    if !documents.is_empty() {
+        match QuarantinedTopic::keys(state).await {
+            Ok(quarantined) => documents.retain(|document| match (&document.discourse_id, document.topic_id) {
+                (Some(discourse_id), Some(topic_id)) => !quarantined.contains(&(discourse_id.clone(), topic_id)),
+                _ => true,
+            }),
+            Err(e) => {
+                tracing::error!("Error loading quarantined topics, not indexing imported rows: {:?}", e);
+                documents.clear();
+            }
+        }
+    }
+
+    if let (Some(meili), false) = (&state.meili, documents.is_empty()) {
+        if let Err(e) = meili
+            .index("forum")
+            .add_documents(&documents, Some("entity_id"))
+            .await
+        {
+            tracing::error!("Error indexing imported rows in Meilisearch: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Entities mentioned in posts
+//!
+//! EIP numbers, Ethereum addresses and ENS names are pulled out of a post's cooked HTML while
+//! indexing, stored in `topic_entities` and added to the post's search document as
+//! `kind:value` keys (e.g. `eip:1559`), so topics mentioning the same entity can be linked.
+
+use std::{collections::BTreeSet, sync::LazyLock};
+
+use regex::Regex;
+
+pub const EIP: &str = "eip";
+pub const ADDRESS: &str = "address";
+pub const ENS: &str = "ens";
+
+static EIP_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\beip-(\d{1,5})\b").unwrap());
+static ADDRESS_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b0x[0-9a-f]{40}\b").unwrap());
+static ENS_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9][a-z0-9-]*(?:\.[a-z0-9][a-z0-9-]*)*\.eth\b").unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Entity {
+    pub kind: &'static str,
+    /// Normalized value, EIP numbers without leading zeros and lowercase addresses and names
+    pub value: String,
+}
+
+impl Entity {
+    /// Filterable search document key, `kind:value`
+    pub fn search_key(&self) -> String {
+        format!("{}:{}", self.kind, self.value)
+    }
+}
+
+/// Distinct entities mentioned in cooked post HTML, links included
+pub fn extract(cooked: &str) -> Vec<Entity> {
+    let mut entities = BTreeSet::new();
+
+    for captures in EIP_PATTERN.captures_iter(cooked) {
+        if let Ok(number) = captures[1].parse::<u32>() {
+            entities.insert(Entity {
+                kind: EIP,
+                value: number.to_string(),
+            });
+        }
+    }
+
+    for address in ADDRESS_PATTERN.find_iter(cooked) {
+        entities.insert(Entity {
+            kind: ADDRESS,
+            value: address.as_str().to_lowercase(),
+        });
+    }
+
+    for name in ENS_PATTERN.find_iter(cooked) {
+        entities.insert(Entity {
+            kind: ENS,
+            value: name.as_str().to_lowercase(),
+        });
+    }
+
+    entities.into_iter().collect()
+}
+
+/// Search document keys of the entities mentioned in cooked post HTML
+pub fn search_keys(cooked: &str) -> Vec<String> {
+    extract(cooked).iter().map(Entity::search_key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let cooked = r#"<p>Builds on <a href="https://eips.ethereum.org/EIPS/eip-1559">EIP-1559</a> and EIP-07702, see vitalik.eth
+            or 0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045 on <a href="https://ethresear.ch">ethresear.ch</a></p>"#;
+
+        assert_eq!(
+            search_keys(cooked),
+            vec![
+                "address:0xd8da6bf26964af9d7eed9e03e53415d37aa96045",
+                "eip:1559",
+                "eip:7702",
+                "ens:vitalik.eth",
+            ]
+        );
+        assert!(extract("<p>ethereum.org and EIP1559</p>").is_empty());
+    }
+}
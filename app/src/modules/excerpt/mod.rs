@@ -0,0 +1,109 @@
+//! Plaintext previews of posts, bios and other rich text
+//!
+//! Used wherever a short preview of content is shown (search hits, OpenGraph descriptions),
+//! so previews are stripped and cut the same way everywhere. The default length is read from
+//! `EXCERPT_MAX_CHARS`.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+const DEFAULT_MAX_CHARS: usize = 200;
+
+static MAX_CHARS: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("EXCERPT_MAX_CHARS")
+        .ok()
+        .and_then(|max_chars| max_chars.parse::<usize>().ok())
+        .filter(|max_chars| *max_chars > 0)
+        .unwrap_or(DEFAULT_MAX_CHARS)
+});
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+static MARKDOWN_MARKUP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s{0,3}(?:#{1,6}\s+|>\s?)|[*`]{1,3}|~~").unwrap());
+
+/// Configured preview length in characters, `EXCERPT_MAX_CHARS` or 200
+pub fn max_chars() -> usize {
+    *MAX_CHARS
+}
+
+/// Plaintext preview of HTML or markdown, at most `max_chars` characters
+///
+/// Cuts at the last word boundary that fits and appends an ellipsis when anything was cut.
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    truncate_words(&plaintext(text), max_chars)
+}
+
+/// Strip tags and common markdown, decode basic entities and collapse whitespace
+pub fn plaintext(text: &str) -> String {
+    // tags become spaces so words in adjacent block elements don't run together
+    let text = TAG.replace_all(text, " ");
+    let text = MARKDOWN_LINK.replace_all(&text, "$1");
+    let text = MARKDOWN_MARKUP.replace_all(&text, "");
+    let text = decode_entities(&text);
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut collapsed text to `max_chars` characters on a word boundary, with an ellipsis
+pub fn truncate_words(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    // leave room for the ellipsis
+    let limit = max_chars.saturating_sub(1);
+    let cut = match text.char_indices().nth(limit) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+
+    // back off to the last space, unless that would drop (almost) everything
+    let cut = match cut.rfind(' ') {
+        Some(space) if space >= cut.len() / 2 => &cut[..space],
+        _ => cut,
+    };
+
+    format!("{}…", cut.trim_end())
+}
+
+/// Decode the handful of entities Discourse emits in cooked HTML
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_strips_and_cuts_on_words() {
+        let cooked = "<h2>Motivation</h2><p>Raise the <strong>gas limit</strong> &amp; see <a href=\"https://eips.ethereum.org\">EIP-7935</a></p>";
+        assert_eq!(
+            plaintext(cooked),
+            "Motivation Raise the gas limit & see EIP-7935"
+        );
+        assert_eq!(excerpt(cooked, 28), "Motivation Raise the gas…");
+        assert_eq!(plaintext("## Spec\n\nSee [the EIP](https://eips.ethereum.org) **now**"), "Spec See the EIP now");
+    }
+
+    #[test]
+    fn test_truncate_words_on_char_boundary() {
+        // the cut falls right after a four byte char, byte 200 is inside it
+        let text = format!("{}🦇🔊🦇", "a".repeat(198));
+
+        let excerpt = truncate_words(&text, 200);
+
+        assert_eq!(excerpt.chars().count(), 200);
+        assert!(excerpt.ends_with("a🦇…"));
+        assert_eq!(truncate_words("gm ser", 200), "gm ser");
+    }
+}
@@ -1,8 +1,15 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
 use anyhow::Error;
-use chrono::{Duration, Utc};
+use async_std::sync::Mutex;
+use chrono::{DateTime, Duration, Utc};
 use figment::{Figment, providers::Env};
-use icalendar::{Calendar, CalendarComponent, Component};
-use serde::Deserialize;
+use icalendar::{Calendar, CalendarComponent, Component, Event};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
 use crate::{
@@ -13,6 +20,25 @@ use crate::{
 #[derive(Debug, Deserialize)]
 pub struct ICalConfig {
     pub url: String,
+    #[serde(skip)]
+    synced: Arc<Mutex<HashMap<String, SyncedEvent>>>,
+    #[serde(skip)]
+    last_sync: Arc<Mutex<Option<ICalSyncStats>>>,
+}
+
+/// Expanded occurrences of a calendar uid, along with the version they were expanded from
+#[derive(Debug, Clone)]
+struct SyncedEvent {
+    last_modified: Option<DateTime<Utc>>,
+    events: Vec<CalendarEvent>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Object)]
+pub struct ICalSyncStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
 }
 
 pub async fn init_ical(figment: Figment) -> Option<ICalConfig> {
@@ -34,51 +60,91 @@ impl ICalConfig {
         let body = response.text().await?;
 
         let cal: Calendar = body.parse().map_err(Error::msg)?;
-        let mut events: Vec<CalendarEvent> = Vec::new();
 
-        let mut overrides: std::collections::HashMap<String, std::collections::HashSet<_>> =
-            std::collections::HashMap::new();
-        for component in &cal.components {
+        // group the master event and its RECURRENCE-ID overrides by uid
+        let mut by_uid: HashMap<String, Vec<Event>> = HashMap::new();
+        let mut without_uid: Vec<Event> = Vec::new();
+        for component in cal.components {
             if let CalendarComponent::Event(event) = component {
-                if let (Some(uid), Some(recurrence_id)) = (event.get_uid(), recurrence_id(event)) {
-                    overrides
-                        .entry(uid.to_string())
-                        .or_default()
-                        .insert(recurrence_id);
+                match event.get_uid() {
+                    Some(uid) => by_uid.entry(uid.to_string()).or_default().push(event),
+                    None => without_uid.push(event),
                 }
             }
         }
 
-        // keep 365 days of history
-        let now = Utc::now() - Duration::days(365);
-        for calendar in cal.components {
-            if let CalendarComponent::Event(event) = calendar {
-                let empty = std::collections::HashSet::new();
-                let excluded_starts = event
-                    .get_uid()
-                    .and_then(|uid| overrides.get(uid))
-                    .unwrap_or(&empty);
-                let parsed_events = match CalendarEvent::from_event(event, excluded_starts) {
-                    Ok(events) => events,
-                    Err(e) => {
-                        error!("Error parsing event: {}", e);
-                        continue;
-                    }
-                };
-
-                for event in parsed_events {
-                    if let Some(start) = event.start {
-                        if start >= now {
-                            events.push(event);
-                        }
-                    }
-                }
+        let mut synced = self.synced.lock().await;
+        let mut stats = ICalSyncStats::default();
+
+        let removed: Vec<String> = synced
+            .keys()
+            .filter(|uid| !by_uid.contains_key(*uid))
+            .cloned()
+            .collect();
+        for uid in removed {
+            synced.remove(&uid);
+            stats.removed += 1;
+        }
+
+        for (uid, components) in by_uid {
+            // an override changing also changes how the master expands, so take the newest of all
+            let last_modified = components
+                .iter()
+                .filter_map(|event| event.get_last_modified())
+                .max();
+
+            let previous = synced.get(&uid);
+            let is_current = previous.is_some_and(|previous| {
+                last_modified.is_some() && previous.last_modified >= last_modified
+            });
+            if is_current {
+                stats.unchanged += 1;
+                continue;
+            }
+
+            if previous.is_some() {
+                stats.updated += 1;
+            } else {
+                stats.added += 1;
             }
+
+            synced.insert(
+                uid,
+                SyncedEvent {
+                    last_modified,
+                    events: expand_components(components),
+                },
+            );
         }
+
+        let mut all_events: Vec<CalendarEvent> = synced
+            .values()
+            .flat_map(|synced_event| synced_event.events.iter().cloned())
+            .collect();
+        all_events.extend(expand_components(without_uid));
+        drop(synced);
+
+        info!(
+            "Synced ical {}: {} added, {} updated, {} removed, {} unchanged",
+            self.url, stats.added, stats.updated, stats.removed, stats.unchanged
+        );
+        *self.last_sync.lock().await = Some(stats);
+
+        // keep 365 days of history
+        let now = Utc::now() - Duration::days(365);
+        let mut events: Vec<CalendarEvent> = all_events
+            .into_iter()
+            .filter(|event| event.start.is_some_and(|start| start >= now))
+            .collect();
         events.sort_by_key(|event| event.start.unwrap());
         Ok(events)
     }
 
+    /// Added/updated/removed counts of the most recent sync
+    pub async fn last_sync_stats(&self) -> Option<ICalSyncStats> {
+        *self.last_sync.lock().await
+    }
+
     pub async fn fetch_cached(&self, state: &AppState) -> Result<Vec<CalendarEvent>, Error> {
         let x = match state
             .cache
@@ -119,6 +185,33 @@ impl ICalConfig {
     }
 }
 
+fn expand_components(components: Vec<Event>) -> Vec<CalendarEvent> {
+    let mut overrides: HashMap<String, HashSet<_>> = HashMap::new();
+    for event in &components {
+        if let (Some(uid), Some(recurrence_id)) = (event.get_uid(), recurrence_id(event)) {
+            overrides
+                .entry(uid.to_string())
+                .or_default()
+                .insert(recurrence_id);
+        }
+    }
+
+    let mut events = Vec::new();
+    let empty = HashSet::new();
+    for event in components {
+        let excluded_starts = event
+            .get_uid()
+            .and_then(|uid| overrides.get(uid))
+            .unwrap_or(&empty);
+        match CalendarEvent::from_event(event, excluded_starts) {
+            Ok(parsed_events) => events.extend(parsed_events),
+            Err(e) => error!("Error parsing event: {}", e),
+        }
+    }
+
+    events
+}
+
 #[cfg(test)]
 mod tests {
     use figment::providers::Env;
@@ -1,5 +1,81 @@
+use figment::{Figment, providers::Env};
+use meilisearch_sdk::{
+    documents::DocumentDeletionQuery, errors::Error as MeiliError, indexes::Index,
+    settings::Settings, task_info::TaskInfo,
+};
+use serde::Deserialize;
+
 pub use meilisearch_sdk::client::Client;
 
+/// Settings applied to the `forum` index on startup, lists are comma separated
+#[derive(Debug, Deserialize)]
+pub struct MeiliIndexConfig {
+    #[serde(default = "default_searchable_attributes")]
+    pub searchable_attributes: String,
+    #[serde(default = "default_filterable_attributes")]
+    pub filterable_attributes: String,
+    #[serde(default = "default_sortable_attributes")]
+    pub sortable_attributes: String,
+    /// Leave unset to keep Meilisearch's default ranking rules
+    pub ranking_rules: Option<String>,
+}
+
+fn default_searchable_attributes() -> String {
+    "title,cooked,code,slug,username".to_string()
+}
+
+fn default_filterable_attributes() -> String {
+    "entity_type,topic_id,user_id,username,pm_issue,post_id,discourse_id,entities".to_string()
+}
+
+fn default_sortable_attributes() -> String {
+    "topic_id,post_id,post_number".to_string()
+}
+
+impl Default for MeiliIndexConfig {
+    fn default() -> Self {
+        Self {
+            searchable_attributes: default_searchable_attributes(),
+            filterable_attributes: default_filterable_attributes(),
+            sortable_attributes: default_sortable_attributes(),
+            ranking_rules: None,
+        }
+    }
+}
+
+impl MeiliIndexConfig {
+    pub fn load() -> Self {
+        Figment::new()
+            .merge(Env::prefixed("MEILI_INDEX_"))
+            .extract::<MeiliIndexConfig>()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Invalid Meilisearch index config, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn to_settings(&self) -> Settings {
+        let mut settings = Settings::new()
+            .with_searchable_attributes(split_list(&self.searchable_attributes))
+            .with_filterable_attributes(split_list(&self.filterable_attributes))
+            .with_sortable_attributes(split_list(&self.sortable_attributes));
+
+        if let Some(ranking_rules) = &self.ranking_rules {
+            settings = settings.with_ranking_rules(split_list(ranking_rules));
+        }
+
+        settings
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 pub async fn init_meili() -> Option<Client> {
     match (std::env::var("MEILI_HOST"), std::env::var("MEILI_KEY")) {
         (Ok(meili_url), Ok(meili_key)) => {
@@ -8,13 +84,13 @@ pub async fn init_meili() -> Option<Client> {
             match client.get_version().await {
                 Ok(version) => {
                     tracing::info!("Connected to MeiliSearch: version {}", version.commit_sha);
-                    
+
                     // Configure the forum index
                     if let Err(e) = configure_forum_index(&client).await {
                         tracing::error!("Failed to configure forum index: {}", e);
                         return None;
                     }
-                    
+
                     Some(client)
                 }
                 Err(e) => {
@@ -27,59 +103,142 @@ pub async fn init_meili() -> Option<Client> {
     }
 }
 
-async fn configure_forum_index(client: &Client) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// Returns the last settings task, tasks of an index run in order so waiting on it waits for all
+pub async fn configure_forum_index(client: &Client) -> Result<TaskInfo, Box<dyn std::error::Error + Send + Sync>> {
     let mut forum_index = client.index("forum");
-    
-    // Set filterable attributes for the forum index
-    let filterable_attributes = vec![
-        "entity_type".to_string(),
-        "topic_id".to_string(), 
-        "user_id".to_string(),
-        "username".to_string(),
-        "pm_issue".to_string(),
-        "post_id".to_string(),
-        "discourse_id".to_string(),
-    ];
-    
-    // Set searchable attributes for better search experience
-    let searchable_attributes = vec![
-        "title".to_string(),
-        "cooked".to_string(),
-        "slug".to_string(),
-        "username".to_string(),
-    ];
-    
-    // Configure the index settings
-    match forum_index.set_filterable_attributes(&filterable_attributes).await {
-        Ok(_) => {
-            tracing::info!("Successfully configured filterable attributes for forum index");
-        }
-        Err(e) => {
-            tracing::error!("Failed to set filterable attributes: {}", e);
-            return Err(Box::new(e));
-        }
-    }
-    
-    match forum_index.set_searchable_attributes(&searchable_attributes).await {
+    let config = MeiliIndexConfig::load();
+
+    // Settings are applied as a whole, so re-applying the same config is a no-op
+    match forum_index.set_settings(&config.to_settings()).await {
         Ok(_) => {
-            tracing::info!("Successfully configured searchable attributes for forum index");
+            tracing::info!(
+                "Applied forum index settings: searchable [{}], filterable [{}], sortable [{}], ranking rules [{}]",
+                config.searchable_attributes,
+                config.filterable_attributes,
+                config.sortable_attributes,
+                config.ranking_rules.as_deref().unwrap_or("default"),
+            );
         }
         Err(e) => {
-            tracing::error!("Failed to set searchable attributes: {}", e);
+            tracing::error!("Failed to apply forum index settings: {}", e);
             return Err(Box::new(e));
         }
     }
-    
+
     // Set the primary key for the index
     match forum_index.set_primary_key("entity_id").await {
-        Ok(_) => {
+        Ok(task) => {
             tracing::info!("Successfully configured primary key for forum index");
+            Ok(task)
         }
         Err(e) => {
             tracing::error!("Failed to set primary key: {}", e);
-            return Err(Box::new(e));
+            Err(Box::new(e))
         }
     }
-    
-    Ok(())
+}
+
+/// Delete every document matching a Meilisearch filter expression
+pub async fn delete_by_filter(index: &Index, filter: &str) -> Result<TaskInfo, MeiliError> {
+    let mut query = DocumentDeletionQuery::new(index);
+    query.with_filter(filter);
+    index.delete_documents_with(&query).await
+}
+
+/// Delete the topic document and all post documents of a topic
+pub async fn delete_topic_documents(
+    index: &Index,
+    discourse_id: &str,
+    topic_id: i32,
+) -> Result<TaskInfo, MeiliError> {
+    let filter = format!("discourse_id = \"{}\" AND topic_id = {}", discourse_id, topic_id);
+    delete_by_filter(index, &filter).await
+}
+
+/// Delete post documents of a topic whose post no longer exists in the topic
+pub async fn delete_orphaned_post_documents(
+    index: &Index,
+    discourse_id: &str,
+    topic_id: i32,
+    post_ids: &[i32],
+) -> Result<TaskInfo, MeiliError> {
+    let mut filter = format!(
+        "entity_type = post AND discourse_id = \"{}\" AND topic_id = {}",
+        discourse_id, topic_id
+    );
+
+    if !post_ids.is_empty() {
+        let post_ids = post_ids
+            .iter()
+            .map(|post_id| post_id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        filter.push_str(&format!(" AND post_id NOT IN [{}]", post_ids));
+    }
+
+    delete_by_filter(index, &filter).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct TestDocument {
+        entity_id: String,
+        entity_type: String,
+        discourse_id: String,
+        topic_id: i32,
+        post_id: i32,
+    }
+
+    #[async_std::test]
+    async fn test_reindex_leaves_no_orphaned_posts() {
+        dotenvy::dotenv().ok();
+        let Some(client) = init_meili().await else {
+            tracing::warn!("Meilisearch is not configured, skipping");
+            return;
+        };
+
+        let index = client.index("forum_test_orphans");
+        index
+            .set_filterable_attributes(["entity_type", "discourse_id", "topic_id", "post_id"])
+            .await
+            .unwrap()
+            .wait_for_completion(&client, None, None)
+            .await
+            .unwrap();
+
+        let documents = (1..=3)
+            .map(|post_id| TestDocument {
+                entity_id: format!("post_{}", post_id),
+                entity_type: "post".to_string(),
+                discourse_id: "magicians".to_string(),
+                topic_id: 1,
+                post_id,
+            })
+            .collect::<Vec<_>>();
+        index
+            .add_documents(&documents, Some("entity_id"))
+            .await
+            .unwrap()
+            .wait_for_completion(&client, None, None)
+            .await
+            .unwrap();
+
+        // the topic was reindexed and post 3 is gone
+        delete_orphaned_post_documents(&index, "magicians", 1, &[1, 2])
+            .await
+            .unwrap()
+            .wait_for_completion(&client, None, None)
+            .await
+            .unwrap();
+
+        let stats = index.get_stats().await.unwrap();
+        assert_eq!(stats.number_of_documents, 2);
+
+        index.delete().await.unwrap();
+    }
 }
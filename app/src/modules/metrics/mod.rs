@@ -0,0 +1,200 @@
+use opentelemetry::{
+    KeyValue,
+    metrics::{Counter, Gauge, MeterProvider},
+};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// Application metrics, exported in the Prometheus text format on `/metrics`
+///
+/// Attributes only ever carry configured names (instances, caches, providers),
+/// never user content, the export is not covered by log redaction.
+pub struct Metrics {
+    registry: Registry,
+    // kept alive so the instruments keep reporting
+    _provider: SdkMeterProvider,
+    pub indexer: IndexerMetrics,
+    pub cache: CacheMetrics,
+    pub ai: AiMetrics,
+}
+
+/// Throughput of the discourse indexers, labelled by `discourse_id`
+#[derive(Clone)]
+pub struct IndexerMetrics {
+    topics_indexed: Counter<u64>,
+    posts_indexed: Counter<u64>,
+    fetch_errors: Counter<u64>,
+    queue_depth: Gauge<u64>,
+    queue_dropped: Counter<u64>,
+}
+
+/// Hits and misses of in-memory caches, labelled by `cache`
+#[derive(Clone)]
+pub struct CacheMetrics {
+    hits: Counter<u64>,
+    misses: Counter<u64>,
+}
+
+/// Health of the OpenAI-compatible provider as seen by the workshop circuit breaker
+#[derive(Clone)]
+pub struct AiMetrics {
+    breaker_state: Gauge<u64>,
+    breaker_rejections: Counter<u64>,
+    provider_requests: Counter<u64>,
+    provider_failovers: Counter<u64>,
+}
+
+impl Metrics {
+    /// # Panics
+    /// Panics if the Prometheus exporter cannot be registered.
+    pub fn init() -> Self {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()
+            .expect("Failed to create Prometheus exporter");
+
+        let provider = SdkMeterProvider::builder().with_reader(exporter).build();
+        let meter = provider.meter("ethereum-forum");
+
+        let indexer = IndexerMetrics {
+            topics_indexed: meter
+                .u64_counter("discourse_topics_indexed")
+                .with_description("Topics upserted by the indexer")
+                .build(),
+            posts_indexed: meter
+                .u64_counter("discourse_posts_indexed")
+                .with_description("Posts upserted by the indexer")
+                .build(),
+            fetch_errors: meter
+                .u64_counter("discourse_fetch_errors")
+                .with_description("Failed requests to a discourse instance")
+                .build(),
+            queue_depth: meter
+                .u64_gauge("indexer_queue_depth")
+                .with_description("Topic pages waiting to be indexed")
+                .build(),
+            queue_dropped: meter
+                .u64_counter("indexer_queue_dropped")
+                .with_description("Topic pages dropped because the indexer queue was full")
+                .build(),
+        };
+
+        let cache = CacheMetrics {
+            hits: meter
+                .u64_counter("cache_hits")
+                .with_description("Lookups answered from a cache")
+                .build(),
+            misses: meter
+                .u64_counter("cache_misses")
+                .with_description("Lookups that had to be fetched")
+                .build(),
+        };
+
+        let ai = AiMetrics {
+            breaker_state: meter
+                .u64_gauge("workshop_ai_breaker_state")
+                .with_description("Circuit breaker state, 0 closed, 1 half-open, 2 open")
+                .build(),
+            breaker_rejections: meter
+                .u64_counter("workshop_ai_breaker_rejections")
+                .with_description("Prompts rejected while the circuit breaker was open")
+                .build(),
+            provider_requests: meter
+                .u64_counter("workshop_ai_provider_requests")
+                .with_description("Completions served, labelled by provider")
+                .build(),
+            provider_failovers: meter
+                .u64_counter("workshop_ai_provider_failovers")
+                .with_description("Requests moved on to the next provider, labelled by the failed one")
+                .build(),
+        };
+
+        Self {
+            registry,
+            _provider: provider,
+            indexer,
+            cache,
+            ai,
+        }
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+impl IndexerMetrics {
+    pub fn topic_indexed(&self, discourse_id: &str) {
+        self.topics_indexed.add(1, &labels(discourse_id));
+    }
+
+    pub fn posts_indexed(&self, discourse_id: &str, count: u64) {
+        self.posts_indexed.add(count, &labels(discourse_id));
+    }
+
+    pub fn fetch_error(&self, discourse_id: &str) {
+        self.fetch_errors.add(1, &labels(discourse_id));
+    }
+
+    pub fn queue_depth(&self, discourse_id: &str, depth: usize) {
+        self.queue_depth.record(depth as u64, &labels(discourse_id));
+    }
+
+    pub fn queue_dropped(&self, discourse_id: &str) {
+        self.queue_dropped.add(1, &labels(discourse_id));
+    }
+}
+
+impl CacheMetrics {
+    /// Record a lookup, `fresh` being true when the value had to be fetched
+    pub fn record(&self, cache: &'static str, fresh: bool) {
+        let labels = [KeyValue::new("cache", cache)];
+        if fresh {
+            self.misses.add(1, &labels);
+        } else {
+            self.hits.add(1, &labels);
+        }
+    }
+}
+
+impl AiMetrics {
+    pub fn breaker_state(&self, state: u64) {
+        self.breaker_state.record(state, &[]);
+    }
+
+    pub fn breaker_rejected(&self) {
+        self.breaker_rejections.add(1, &[]);
+    }
+
+    pub fn provider_served(&self, provider: &str) {
+        self.provider_requests.add(1, &[KeyValue::new("provider", provider.to_string())]);
+    }
+
+    pub fn provider_failed_over(&self, provider: &str) {
+        self.provider_failovers.add(1, &[KeyValue::new("provider", provider.to_string())]);
+    }
+}
+
+fn labels(discourse_id: &str) -> [KeyValue; 1] {
+    [KeyValue::new("discourse_id", discourse_id.to_string())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexer_metrics_are_exported() {
+        let metrics = Metrics::init();
+        metrics.indexer.topic_indexed("magicians");
+        metrics.indexer.posts_indexed("magicians", 3);
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains("discourse_posts_indexed_total{discourse_id=\"magicians\""));
+    }
+}
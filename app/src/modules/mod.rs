@@ -1,6 +1,15 @@
+pub mod code;
 pub mod discourse;
+pub mod dump;
+pub mod entities;
+pub mod excerpt;
 pub mod ical;
 pub mod meili;
+pub mod metrics;
+pub mod notifications;
 pub mod pm;
+pub mod redact;
+pub mod reindex;
+pub mod schedule;
 pub mod sso;
 pub mod workshop;
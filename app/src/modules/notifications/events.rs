@@ -0,0 +1,150 @@
+//! Signed platform events for registered integrations
+//!
+//! Every active endpoint subscribed to an event gets a JSON POST with the event name, the
+//! delivery id and the event data. The raw body is signed with the endpoint's secret as
+//! `X-Forum-Signature: sha256=<hex hmac>`, the same scheme Discourse uses for its webhooks.
+//! Deliveries are logged in `webhook_deliveries` and retried like subscription notifications.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use super::{CLIENT, RETRY_BASE_DELAY, max_attempts};
+use crate::{
+    models::webhooks::{WebhookDelivery, WebhookEndpoint},
+    state::AppState,
+};
+
+pub const TOPIC_CREATED: &str = "topic.created";
+pub const SUMMARY_READY: &str = "summary.ready";
+
+/// Events integrations can subscribe to
+pub const EVENTS: &[&str] = &[TOPIC_CREATED, SUMMARY_READY];
+
+/// Send an event to every endpoint subscribed to it, in the background
+pub fn dispatch(event: &'static str, data: serde_json::Value, state: &AppState) {
+    let state = state.clone();
+
+    async_std::task::spawn(async move {
+        let endpoints = match WebhookEndpoint::find_subscribed(event, &state).await {
+            Ok(endpoints) => endpoints,
+            Err(e) => {
+                warn!("Error loading webhook endpoints for {}: {:?}", event, e);
+                return;
+            }
+        };
+
+        for endpoint in endpoints {
+            let state = state.clone();
+            let data = data.clone();
+            async_std::task::spawn(async move {
+                deliver(endpoint, event, data, &state).await;
+            });
+        }
+    });
+}
+
+async fn deliver(endpoint: WebhookEndpoint, event: &str, data: serde_json::Value, state: &AppState) {
+    let mut payload = json!({
+        "event": event,
+        "created_at": Utc::now(),
+        "data": data,
+    });
+
+    let delivery = match WebhookDelivery::create(endpoint.endpoint_id, event, &payload, state).await {
+        Ok(delivery) => delivery,
+        Err(e) => {
+            warn!("Error logging webhook delivery to endpoint {}: {:?}", endpoint.endpoint_id, e);
+            return;
+        }
+    };
+    payload["delivery_id"] = json!(delivery.delivery_id);
+
+    let body = payload.to_string();
+    let signature = sign(&endpoint.secret, body.as_bytes());
+    let max_attempts = max_attempts();
+
+    for attempt in 1..=max_attempts {
+        let result = CLIENT
+            .post(&endpoint.url)
+            .header("Content-Type", "application/json")
+            .header("X-Forum-Event", event)
+            .header("X-Forum-Delivery", delivery.delivery_id.to_string())
+            .header("X-Forum-Signature", &signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16() as i32), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16() as i32),
+                Some(format!("status {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let status = match (&error, attempt == max_attempts) {
+            (None, _) => "delivered",
+            (Some(_), true) => "failed",
+            (Some(_), false) => "pending",
+        };
+
+        if let Err(e) = WebhookDelivery::record_attempt(
+            delivery.delivery_id,
+            status,
+            response_status,
+            error.as_deref(),
+            state,
+        )
+        .await
+        {
+            warn!("Error updating webhook delivery {}: {:?}", delivery.delivery_id, e);
+        }
+
+        match error {
+            None => return,
+            Some(error) if attempt == max_attempts => {
+                warn!(
+                    "Giving up on webhook delivery {} to endpoint {} after {} attempts: {}",
+                    delivery.delivery_id, endpoint.endpoint_id, attempt, error
+                );
+            }
+            Some(error) => {
+                let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                info!(
+                    "Webhook delivery {} failed ({}), retrying in {:?}",
+                    delivery.delivery_id, error, delay
+                );
+                async_std::task::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC of the body
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // echo -n '{"event":"topic.created"}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", br#"{"event":"topic.created"}"#),
+            "sha256=7479e30aa905f6987c640a5ca6323bd27e472841da6d46ee278e4f12f668af99"
+        );
+    }
+}
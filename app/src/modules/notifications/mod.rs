@@ -0,0 +1,267 @@
+//! Outbound webhooks for topic subscriptions
+//!
+//! When the indexer stores new posts on a watched topic, every subscriber's webhook receives a
+//! `topic.new_posts` delivery. Deliveries are best-effort: failed ones are retried with
+//! exponential backoff up to `NOTIFICATION_MAX_ATTEMPTS` (default 3) and then dropped.
+//!
+//! Platform events for integrations are dispatched from `events`.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use async_std::net::ToSocketAddrs;
+use reqwest::{
+    Client, ClientBuilder,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+};
+use serde::Serialize;
+use tracing::{info, warn};
+use url::{Host, Url};
+
+use crate::{
+    models::topics::{post::Post, subscription::TopicSubscription},
+    modules::excerpt,
+    state::AppState,
+};
+
+pub mod events;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(5);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    ClientBuilder::new()
+        .use_rustls_tls()
+        .timeout(DELIVERY_TIMEOUT)
+        // a redirect could point the delivery at an internal address
+        .redirect(Policy::none())
+        // and so could a public name resolving to one
+        .dns_resolver(Arc::new(PublicResolver))
+        .user_agent("ethereum-forum-notifications")
+        .build()
+        .expect("Failed to build notification client")
+});
+
+fn max_attempts() -> u32 {
+    std::env::var("NOTIFICATION_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|attempts| attempts.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+        .max(1)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewPost {
+    pub post_id: i32,
+    pub post_number: i32,
+    pub username: Option<String>,
+    pub excerpt: String,
+    pub url: Option<String>,
+}
+
+impl NewPost {
+    pub fn from_post(post: &Post, discourse_url: Option<&str>) -> Self {
+        Self {
+            post_id: post.post_id,
+            post_number: post.post_number,
+            username: post
+                .extra
+                .as_ref()
+                .and_then(|extra| extra.get("username"))
+                .and_then(|username| username.as_str())
+                .map(String::from),
+            excerpt: post
+                .cooked
+                .as_deref()
+                .map(|cooked| excerpt::excerpt(cooked, excerpt::max_chars()))
+                .unwrap_or_default(),
+            url: discourse_url
+                .map(|url| format!("{}/t/{}/{}", url, post.topic_id, post.post_number)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NewPostsPayload<'a> {
+    event: &'static str,
+    discourse_id: &'a str,
+    topic_id: i32,
+    title: &'a str,
+    posts: &'a [NewPost],
+}
+
+/// Queue a delivery of the new posts to everyone subscribed to the topic
+pub async fn notify_new_posts(
+    discourse_id: &str,
+    topic_id: i32,
+    title: &str,
+    posts: &[NewPost],
+    state: &AppState,
+) {
+    if posts.is_empty() {
+        return;
+    }
+
+    let subscriptions = match TopicSubscription::find_by_topic(discourse_id, topic_id, state).await {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => {
+            warn!("Error loading subscriptions of topic {}: {:?}", topic_id, e);
+            return;
+        }
+    };
+    if subscriptions.is_empty() {
+        return;
+    }
+
+    let payload = NewPostsPayload {
+        event: "topic.new_posts",
+        discourse_id,
+        topic_id,
+        title,
+        posts,
+    };
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Error serializing notification payload: {:?}", e);
+            return;
+        }
+    };
+
+    info!(
+        "Notifying {} subscribers of {} new posts on topic {}",
+        subscriptions.len(),
+        posts.len(),
+        topic_id
+    );
+
+    for subscription in subscriptions {
+        let body = body.clone();
+        async_std::task::spawn(async move {
+            deliver(&subscription, body).await;
+        });
+    }
+}
+
+async fn deliver(subscription: &TopicSubscription, body: String) {
+    let max_attempts = max_attempts();
+
+    for attempt in 1..=max_attempts {
+        let result = CLIENT
+            .post(&subscription.webhook_url)
+            .header("Content-Type", "application/json")
+            .body(body.clone())
+            .send()
+            .await;
+
+        let error = match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == max_attempts {
+            warn!(
+                "Giving up on notification for subscription {} after {} attempts: {}",
+                subscription.subscription_id, attempt, error
+            );
+        } else {
+            let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+            info!(
+                "Notification for subscription {} failed ({}), retrying in {:?}",
+                subscription.subscription_id, error, delay
+            );
+            async_std::task::sleep(delay).await;
+        }
+    }
+}
+
+/// Check a user supplied webhook URL, only public https endpoints are accepted
+pub fn validate_webhook_url(webhook_url: &str) -> Result<Url, &'static str> {
+    let url = Url::parse(webhook_url).map_err(|_| "invalid URL")?;
+
+    if url.scheme() != "https" {
+        return Err("webhook URL must use https");
+    }
+
+    let is_internal = match url.host() {
+        None => true,
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost") || domain.ends_with(".internal")
+        }
+        Some(Host::Ipv4(ip)) => is_internal_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_internal_ip(IpAddr::V6(ip)),
+    };
+    if is_internal {
+        return Err("webhook URL must point to a public host");
+    }
+
+    Ok(url)
+}
+
+/// Resolves webhook hosts at connect time and refuses names with any internal address, the
+/// URL check alone can't see where a name points or will point by the time we deliver
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = (name.as_str(), 0).to_socket_addrs().await?.collect();
+
+            if addrs.is_empty() || addrs.iter().any(|addr| is_internal_ip(addr.ip())) {
+                return Err(format!("{} does not resolve to a public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // carrier-grade NAT, 100.64.0.0/10
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // unique local (fc00::/7) and link local (fe80::/10)
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://hooks.example.com/forum").is_ok());
+        assert!(validate_webhook_url("http://hooks.example.com/forum").is_err());
+        assert!(validate_webhook_url("https://localhost:8080/").is_err());
+        assert!(validate_webhook_url("https://10.1.2.3/").is_err());
+        assert!(validate_webhook_url("https://[::1]/").is_err());
+        assert!(validate_webhook_url("https://[::ffff:192.168.1.1]/").is_err());
+        assert!(validate_webhook_url("not a url").is_err());
+    }
+
+    #[async_std::test]
+    async fn test_resolver_rejects_internal_addresses() {
+        let name: Name = "localhost".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+}
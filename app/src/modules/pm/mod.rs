@@ -1,11 +1,17 @@
+use std::collections::{HashMap, HashSet};
+
 use crate::{
-    models::pm::{PMData, PMMeetingData},
+    models::{
+        pm::{PMData, PMMeeting, PMMeetingData},
+        topics::{Topic, quarantine::QuarantinedTopic},
+    },
+    modules::entities,
     state::AppState,
 };
 use anyhow::Error;
 use chrono::{DateTime, Utc};
 use reqwest::ClientBuilder;
-use tracing::error;
+use tracing::{error, info};
 
 #[derive(Debug, Clone, Default)]
 pub struct PMModule;
@@ -47,6 +53,149 @@ impl PMModule {
         Ok(x)
     }
 
+    /// Correlate calendar events with ethereum/pm issues and the forum topics linking to them
+    ///
+    /// Calls from the ethereum/pm mapping that are not on the calendar (older ones mostly)
+    /// are included as well, sorted by start time.
+    pub async fn index_meetings(&self, state: &AppState) -> Result<Vec<PMMeeting>, Error> {
+        let pm_data = self.get_pm_data_from_cache(state).await?;
+        let mut meetings = Vec::new();
+        let mut seen_issues = HashSet::new();
+
+        if let Some(ical) = &state.ical {
+            for event in ical.fetch_cached(state).await? {
+                let rich = event.rich(state).await?;
+                let event = rich.calendar_event;
+
+                if let Some(issue_number) = rich.pm_number {
+                    seen_issues.insert(issue_number);
+                }
+
+                meetings.push(PMMeeting::new(
+                    event.summary,
+                    event.start,
+                    event.uid,
+                    rich.pm_data.as_ref(),
+                    rich.pm_number,
+                ));
+            }
+        }
+
+        for meeting_data in pm_data.values() {
+            match meeting_data {
+                PMMeetingData::Recurring(recurring) => {
+                    for occurrence in recurring.occurrences.iter().flatten() {
+                        let Some(issue_number) = occurrence.issue_number else {
+                            continue;
+                        };
+                        if seen_issues.insert(issue_number) {
+                            meetings.push(PMMeeting::new(
+                                occurrence.issue_title.clone(),
+                                occurrence.start_time,
+                                None,
+                                Some(meeting_data),
+                                Some(issue_number),
+                            ));
+                        }
+                    }
+                }
+                PMMeetingData::OneOff(one_off) => {
+                    let Some(issue_number) = one_off.issue_number else {
+                        continue;
+                    };
+                    if seen_issues.insert(issue_number) {
+                        meetings.push(PMMeeting::new(
+                            one_off.issue_title.clone(),
+                            one_off.start_time,
+                            None,
+                            Some(meeting_data),
+                            Some(issue_number),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let issue_numbers: Vec<i32> = seen_issues
+            .iter()
+            .filter_map(|issue| i32::try_from(*issue).ok())
+            .collect();
+        let mut topics_by_issue: HashMap<u32, Vec<Topic>> = HashMap::new();
+        for topic in Topic::get_by_pm_issues(&issue_numbers, state).await? {
+            if let Some(issue_number) = topic.pm_issue.and_then(|issue| u32::try_from(issue).ok()) {
+                topics_by_issue.entry(issue_number).or_default().push(topic);
+            }
+        }
+
+        for meeting in &mut meetings {
+            if let Some(topics) = meeting
+                .issue_number
+                .and_then(|issue| topics_by_issue.get(&issue))
+            {
+                meeting.topics = topics.clone();
+            }
+        }
+
+        meetings.sort_by_key(|meeting| meeting.start);
+
+        info!(
+            "Indexed {} PM meetings ({} linked issues)",
+            meetings.len(),
+            seen_issues.len()
+        );
+
+        Ok(meetings)
+    }
+
+    pub async fn get_meetings_from_cache(&self, state: &AppState) -> Result<Vec<PMMeeting>, Error> {
+        match state
+            .cache
+            .pm_meetings_cache
+            .try_get_with("pm_meetings".to_string(), self.index_meetings(state))
+            .await
+        {
+            Ok(mut meetings) => {
+                // topics may have been quarantined after the meetings were cached
+                let quarantined = QuarantinedTopic::keys(state).await?;
+                for meeting in &mut meetings {
+                    meeting.topics.retain(|topic| {
+                        !quarantined.contains(&(topic.discourse_id.clone(), topic.topic_id))
+                    });
+                }
+                Ok(meetings)
+            }
+            Err(e) => {
+                error!("Error indexing pm meetings: {}", e);
+                Err(anyhow::anyhow!("Error indexing pm meetings: {}", e))
+            }
+        }
+    }
+
+    /// Calls with an ethereum/pm issue whose title mentions an entity, most recent first
+    pub async fn meetings_mentioning(
+        &self,
+        kind: &str,
+        value: &str,
+        state: &AppState,
+    ) -> Result<Vec<PMMeeting>, Error> {
+        let mut meetings: Vec<PMMeeting> = self
+            .get_meetings_from_cache(state)
+            .await?
+            .into_iter()
+            .filter(|meeting| meeting.issue_number.is_some())
+            .filter(|meeting| {
+                meeting.title.as_deref().is_some_and(|title| {
+                    entities::extract(title)
+                        .iter()
+                        .any(|entity| entity.kind == kind && entity.value == value)
+                })
+            })
+            .collect();
+
+        meetings.sort_by(|a, b| b.start.cmp(&a.start));
+        Ok(meetings)
+    }
+
     pub async fn get_by_issue_id(&self, issue_id: u32) -> Result<PMMeetingData, Error> {
         let pm_data = self.get_pm_data().await?;
         let meeting_data = pm_data.values().find(|meeting| {
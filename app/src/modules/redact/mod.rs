@@ -0,0 +1,61 @@
+//! Redaction of user content in logs
+//!
+//! Messages, responses, tool arguments and tool results are replaced with a length-only
+//! placeholder in all `tracing` output unless `LOG_REDACT=false`. Structural information
+//! (ids, counts, models, tool names) is logged either way.
+
+use std::sync::LazyLock;
+
+static ENABLED: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var("LOG_REDACT")
+        .map(|value| !matches!(value.to_lowercase().as_str(), "false" | "0" | "off"))
+        .unwrap_or(true)
+});
+
+/// Whether user content is kept out of the logs, on unless `LOG_REDACT=false`
+pub fn enabled() -> bool {
+    *ENABLED
+}
+
+/// The first `max_chars` of a text for the logs, or a placeholder when redacting
+pub fn preview(text: &str, max_chars: usize) -> String {
+    if enabled() {
+        placeholder(text)
+    } else {
+        truncate(text, max_chars).to_string()
+    }
+}
+
+/// The first `max_chars` characters of a text, never cutting a character in half
+pub fn truncate(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Length-only stand-in for a text
+pub fn placeholder(text: &str) -> String {
+    format!("[{} chars redacted]", text.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder_keeps_only_the_length() {
+        assert_eq!(placeholder("gm, my address is 0xabc"), "[23 chars redacted]");
+        assert_eq!(placeholder("ßü"), "[2 chars redacted]");
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        // byte 200 falls inside the 🦇
+        let text = format!("{}🦇🔊", "a".repeat(198));
+
+        assert_eq!(truncate(&text, 199), format!("{}🦇", "a".repeat(198)));
+        assert_eq!(truncate(&text, 500), text);
+        assert_eq!(truncate("Ξther", 1), "Ξ");
+    }
+}
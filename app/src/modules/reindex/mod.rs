@@ -0,0 +1,539 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::{Sender, unbounded},
+    sync::{Mutex, RwLock},
+};
+use chrono::{DateTime, Utc};
+use figment::{Figment, providers::Env};
+use futures::{Stream, StreamExt, stream};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::query_as;
+use strip_tags::strip_tags;
+use tracing::{error, info, warn};
+
+use crate::{
+    models::topics::{Topic, entity::TopicEntity, post::Post, quarantine::QuarantinedTopic},
+    modules::{code, discourse::ForumSearchDocument, entities},
+    state::AppState,
+};
+
+/// How long a reindex waits for Meilisearch to process its documents before counting them
+const INDEXING_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct ReindexResponse {
+    pub success: bool,
+    pub message: String,
+    pub topics_processed: i32,
+    pub posts_processed: i32,
+    pub errors: i32,
+    /// Documents in the index once the reindex finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meilisearch_documents: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct ReindexJobStatus {
+    pub job_id: String,
+    pub done: bool,
+    pub topics_processed: i32,
+    pub posts_processed: i32,
+    pub errors: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_batch: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_batches: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The final result, available once the job is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ReindexResponse>,
+}
+
+pub struct ReindexJob {
+    status: RwLock<ReindexJobStatus>,
+    senders: Mutex<Vec<Sender<ReindexJobStatus>>>,
+}
+
+impl ReindexJob {
+    fn new(job_id: String) -> Self {
+        Self {
+            status: RwLock::new(ReindexJobStatus {
+                job_id,
+                done: false,
+                topics_processed: 0,
+                posts_processed: 0,
+                errors: 0,
+                current_batch: None,
+                total_batches: None,
+                started_at: Utc::now(),
+                finished_at: None,
+                result: None,
+            }),
+            senders: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn status(&self) -> ReindexJobStatus {
+        self.status.read().await.clone()
+    }
+
+    /// Stream of progress updates, starting with the current status and ending after the final summary
+    pub async fn subscribe(&self) -> impl Stream<Item = ReindexJobStatus> + Send + 'static {
+        // hold the status lock so no update slips in between the snapshot and subscribing
+        let status = self.status.read().await;
+        let current = status.clone();
+
+        if current.done {
+            return stream::once(async { current }).boxed();
+        }
+
+        let (sender, receiver) = unbounded();
+        self.senders.lock().await.push(sender);
+        drop(status);
+
+        stream::once(async { current }).chain(receiver).boxed()
+    }
+
+    async fn update(&self, f: impl FnOnce(&mut ReindexJobStatus)) {
+        let mut status = self.status.write().await;
+        f(&mut status);
+
+        let mut senders = self.senders.lock().await;
+        senders.retain(|sender| sender.try_send(status.clone()).is_ok());
+
+        // the final summary has been sent, closing the channels ends the streams
+        if status.done {
+            senders.clear();
+        }
+    }
+
+    async fn finish(&self, result: ReindexResponse) {
+        self.update(|status| {
+            status.done = true;
+            status.finished_at = Some(Utc::now());
+            status.topics_processed = result.topics_processed;
+            status.posts_processed = result.posts_processed;
+            status.errors = result.errors;
+            status.result = Some(result);
+        })
+        .await;
+    }
+}
+
+#[derive(Debug)]
+pub enum ReindexError {
+    MeiliNotConfigured,
+    AlreadyRunning(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReindexConfig {
+    /// Number of posts sent to Meilisearch per request
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Number of batches in flight at once
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+impl Default for ReindexConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: default_batch_size(),
+            concurrency: default_concurrency(),
+        }
+    }
+}
+
+/// Tracks full reindex jobs, only one of which may run at a time
+pub struct ReindexService {
+    config: ReindexConfig,
+    jobs: RwLock<HashMap<String, Arc<ReindexJob>>>,
+    running: RwLock<Option<String>>,
+}
+
+impl ReindexService {
+    pub fn new(figment: Figment) -> Self {
+        let config = figment
+            .merge(Env::prefixed("REINDEX_"))
+            .extract::<ReindexConfig>()
+            .unwrap_or_else(|e| {
+                warn!("Invalid reindex config, using defaults: {}", e);
+                ReindexConfig::default()
+            });
+
+        Self {
+            config,
+            jobs: RwLock::new(HashMap::new()),
+            running: RwLock::new(None),
+        }
+    }
+
+    /// Spawn a full reindex in the background, returning the job to poll
+    pub async fn start(&self, state: &AppState) -> Result<Arc<ReindexJob>, ReindexError> {
+        if state.meili.is_none() {
+            return Err(ReindexError::MeiliNotConfigured);
+        }
+
+        let mut running = self.running.write().await;
+        if let Some(job_id) = running.as_ref() {
+            return Err(ReindexError::AlreadyRunning(job_id.clone()));
+        }
+
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let job = Arc::new(ReindexJob::new(job_id.clone()));
+        self.jobs.write().await.insert(job_id.clone(), job.clone());
+        *running = Some(job_id.clone());
+        drop(running);
+
+        let state = state.clone();
+        let spawned_job = job.clone();
+        async_std::task::spawn(async move {
+            let result = reindex_all(&state, &spawned_job, &state.reindex.config).await;
+            spawned_job.finish(result).await;
+            *state.reindex.running.write().await = None;
+            info!("Reindex job {} finished", job_id);
+        });
+
+        Ok(job)
+    }
+
+    pub async fn is_running(&self) -> bool {
+        self.running.read().await.is_some()
+    }
+
+    pub async fn get(&self, job_id: &str) -> Option<Arc<ReindexJob>> {
+        self.jobs.read().await.get(job_id).cloned()
+    }
+}
+
+/// Reindex all topics and posts from the database into Meilisearch
+async fn reindex_all(state: &AppState, job: &ReindexJob, config: &ReindexConfig) -> ReindexResponse {
+    let Some(meili) = &state.meili else {
+        return ReindexResponse {
+            success: false,
+            message: "Meilisearch is not configured".to_string(),
+            topics_processed: 0,
+            posts_processed: 0,
+            errors: 0,
+            meilisearch_documents: None,
+        };
+    };
+
+    info!("Starting full reindex of all topics and posts");
+
+    let mut topics_processed = 0i32;
+    let mut posts_processed = 0i32;
+    let mut errors = 0i32;
+
+    // Get all topics from database
+    let quarantined = match QuarantinedTopic::keys(state).await {
+        Ok(quarantined) => quarantined,
+        Err(e) => {
+            error!("Failed to fetch quarantined topics from database: {}", e);
+            return ReindexResponse {
+                success: false,
+                message: format!("Database error: {}", e),
+                topics_processed: 0,
+                posts_processed: 0,
+                errors: 1,
+                meilisearch_documents: None,
+            };
+        }
+    };
+
+    let mut topics = match query_as!(Topic, "SELECT * FROM topics ORDER BY topic_id ASC")
+        .fetch_all(&state.database.pool)
+        .await
+    {
+        Ok(topics) => topics,
+        Err(e) => {
+            error!("Failed to fetch topics from database: {}", e);
+            return ReindexResponse {
+                success: false,
+                message: format!("Database error: {}", e),
+                topics_processed: 0,
+                posts_processed: 0,
+                errors: 1,
+                meilisearch_documents: None,
+            };
+        }
+    };
+
+    topics.retain(|topic| !quarantined.contains(&(topic.discourse_id.clone(), topic.topic_id)));
+    info!("Found {} topics to reindex", topics.len());
+
+    // Index all topics
+    let forum_index = meili.index("forum");
+    let mut tasks = Vec::new();
+    let mut topic_docs = Vec::new();
+
+    for topic in &topics {
+        topic_docs.push(topic_document(topic));
+        topics_processed += 1;
+    }
+
+    // Batch insert topics
+    if !topic_docs.is_empty() {
+        match forum_index
+            .add_documents(&topic_docs, Some("entity_id"))
+            .await
+        {
+            Ok(task) => {
+                info!("Successfully enqueued {} topics", topic_docs.len());
+                tasks.push(task);
+            }
+            Err(e) => {
+                error!("Failed to index topics: {}", e);
+                errors += 1;
+            }
+        }
+    }
+
+    job.update(|status| {
+        status.topics_processed = topics_processed;
+        status.errors = errors;
+    })
+    .await;
+
+    // Get all posts from database
+    let mut posts = match query_as!(Post, "SELECT * FROM posts ORDER BY post_id ASC")
+        .fetch_all(&state.database.pool)
+        .await
+    {
+        Ok(posts) => posts,
+        Err(e) => {
+            error!("Failed to fetch posts from database: {}", e);
+            errors += 1;
+            return ReindexResponse {
+                success: false,
+                message: format!("Database error fetching posts: {}", e),
+                topics_processed,
+                posts_processed: 0,
+                errors,
+                meilisearch_documents: None,
+            };
+        }
+    };
+
+    posts.retain(|post| !quarantined.contains(&(post.discourse_id.clone(), post.topic_id)));
+    info!("Found {} posts to reindex", posts.len());
+
+    // Build user mapping from post extras for more efficient username lookup
+    let user_mapping = build_user_mapping_from_posts(&posts);
+    info!("Built user mapping for {} users", user_mapping.len());
+
+    // Index all posts in batches to avoid memory issues, a few batches in flight at once
+    let post_batches = posts.chunks(config.batch_size.max(1));
+    let total_batches = post_batches.len() as i32;
+    let started = Instant::now();
+
+    let mut batch_results = stream::iter(post_batches)
+        .map(|batch| {
+            let post_docs: Vec<ForumSearchDocument> = batch
+                .iter()
+                .map(|post| post_document(post, user_mapping.get(&post.user_id).cloned()))
+                .collect();
+            let forum_index = &forum_index;
+
+            async move {
+                // the entity lookups read topic_entities, keep it in line with the search index
+                let mut entity_errors = 0;
+                for post in batch {
+                    let post_entities = post.cooked.as_deref().map(entities::extract).unwrap_or_default();
+                    if let Err(e) = TopicEntity::replace_for_post(
+                        &post.discourse_id,
+                        post.topic_id,
+                        post.post_id,
+                        &post_entities,
+                        state,
+                    )
+                    .await
+                    {
+                        error!("Failed to store entities of post {}: {}", post.post_id, e);
+                        entity_errors += 1;
+                    }
+                }
+
+                match forum_index
+                    .add_documents(&post_docs, Some("entity_id"))
+                    .await
+                {
+                    Ok(task) => {
+                        info!("Successfully enqueued batch of {} posts", post_docs.len());
+                        (post_docs.len() as i32, entity_errors, Some(task))
+                    }
+                    Err(e) => {
+                        error!("Failed to index post batch: {}", e);
+                        (post_docs.len() as i32, entity_errors + 1, None)
+                    }
+                }
+            }
+        })
+        .buffer_unordered(config.concurrency.max(1));
+
+    let mut batches_done = 0i32;
+    while let Some((batch_posts, batch_errors, task)) = batch_results.next().await {
+        posts_processed += batch_posts;
+        errors += batch_errors;
+        tasks.extend(task);
+        batches_done += 1;
+
+        job.update(|status| {
+            status.posts_processed = posts_processed;
+            status.errors = errors;
+            status.current_batch = Some(batches_done);
+            status.total_batches = Some(total_batches);
+        })
+        .await;
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+    info!(
+        "Indexed {} posts in {:.1}s ({:.0} posts/s, batch size {}, concurrency {})",
+        posts_processed,
+        elapsed,
+        posts_processed as f64 / elapsed.max(0.001),
+        config.batch_size,
+        config.concurrency
+    );
+
+    let success = errors == 0;
+    let message = if success {
+        format!(
+            "Successfully reindexed {} topics and {} posts",
+            topics_processed, posts_processed
+        )
+    } else {
+        format!(
+            "Reindexing completed with {} errors. Processed {} topics and {} posts",
+            errors, topics_processed, posts_processed
+        )
+    };
+
+    info!("{}", message);
+
+    // documents only count once Meilisearch processed them, tasks of an index run in order
+    if let Some(task) = tasks.into_iter().max_by_key(|task| task.task_uid) {
+        let indexed = task.wait_for_completion(meili, None, Some(INDEXING_TIMEOUT)).await;
+        if let Err(e) = indexed {
+            warn!("Failed to wait for Meilisearch to finish indexing: {}", e);
+        }
+    }
+
+    let meilisearch_documents = match forum_index.get_stats().await {
+        Ok(stats) => Some(stats.number_of_documents as i64),
+        Err(e) => {
+            warn!("Failed to get Meilisearch stats: {}", e);
+            None
+        }
+    };
+
+    ReindexResponse {
+        success,
+        message,
+        topics_processed,
+        posts_processed,
+        errors,
+        meilisearch_documents,
+    }
+}
+
+/// Put a single topic and its posts back into Meilisearch, e.g. after lifting a quarantine
+pub async fn reindex_topic(discourse_id: &str, topic_id: i32, state: &AppState) -> anyhow::Result<()> {
+    let Some(meili) = &state.meili else {
+        return Ok(());
+    };
+
+    let topic = Topic::get_by_topic_id(discourse_id, topic_id, state).await?;
+    let posts: Vec<Post> = sqlx::query_as("SELECT * FROM posts WHERE discourse_id = $1 AND topic_id = $2")
+        .bind(discourse_id)
+        .bind(topic_id)
+        .fetch_all(&state.database.pool)
+        .await?;
+    let user_mapping = build_user_mapping_from_posts(&posts);
+
+    let mut documents = vec![topic_document(&topic)];
+    documents.extend(
+        posts
+            .iter()
+            .map(|post| post_document(post, user_mapping.get(&post.user_id).cloned())),
+    );
+
+    meili
+        .index("forum")
+        .add_documents(&documents, Some("entity_id"))
+        .await?;
+
+    Ok(())
+}
+
+fn topic_document(topic: &Topic) -> ForumSearchDocument {
+    ForumSearchDocument {
+        entity_type: "topic".to_string(),
+        discourse_id: Some(topic.discourse_id.clone()),
+        topic_id: Some(topic.topic_id),
+        post_id: None,
+        post_number: None,
+        user_id: None,
+        username: None,
+        title: Some(topic.title.clone()),
+        slug: Some(topic.slug.clone()),
+        pm_issue: topic.pm_issue,
+        cooked: None,
+        code: None,
+        entities: None,
+        entity_id: format!("topic_{}", topic.topic_id),
+    }
+}
+
+fn post_document(post: &Post, username: Option<String>) -> ForumSearchDocument {
+    ForumSearchDocument {
+        entity_type: "post".to_string(),
+        discourse_id: Some(post.discourse_id.clone()),
+        topic_id: Some(post.topic_id),
+        post_id: Some(post.post_id),
+        post_number: Some(post.post_number),
+        user_id: Some(post.user_id),
+        username,
+        title: None,
+        slug: None,
+        pm_issue: None,
+        cooked: post.cooked.as_deref().map(strip_tags),
+        code: post.cooked.as_deref().and_then(code::extract),
+        entities: post.cooked.as_deref().map(entities::search_keys),
+        entity_id: format!("post_{}", post.post_id),
+    }
+}
+
+/// Build a comprehensive user mapping by extracting user info from post extras
+fn build_user_mapping_from_posts(posts: &[Post]) -> HashMap<i32, String> {
+    let mut user_map = HashMap::new();
+
+    for post in posts {
+        if let Some(extra) = &post.extra {
+            // Try to extract username from the extra JSON data
+            if let Some(username) = extra.get("username").and_then(|u| u.as_str()) {
+                user_map.insert(post.user_id, username.to_string());
+            }
+        }
+    }
+
+    user_map
+}
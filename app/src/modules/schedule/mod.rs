@@ -0,0 +1,85 @@
+//! Wall-clock aligned schedules for periodic tasks
+//!
+//! A task running every 30 minutes runs at :00 and :30 rather than 30 minutes after whenever
+//! the process started, so runs land on the same times across restarts. Slots are counted from
+//! midnight in `SCHEDULE_TIMEZONE` (an IANA name, UTC by default), which makes intervals that
+//! divide a day (`30m`, `2h`, `6h`) line up with local wall-clock times.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use tracing::warn;
+
+static TIMEZONE: LazyLock<Tz> = LazyLock::new(|| match std::env::var("SCHEDULE_TIMEZONE") {
+    Ok(timezone) => timezone.parse().unwrap_or_else(|_| {
+        warn!("Unknown SCHEDULE_TIMEZONE {:?}, using UTC", timezone);
+        Tz::UTC
+    }),
+    Err(_) => Tz::UTC,
+});
+
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    interval: TimeDelta,
+    timezone: Tz,
+}
+
+impl Schedule {
+    /// Every `interval` in the configured timezone, at least every second
+    pub fn every(interval: TimeDelta) -> Self {
+        Self::in_timezone(interval, *TIMEZONE)
+    }
+
+    fn in_timezone(interval: TimeDelta, timezone: Tz) -> Self {
+        Self {
+            interval: interval.max(TimeDelta::seconds(1)),
+            timezone,
+        }
+    }
+
+    /// The first slot strictly after `now`
+    pub fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&self.timezone).naive_local();
+        let midnight = local.date().and_hms_opt(0, 0, 0).unwrap();
+
+        let slots = (local - midnight).num_seconds() / self.interval.num_seconds() + 1;
+        let next = midnight + TimeDelta::seconds(slots * self.interval.num_seconds());
+
+        // a slot skipped by a DST change falls back to a plain interval
+        self.timezone
+            .from_local_datetime(&next)
+            .earliest()
+            .map(|next| next.with_timezone(&Utc))
+            .filter(|next| *next > now)
+            .unwrap_or(now + self.interval)
+    }
+
+    /// Sleep until the first slot after now, returns the time slept until
+    pub async fn sleep(&self) -> DateTime<Utc> {
+        let now = Utc::now();
+        let next = self.next_after(now);
+        let wait = (next - now).to_std().unwrap_or_default();
+        async_std::task::sleep(wait).await;
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_after() {
+        let at = |value: &str| value.parse::<DateTime<Utc>>().unwrap();
+
+        let half_hourly = Schedule::in_timezone(TimeDelta::minutes(30), Tz::UTC);
+        assert_eq!(half_hourly.next_after(at("2025-05-01T10:12:00Z")), at("2025-05-01T10:30:00Z"));
+        assert_eq!(half_hourly.next_after(at("2025-05-01T10:30:00Z")), at("2025-05-01T11:00:00Z"));
+        assert_eq!(half_hourly.next_after(at("2025-05-01T23:45:00Z")), at("2025-05-02T00:00:00Z"));
+
+        // six hourly in Berlin (UTC+2 in May) runs at local 00:00, 06:00, 12:00 and 18:00
+        let berlin = Schedule::in_timezone(TimeDelta::hours(6), chrono_tz::Europe::Berlin);
+        assert_eq!(berlin.next_after(at("2025-05-01T05:00:00Z")), at("2025-05-01T10:00:00Z"));
+    }
+}
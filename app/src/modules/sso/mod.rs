@@ -2,11 +2,13 @@ use anyhow::{Result, anyhow};
 use chrono::{Duration, Utc};
 use figment::{Figment, providers::Env};
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use moka::future::Cache;
 use poem_openapi::Object;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::time::Duration as StdDuration;
 use tracing::info;
 use url::Url;
 
@@ -60,7 +62,10 @@ pub struct JWTClaims {
     pub exp: i64, // expiry
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Name of the cookie carrying the session token
+pub const SESSION_COOKIE: &str = "forum_session";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AuthSession {
     pub csrf_token: String,
     pub nonce: String,
@@ -96,6 +101,8 @@ pub struct SSOService {
     pub config: SSOConfig,
     jwt_encoding_key: EncodingKey,
     jwt_decoding_key: DecodingKey,
+    /// Login attempts awaiting their callback, keyed by the csrf state
+    pending_sessions: Cache<String, AuthSession>,
 }
 
 impl SSOService {
@@ -200,9 +207,53 @@ impl SSOService {
             config,
             jwt_encoding_key,
             jwt_decoding_key,
+            pending_sessions: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(StdDuration::from_secs(10 * 60))
+                .build(),
         })
     }
 
+    /// Remember a login attempt until its callback arrives
+    pub async fn store_pending_session(&self, session: AuthSession) {
+        self.pending_sessions
+            .insert(session.csrf_token.clone(), session)
+            .await;
+    }
+
+    /// Take the login attempt matching the callback state, each state can only be used once
+    pub async fn take_pending_session(&self, csrf_token: &str) -> Option<AuthSession> {
+        self.pending_sessions.remove(csrf_token).await
+    }
+
+    /// Build a `Set-Cookie` value carrying the session token
+    pub fn session_cookie(&self, token: &str, provider_id: &str) -> String {
+        let max_age = self.config.jwt_expiry_hours.unwrap_or(24) * 60 * 60;
+        format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            SESSION_COOKIE,
+            token,
+            max_age,
+            self.cookie_secure_suffix(provider_id)
+        )
+    }
+
+    /// Build a `Set-Cookie` value clearing the session
+    pub fn clear_session_cookie(&self) -> String {
+        format!(
+            "{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax",
+            SESSION_COOKIE
+        )
+    }
+
+    fn cookie_secure_suffix(&self, provider_id: &str) -> &'static str {
+        // only mark the cookie secure when the login flow itself runs over https
+        match self.config.providers.get(provider_id) {
+            Some(provider) if provider.redirect_uri.starts_with("https://") => "; Secure",
+            _ => "",
+        }
+    }
+
     pub fn get_authorization_url(&self, provider_id: &str) -> Result<(String, AuthSession)> {
         let provider_config = self
             .config
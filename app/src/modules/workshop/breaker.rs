@@ -0,0 +1,190 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poem_openapi::Enum;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::metrics::AiMetrics;
+
+const DEFAULT_THRESHOLD: u32 = 5;
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Returned instead of calling the provider while the breaker is open
+#[derive(Debug)]
+pub struct AiUnavailable;
+
+impl std::fmt::Display for AiUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AI temporarily unavailable")
+    }
+}
+
+impl std::error::Error for AiUnavailable {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    fn as_gauge(self) -> u64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::HalfOpen => 1,
+            BreakerState::Open => 2,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    // a single prompt is let through to test whether the provider recovered
+    probing: bool,
+}
+
+/// Circuit breaker around the OpenAI-compatible provider
+///
+/// Opens after `threshold` consecutive failures and rejects prompts for `cooldown`,
+/// then lets a single probe through and closes again once a call succeeds.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+    metrics: AiMetrics,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration, metrics: AiMetrics) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            inner: Mutex::new(Inner::default()),
+            metrics,
+        }
+    }
+
+    /// Reads `WORKSHOP_BREAKER_THRESHOLD` and `WORKSHOP_BREAKER_COOLDOWN_SECS`
+    pub fn load(metrics: AiMetrics) -> Self {
+        let threshold = std::env::var("WORKSHOP_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|threshold| threshold.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_THRESHOLD);
+        let cooldown = std::env::var("WORKSHOP_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_COOLDOWN);
+
+        Self::new(threshold, cooldown, metrics)
+    }
+
+    /// Current state, also refreshing the exported gauge
+    pub fn state(&self) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        let state = Self::state_of(&inner);
+        self.metrics.breaker_state(state.as_gauge());
+
+        state
+    }
+
+    fn state_of(inner: &Inner) -> BreakerState {
+        match inner.open_until {
+            None => BreakerState::Closed,
+            Some(until) if inner.probing || Instant::now() >= until => BreakerState::HalfOpen,
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    /// Check whether a call to the provider may be made
+    pub fn try_acquire(&self) -> Result<(), AiUnavailable> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+
+        match inner.open_until {
+            None => Ok(()),
+            Some(until) if now < until => {
+                self.metrics.breaker_rejected();
+                Err(AiUnavailable)
+            }
+            Some(_) => {
+                // half-open, hold off other prompts until the probe reports back
+                // (or it never does and the cooldown passes again)
+                inner.open_until = Some(now + self.cooldown);
+                inner.probing = true;
+                self.metrics.breaker_state(BreakerState::HalfOpen.as_gauge());
+                Ok(())
+            }
+        }
+    }
+
+    /// Record the outcome of a call to the provider, passing the result through
+    pub fn observe<T, E>(&self, result: Result<T, E>) -> Result<T, E> {
+        if result.is_ok() {
+            self.record_success();
+        } else {
+            self.record_failure();
+        }
+
+        result
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.open_until.is_some() {
+            tracing::info!("AI provider recovered, closing circuit breaker");
+        }
+        *inner = Inner::default();
+        self.metrics.breaker_state(BreakerState::Closed.as_gauge());
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+
+        if inner.probing || inner.consecutive_failures >= self.threshold {
+            if inner.open_until.is_none() || inner.probing {
+                tracing::warn!(
+                    "AI provider failed {} times in a row, opening circuit breaker for {:?}",
+                    inner.consecutive_failures,
+                    self.cooldown
+                );
+            }
+            inner.open_until = Some(Instant::now() + self.cooldown);
+            inner.probing = false;
+            self.metrics.breaker_state(BreakerState::Open.as_gauge());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::metrics::Metrics;
+
+    #[test]
+    fn test_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60), Metrics::init().ai);
+        breaker.record_failure();
+        assert!(breaker.try_acquire().is_ok());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.try_acquire().is_err());
+
+        let breaker = CircuitBreaker::new(1, Duration::ZERO, Metrics::init().ai);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.try_acquire().is_ok());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
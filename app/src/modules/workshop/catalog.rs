@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use async_openai::{Client, config::OpenAIConfig};
+use moka::future::Cache;
+
+use crate::modules::workshop::prompts::WORKSHOP_MODEL;
+
+/// How long the provider's model list is trusted before asking again
+const CATALOG_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone)]
+pub struct CatalogModel {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    pub context_window: Option<u32>,
+}
+
+/// Display names and context windows of the models we know about
+const KNOWN_MODELS: &[(&str, &str, &str, u32)] = &[
+    ("google/gemini-2.5-flash-preview-05-20", "Gemini 2.5 Flash Preview", "Google", 1_048_576),
+    ("google/gemini-2.0-flash-001", "Gemini 2.0 Flash", "Google", 1_048_576),
+    ("google/gemini-2.5-pro-preview", "Gemini 2.5 Pro Preview", "Google", 1_048_576),
+    ("anthropic/claude-sonnet-4", "Claude Sonnet 4", "Anthropic", 200_000),
+    ("openai/gpt-4o-mini", "OpenAI o4 Mini", "OpenAI", 128_000),
+    ("mistralai/mistral-nemo", "Mistral Nemo", "Mistral AI", 131_072),
+];
+
+impl CatalogModel {
+    fn from_id(id: &str) -> Self {
+        match KNOWN_MODELS.iter().find(|(known, ..)| *known == id) {
+            Some((id, name, provider, context_window)) => Self {
+                id: id.to_string(),
+                name: name.to_string(),
+                provider: provider.to_string(),
+                context_window: Some(*context_window),
+            },
+            None => Self {
+                id: id.to_string(),
+                name: id.to_string(),
+                provider: id.split('/').next().unwrap_or_default().to_string(),
+                context_window: None,
+            },
+        }
+    }
+}
+
+/// Models offered to workshop users, the allow-list intersected with what the provider serves
+pub struct ModelCatalog {
+    allowlist: Vec<String>,
+    cache: Cache<(), Vec<CatalogModel>>,
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModelCatalog {
+    /// Reads the allow-list from `WORKSHOP_MODELS` (comma separated ids), defaulting to the known models
+    pub fn new() -> Self {
+        let allowlist = std::env::var("WORKSHOP_MODELS")
+            .ok()
+            .map(|models| {
+                models
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|model| !model.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|models| !models.is_empty())
+            .unwrap_or_else(|| KNOWN_MODELS.iter().map(|(id, ..)| id.to_string()).collect());
+
+        Self {
+            allowlist,
+            cache: Cache::builder().time_to_live(CATALOG_TTL).build(),
+        }
+    }
+
+    pub fn default_model(&self) -> String {
+        if self.allowlist.iter().any(|model| model == WORKSHOP_MODEL) {
+            WORKSHOP_MODEL.to_string()
+        } else {
+            self.allowlist.first().cloned().unwrap_or_else(|| WORKSHOP_MODEL.to_string())
+        }
+    }
+
+    /// The allow-list as-is, used when the provider can't be reached
+    pub fn fallback(&self) -> Vec<CatalogModel> {
+        self.allowlist.iter().map(|id| CatalogModel::from_id(id)).collect()
+    }
+
+    pub async fn list(&self, client: &Client<OpenAIConfig>) -> Vec<CatalogModel> {
+        if let Some(models) = self.cache.get(&()).await {
+            return models;
+        }
+
+        let served = match client.models().list().await {
+            Ok(response) => response.data,
+            Err(e) => {
+                tracing::warn!("Failed to list provider models, using fallback list: {}", e);
+                return self.fallback();
+            }
+        };
+
+        let models: Vec<CatalogModel> = self
+            .allowlist
+            .iter()
+            .filter(|id| served.iter().any(|model| &model.id == *id))
+            .map(|id| CatalogModel::from_id(id))
+            .collect();
+
+        if models.is_empty() {
+            tracing::warn!("Provider serves none of the allowed models, using fallback list");
+            return self.fallback();
+        }
+
+        self.cache.insert((), models.clone()).await;
+        models
+    }
+}
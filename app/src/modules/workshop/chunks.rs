@@ -0,0 +1,309 @@
+//! Map-reduce summaries of large topics
+//!
+//! The posts of a topic are split into windows of `SUMMARY_INPUT_CHUNK_POSTS` posts and each
+//! window is summarized on its own with the chunk summary prompt. The summary prompt then gets
+//! those summaries in place of the posts, so the middle of a long discussion isn't cut away.
+//! Window summaries are stored along with a hash of their posts, regenerating a summary after
+//! new posts only summarizes the windows that changed, usually just the last one.
+
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestUserMessage, CompletionUsage};
+use futures::{StreamExt, stream};
+use opentelemetry_http::HttpError;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::info;
+
+use super::prompts::{PromptConfig, add_usage, truncate_messages_to_token_limit};
+use crate::{
+    models::topics::{
+        Topic,
+        chunk::TopicSummaryChunk,
+        post::{Post, WorkshopPost},
+    },
+    state::AppState,
+};
+
+/// Windows summarized at the same time
+const MAP_CONCURRENCY: usize = 4;
+
+/// Tells the summary prompt it gets window summaries instead of posts
+pub const REDUCE_INSTRUCTION: &str = "This thread is too long to include in full. Instead of its posts you are given \
+    `chunk_summaries`, summaries of consecutive windows of its posts in order. Summarize the whole thread from them.";
+
+/// Summary of the posts `first_post_number` through `last_post_number`
+#[derive(Debug, Serialize)]
+pub struct ChunkSummary {
+    pub first_post_number: i32,
+    pub last_post_number: i32,
+    pub summary: String,
+}
+
+struct Window {
+    first_post_number: i32,
+    last_post_number: i32,
+    content_hash: String,
+    posts: Vec<WorkshopPost>,
+}
+
+/// Summaries of all windows of a topic in post order, reusing stored ones that are unchanged,
+/// along with the tokens spent on the windows that had to be summarized
+pub async fn summarize_chunks(
+    topic: &Topic,
+    state: &AppState,
+) -> Result<(Vec<ChunkSummary>, Option<CompletionUsage>), HttpError> {
+    let chunk_posts = state.workshop.summary_input.chunk_posts.max(1);
+
+    let stored: HashMap<i32, TopicSummaryChunk> =
+        TopicSummaryChunk::for_topic(&topic.discourse_id, topic.topic_id, state)
+            .await?
+            .into_iter()
+            .map(|chunk| (chunk.first_post_number, chunk))
+            .collect();
+
+    let mut pages = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = Post::find_page_from_end(
+            &topic.discourse_id,
+            topic.topic_id,
+            false,
+            chunk_posts,
+            offset,
+            state,
+        )
+        .await?;
+        if page.is_empty() {
+            break;
+        }
+        let done = (page.len() as i64) < chunk_posts;
+        offset += page.len() as i64;
+        pages.push(page);
+
+        if done {
+            break;
+        }
+    }
+
+    let (mut summaries, pending) = plan_windows(pages, &stored);
+
+    info!(
+        "Summarizing topic {} on {} in {} windows, {} unchanged",
+        topic.topic_id,
+        topic.discourse_id,
+        summaries.len(),
+        summaries.len() - pending.len()
+    );
+
+    let results: Vec<_> = stream::iter(pending)
+        .map(|(index, window)| async move { (index, summarize_window(topic, window, state).await) })
+        .buffered(MAP_CONCURRENCY)
+        .collect()
+        .await;
+    let mut usage = None;
+    for (index, result) in results {
+        let (summary, window_usage) = result?;
+        summaries[index] = Some(summary);
+        usage = add_usage(usage, window_usage);
+    }
+
+    let summaries: Vec<ChunkSummary> = summaries.into_iter().flatten().collect();
+
+    let first_post_numbers: Vec<i32> = summaries
+        .iter()
+        .map(|summary| summary.first_post_number)
+        .collect();
+    TopicSummaryChunk::retain(&topic.discourse_id, topic.topic_id, &first_post_numbers, state).await?;
+
+    Ok((summaries, usage))
+}
+
+/// Reuses the stored summary of every window whose posts are unchanged, returns the summaries
+/// in post order with a gap for each window that still has to be summarized
+fn plan_windows(
+    pages: Vec<Vec<Post>>,
+    stored: &HashMap<i32, TopicSummaryChunk>,
+) -> (Vec<Option<ChunkSummary>>, Vec<(usize, Window)>) {
+    let mut summaries = Vec::with_capacity(pages.len());
+    let mut pending = Vec::new();
+
+    for page in pages {
+        let (Some(first), Some(last)) = (page.first(), page.last()) else {
+            continue;
+        };
+        let (first_post_number, last_post_number) = (first.post_number, last.post_number);
+        let content_hash = window_hash(&page);
+
+        match stored.get(&first_post_number) {
+            Some(chunk)
+                if chunk.content_hash == content_hash && chunk.last_post_number == last_post_number =>
+            {
+                summaries.push(Some(ChunkSummary {
+                    first_post_number,
+                    last_post_number,
+                    summary: chunk.summary_text.clone(),
+                }));
+            }
+            _ => {
+                pending.push((
+                    summaries.len(),
+                    Window {
+                        first_post_number,
+                        last_post_number,
+                        content_hash,
+                        posts: page.into_iter().map(Into::into).collect(),
+                    },
+                ));
+                summaries.push(None);
+            }
+        }
+    }
+
+    (summaries, pending)
+}
+
+async fn summarize_window(
+    topic: &Topic,
+    window: Window,
+    state: &AppState,
+) -> Result<(ChunkSummary, Option<CompletionUsage>), HttpError> {
+    let messages = vec![
+        state.workshop.prompts.chunk_summary.clone(),
+        ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+            content: serde_json::to_string(&json!({
+                "topic_title": topic.title,
+                "posts": window.posts,
+            }))?
+            .into(),
+            name: None,
+        }),
+    ];
+    let request = PromptConfig::chunk_summary().request(truncate_messages_to_token_limit(messages, &None));
+
+    state.workshop.breaker.try_acquire()?;
+    let (chat_completion, _) = state
+        .workshop
+        .breaker
+        .observe(state.workshop.providers.create(request).await)?;
+
+    let summary = chat_completion
+        .choices
+        .first()
+        .and_then(|choice| choice.message.content.clone())
+        .filter(|summary| !summary.trim().is_empty())
+        .ok_or_else(|| {
+            format!(
+                "Empty summary of posts {}-{} of topic {}",
+                window.first_post_number, window.last_post_number, topic.topic_id
+            )
+        })?;
+
+    TopicSummaryChunk::save(
+        &topic.discourse_id,
+        topic.topic_id,
+        window.first_post_number,
+        window.last_post_number,
+        &window.content_hash,
+        &summary,
+        state,
+    )
+    .await?;
+
+    Ok((
+        ChunkSummary {
+            first_post_number: window.first_post_number,
+            last_post_number: window.last_post_number,
+            summary,
+        },
+        chat_completion.usage,
+    ))
+}
+
+/// Changes when a post of the window is edited, added or removed
+fn window_hash(posts: &[Post]) -> String {
+    let mut hasher = Sha256::new();
+    for post in posts {
+        hasher.update(post.content_hash().as_bytes());
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(post_number: i32, cooked: &str) -> Post {
+        Post {
+            discourse_id: "magicians".to_string(),
+            post_id: post_number,
+            topic_id: 1,
+            user_id: 1,
+            post_number,
+            updated_at: None,
+            created_at: None,
+            cooked: Some(cooked.to_string()),
+            post_url: None,
+            extra: None,
+        }
+    }
+
+    /// Windows of three posts, like the pages `summarize_chunks` reads
+    fn pages(last_post_number: i32, edited: Option<i32>) -> Vec<Vec<Post>> {
+        let mut pages: Vec<Vec<Post>> = Vec::new();
+        for post_number in 1..=last_post_number {
+            if post_number % 3 == 1 {
+                pages.push(Vec::new());
+            }
+            let cooked = if Some(post_number) == edited { "edited" } else { "original" };
+            pages.last_mut().unwrap().push(post(post_number, cooked));
+        }
+
+        pages
+    }
+
+    fn store(pages: &[Vec<Post>]) -> HashMap<i32, TopicSummaryChunk> {
+        pages
+            .iter()
+            .map(|page| {
+                let first_post_number = page.first().unwrap().post_number;
+                let chunk = TopicSummaryChunk {
+                    discourse_id: "magicians".to_string(),
+                    topic_id: 1,
+                    first_post_number,
+                    last_post_number: page.last().unwrap().post_number,
+                    content_hash: window_hash(page),
+                    summary_text: format!("posts from {}", first_post_number),
+                    created_at: chrono::Utc::now(),
+                };
+                (first_post_number, chunk)
+            })
+            .collect()
+    }
+
+    fn pending(pages: Vec<Vec<Post>>, stored: &HashMap<i32, TopicSummaryChunk>) -> Vec<(usize, i32, i32)> {
+        let (summaries, pending) = plan_windows(pages, stored);
+        assert_eq!(summaries.iter().filter(|summary| summary.is_none()).count(), pending.len());
+
+        pending
+            .into_iter()
+            .map(|(index, window)| (index, window.first_post_number, window.last_post_number))
+            .collect()
+    }
+
+    #[test]
+    fn test_changed_last_window_is_summarized_again() {
+        let stored = store(&pages(8, None));
+        assert!(pending(pages(8, None), &stored).is_empty());
+
+        // a new post only touches the last window
+        assert_eq!(pending(pages(9, None), &stored), vec![(2, 7, 9)]);
+
+        // so does an edit in it
+        assert_eq!(pending(pages(8, Some(8)), &stored), vec![(2, 7, 8)]);
+
+        // an edit further up only redoes its own window
+        assert_eq!(pending(pages(8, Some(2)), &stored), vec![(0, 1, 3)]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Which posts of a topic go into its summary prompt
+//!
+//! Posts are read page by page and only kept while they fit in `SUMMARY_INPUT_MAX_TOKENS`, so
+//! a topic with thousands of posts never gets serialized whole. The opening post is always
+//! kept; `recency` spends the rest of the budget on the newest posts, `coverage` splits it
+//! between the first and the newest posts.
+//!
+//! Topics too large for that to do them justice are summarized by map-reduce instead, see
+//! [`super::chunks`].
+
+use figment::{Figment, providers::Env};
+use serde::Deserialize;
+
+use super::prompts::estimate_tokens_in_text;
+use crate::{
+    models::topics::{
+        Topic,
+        post::{Post, WorkshopPost},
+    },
+    state::AppState,
+};
+
+/// Posts read from the database at a time
+const PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// The opening post and the newest posts, where the discussion stands
+    Recency,
+    /// Half the budget for the first posts, the rest for the newest posts
+    #[default]
+    Coverage,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMode {
+    /// Summarize the posts that fit in the budget
+    Truncate,
+    /// Summarize windows of posts separately, then summarize those summaries
+    MapReduce,
+    /// Map-reduce for topics above `map_reduce_threshold` posts, truncate otherwise
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummaryInput {
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub strategy: SelectionStrategy,
+    #[serde(default)]
+    pub mode: SummaryMode,
+    #[serde(default = "default_map_reduce_threshold")]
+    pub map_reduce_threshold: i32,
+    /// Posts per window in map-reduce
+    #[serde(default = "default_chunk_posts")]
+    pub chunk_posts: i64,
+}
+
+fn default_max_tokens() -> usize {
+    100_000
+}
+
+fn default_map_reduce_threshold() -> i32 {
+    500
+}
+
+fn default_chunk_posts() -> i64 {
+    100
+}
+
+impl Default for SummaryInput {
+    fn default() -> Self {
+        Self {
+            max_tokens: default_max_tokens(),
+            strategy: SelectionStrategy::default(),
+            mode: SummaryMode::default(),
+            map_reduce_threshold: default_map_reduce_threshold(),
+            chunk_posts: default_chunk_posts(),
+        }
+    }
+}
+
+/// Posts picked for a summary, in topic order
+#[derive(Debug, Default)]
+pub struct SelectedPosts {
+    pub posts: Vec<WorkshopPost>,
+    /// Posts of the topic left out to stay within the budget
+    pub omitted: usize,
+}
+
+impl SummaryInput {
+    pub fn load() -> Self {
+        Figment::new()
+            .merge(Env::prefixed("SUMMARY_INPUT_"))
+            .extract::<SummaryInput>()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Invalid summary input config, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn uses_map_reduce(&self, topic: &Topic) -> bool {
+        match self.mode {
+            SummaryMode::Truncate => false,
+            SummaryMode::MapReduce => true,
+            SummaryMode::Auto => topic.post_count > self.map_reduce_threshold,
+        }
+    }
+
+    /// Read the posts of a topic from both ends until the budget is spent
+    pub async fn select_posts(
+        &self,
+        topic: &Topic,
+        state: &AppState,
+    ) -> Result<SelectedPosts, sqlx::Error> {
+        let mut selection = Selection::new(self);
+
+        let mut offset = 0;
+        'head: loop {
+            let page = Post::find_page_from_end(
+                &topic.discourse_id,
+                topic.topic_id,
+                false,
+                PAGE_SIZE,
+                offset,
+                state,
+            )
+            .await?;
+            let done = (page.len() as i64) < PAGE_SIZE;
+            offset += page.len() as i64;
+
+            for post in page {
+                if !selection.push_head(post) {
+                    break 'head;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+
+        let mut offset = 0;
+        'tail: loop {
+            let page = Post::find_page_from_end(
+                &topic.discourse_id,
+                topic.topic_id,
+                true,
+                PAGE_SIZE,
+                offset,
+                state,
+            )
+            .await?;
+            let done = (page.len() as i64) < PAGE_SIZE;
+            offset += page.len() as i64;
+
+            for post in page {
+                if !selection.push_tail(post) {
+                    break 'tail;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+
+        Ok(selection.finish(topic.post_count.max(0) as usize))
+    }
+}
+
+/// Keeps posts from the start of a topic, then from its end, while they fit in the budget
+struct Selection {
+    remaining: usize,
+    /// What the first posts may use, the opening post is kept regardless
+    head_budget: usize,
+    head: Vec<WorkshopPost>,
+    tail: Vec<WorkshopPost>,
+    /// Last post number taken from the start, the end stops there
+    head_end: i32,
+}
+
+impl Selection {
+    fn new(input: &SummaryInput) -> Self {
+        let head_budget = match input.strategy {
+            SelectionStrategy::Recency => 0,
+            SelectionStrategy::Coverage => input.max_tokens / 2,
+        };
+
+        Self {
+            remaining: input.max_tokens,
+            head_budget,
+            head: Vec::new(),
+            tail: Vec::new(),
+            head_end: 0,
+        }
+    }
+
+    /// Take the next post from the start, returns whether to keep going
+    fn push_head(&mut self, post: Post) -> bool {
+        let post_number = post.post_number;
+        let post: WorkshopPost = post.into();
+        let tokens = post_tokens(&post);
+
+        if !self.head.is_empty() && tokens > self.head_budget {
+            return false;
+        }
+
+        self.head_budget = self.head_budget.saturating_sub(tokens);
+        self.remaining = self.remaining.saturating_sub(tokens);
+        self.head_end = post_number;
+        self.head.push(post);
+        true
+    }
+
+    /// Take the next post from the end, returns whether to keep going
+    fn push_tail(&mut self, post: Post) -> bool {
+        if post.post_number <= self.head_end {
+            return false;
+        }
+
+        let post: WorkshopPost = post.into();
+        let tokens = post_tokens(&post);
+        if tokens > self.remaining {
+            return false;
+        }
+
+        self.remaining -= tokens;
+        self.tail.push(post);
+        true
+    }
+
+    fn finish(self, post_count: usize) -> SelectedPosts {
+        let mut posts = self.head;
+        posts.extend(self.tail.into_iter().rev());
+
+        SelectedPosts {
+            omitted: post_count.saturating_sub(posts.len()),
+            posts,
+        }
+    }
+}
+
+/// Estimated tokens of a post as it ends up in the prompt
+fn post_tokens(post: &WorkshopPost) -> usize {
+    serde_json::to_string(post)
+        .map(|json| estimate_tokens_in_text(&json))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(post_number: i32) -> Post {
+        Post {
+            discourse_id: "magicians".to_string(),
+            post_id: post_number,
+            topic_id: 1,
+            user_id: 1,
+            post_number,
+            updated_at: None,
+            created_at: None,
+            cooked: Some("x".repeat(350)),
+            post_url: None,
+            extra: None,
+        }
+    }
+
+    fn select(strategy: SelectionStrategy, posts: i32) -> Vec<i32> {
+        // room for four posts, with slack for longer post ids
+        let tokens = post_tokens(&post(1).into());
+        let mut selection = Selection::new(&SummaryInput {
+            max_tokens: tokens * 4 + 3,
+            strategy,
+            ..SummaryInput::default()
+        });
+
+        for post_number in 1..=posts {
+            if !selection.push_head(post(post_number)) {
+                break;
+            }
+        }
+        for post_number in (1..=posts).rev() {
+            if !selection.push_tail(post(post_number)) {
+                break;
+            }
+        }
+
+        selection
+            .finish(posts as usize)
+            .posts
+            .iter()
+            .map(|post| post.post_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_selection_strategies() {
+        assert_eq!(select(SelectionStrategy::Recency, 10), vec![1, 8, 9, 10]);
+        assert_eq!(select(SelectionStrategy::Coverage, 10), vec![1, 2, 9, 10]);
+        assert_eq!(select(SelectionStrategy::Coverage, 3), vec![1, 2, 3]);
+    }
+}
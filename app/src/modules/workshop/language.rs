@@ -0,0 +1,83 @@
+use serde::Deserialize;
+use strip_tags::strip_tags;
+use whatlang::Lang;
+
+use crate::models::topics::post::WorkshopPost;
+
+/// Upper bound on the text fed to language detection, detection settles long before this
+const MAX_DETECTION_CHARS: usize = 20_000;
+
+/// Which language summaries are written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryLanguageMode {
+    /// Always summarize in English, also for non-English topics
+    #[default]
+    English,
+    /// Summarize in the dominant language of the topic
+    Dominant,
+}
+
+impl SummaryLanguageMode {
+    /// Reads `SUMMARY_LANGUAGE` (`english` or `dominant`)
+    pub fn load() -> Self {
+        match std::env::var("SUMMARY_LANGUAGE").as_deref() {
+            Ok("dominant") => Self::Dominant,
+            Ok("english") | Err(_) => Self::English,
+            Ok(other) => {
+                tracing::warn!("Unknown SUMMARY_LANGUAGE {:?}, summarizing in English", other);
+                Self::English
+            }
+        }
+    }
+}
+
+/// Detect the dominant language of a topic's posts, None when detection is unreliable
+pub fn detect_language(title: &str, posts: &[WorkshopPost]) -> Option<Lang> {
+    let mut text = title.to_string();
+    for cooked in posts.iter().filter_map(|post| post.cooked.as_deref()) {
+        if text.len() >= MAX_DETECTION_CHARS {
+            break;
+        }
+        text.push('\n');
+        text.push_str(&strip_tags(cooked));
+    }
+
+    let info = whatlang::detect(&text)?;
+    info.is_reliable().then_some(info.lang())
+}
+
+/// Extra instruction appended to the summary prompt, None when the default prompt already fits
+pub fn language_instruction(language: Option<Lang>, mode: SummaryLanguageMode) -> Option<String> {
+    let language = language.filter(|language| *language != Lang::Eng)?;
+
+    Some(match mode {
+        SummaryLanguageMode::English => format!(
+            "The discussion is largely written in {}. Write the summary in English regardless.",
+            language.eng_name()
+        ),
+        SummaryLanguageMode::Dominant => format!(
+            "The discussion is largely written in {}. Write the summary in {} ({}).",
+            language.eng_name(),
+            language.eng_name(),
+            language.name()
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_instruction() {
+        assert_eq!(language_instruction(None, SummaryLanguageMode::Dominant), None);
+        assert_eq!(language_instruction(Some(Lang::Eng), SummaryLanguageMode::Dominant), None);
+
+        let instruction = language_instruction(Some(Lang::Deu), SummaryLanguageMode::Dominant).unwrap();
+        assert!(instruction.contains("Write the summary in German"));
+
+        let instruction = language_instruction(Some(Lang::Deu), SummaryLanguageMode::English).unwrap();
+        assert!(instruction.contains("in English"));
+    }
+}
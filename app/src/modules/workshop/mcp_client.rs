@@ -6,6 +6,8 @@ use std::time::{Duration, Instant};
 use reqwest::Client;
 use async_std::task::sleep;
 
+use crate::modules::redact;
+
 #[derive(Debug, thiserror::Error)]
 pub enum McpError {
     #[error("HTTP request error: {0}")]
@@ -226,7 +228,7 @@ impl McpClientManager {
         }
 
         if let Some(ref session_id) = self.session_id {
-            tracing::info!("🔑 Session ID: {}", session_id);
+            tracing::debug!("🔑 Session ID: {}", session_id);
         }
 
         tracing::info!("✅ MCP initialization successful!");
@@ -262,7 +264,7 @@ impl McpClientManager {
 
     /// Get tools using direct HTTP requests (with retries)
     pub async fn get_tools(&mut self) -> Result<Vec<McpTool>, McpError> {
-        tracing::info!("🔧 get_tools called, server_url: {}", self.server_url);
+        tracing::debug!("🔧 get_tools called, server_url: {}", self.server_url);
         
         // Check cache
         if let (Some(cached_tools), Some(last_update)) = (&self.tools_cache, self.last_update) {
@@ -272,17 +274,17 @@ impl McpClientManager {
             }
         }
 
-        tracing::info!("🔄 Fetching fresh MCP tools from server");
+        tracing::debug!("🔄 Fetching fresh MCP tools from server");
         
         // Always ensure we have a fresh connection for tools requests
         // This fixes issues with stale session IDs
-        tracing::info!("🔄 Ensuring fresh MCP session for tools request...");
+        tracing::debug!("🔄 Ensuring fresh MCP session for tools request...");
         self.reset_connection().await;
         self.initialize_connection().await?;
 
         // Store session info for debugging
         let session_id_for_debug = self.session_id.clone();
-        tracing::info!("🔑 About to use session ID for tools request: {:?}", session_id_for_debug);
+        tracing::debug!("🔑 About to use session ID for tools request: {:?}", session_id_for_debug);
 
         let server_url = self.server_url.clone();
         let client = self.client.clone();
@@ -407,7 +409,7 @@ impl McpClientManager {
                 self.initialize_connection().await?;
                 
                 let fresh_session_id = self.session_id.clone();
-                tracing::info!("🆕 Retrying with fresh session ID: {:?}", fresh_session_id);
+                tracing::debug!("🆕 Retrying with fresh session ID: {:?}", fresh_session_id);
                 
                 // Retry the tools request with fresh session
                 let server_url = self.server_url.clone();
@@ -502,7 +504,7 @@ impl McpClientManager {
             Vec::new()
         };
         
-        tracing::info!("✅ Retrieved {} tools from MCP server", tools.len());
+        tracing::debug!("✅ Retrieved {} tools from MCP server", tools.len());
 
         // Update cache
         self.tools_cache = Some(tools.clone());
@@ -513,9 +515,9 @@ impl McpClientManager {
 
     /// Convert MCP tools to OpenAI function format
     pub async fn get_openai_tools(&mut self) -> Result<Vec<ChatCompletionTool>, McpError> {
-        tracing::info!("🔧 get_openai_tools called, getting MCP tools...");
+        tracing::debug!("🔧 get_openai_tools called, getting MCP tools...");
         let tools = self.get_tools().await?;
-        tracing::info!("📋 Retrieved {} MCP tools from get_tools()", tools.len());
+        tracing::debug!("📋 Retrieved {} MCP tools from get_tools()", tools.len());
         
         let openai_tools: Vec<ChatCompletionTool> = tools.into_iter().map(|tool| {
             ChatCompletionTool {
@@ -529,13 +531,17 @@ impl McpClientManager {
             }
         }).collect();
 
-        tracing::info!("🔧 Converted {} MCP tools to OpenAI format", openai_tools.len());
+        tracing::debug!("🔧 Converted {} MCP tools to OpenAI format", openai_tools.len());
         Ok(openai_tools)
     }
 
     /// Call a tool using direct HTTP requests (with retries)
     pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<McpToolResponse, McpError> {
-        tracing::info!("🔧 Calling MCP tool: {} with arguments: {}", name, arguments);
+        tracing::debug!(
+            "🔧 Calling MCP tool: {} with arguments: {}",
+            name,
+            redact::preview(&arguments.to_string(), 500)
+        );
         
         // Initialize connection if not already done
         if self.session_id.is_none() {
@@ -658,7 +664,7 @@ impl McpClientManager {
                 return Err(McpError::Protocol(format!("Tool execution error: {}", error_msg)));
             }
             
-            tracing::info!("✅ MCP tool call completed successfully");
+            tracing::debug!("✅ MCP tool call completed successfully");
             Ok(tool_response)
         } else {
             Err(McpError::Protocol(format!(
@@ -717,8 +723,8 @@ impl McpClientManager {
                 
                 // Read response body
                 let body = response.text().await.unwrap_or_default();
-                if body.len() > 500 {
-                    tracing::debug!("🌐 GET response body (truncated): {}...", &body[..500]);
+                if body.chars().count() > 500 {
+                    tracing::debug!("🌐 GET response body (truncated): {}...", redact::truncate(&body, 500));
                 } else {
                     tracing::debug!("🌐 GET response body: {}", body);
                 }
@@ -765,8 +771,8 @@ impl McpClientManager {
                 tracing::debug!("📤 Test POST response headers: {:?}", headers);
                 
                 let body = response.text().await.unwrap_or_default();
-                if body.len() > 200 {
-                    tracing::debug!("📤 Test POST response body (truncated): {}...", &body[..200]);
+                if body.chars().count() > 200 {
+                    tracing::debug!("📤 Test POST response body (truncated): {}...", redact::truncate(&body, 200));
                 } else {
                     tracing::debug!("📤 Test POST response body: {}", body);
                 }
@@ -1,11 +1,10 @@
-use async_openai::{
-    Client,
-    types::{
-        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-        ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
-    },
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    ChatCompletionRequestUserMessage, CompletionUsage,
 };
 use async_std::sync::RwLock;
+use figment::{Figment, providers::Env};
+use serde::Deserialize;
 use async_std::task;
 use opentelemetry_http::HttpError;
 use serde_json::json;
@@ -16,33 +15,108 @@ use uuid::Uuid;
 use crate::{
     models::{
         topics::{
-            Topic,
+            SummaryGeneration, Topic, TopicSummary,
             post::{Post, WorkshopPost},
         },
-        workshop::{chat::WorkshopChat, message::WorkshopMessage},
+        workshop::{chat::WorkshopChat, context::WorkshopChatContext, message::WorkshopMessage},
     },
     modules::workshop::prompts::{
-        OngoingPrompt, OngoingPromptManager, SHORTSUM_MODEL, SUMMARY_MODEL,
-        truncate_messages_to_token_limit,
+        OngoingPrompt, OngoingPromptManager, PromptConfig, TOPIC_CONTEXT_MAX_TOKENS,
+        add_usage, estimate_tokens_in_text, truncate_messages_to_token_limit,
     },
+    modules::{metrics::Metrics, redact},
     state::AppState,
 };
 
+pub mod breaker;
+pub mod catalog;
+pub mod chunks;
+pub mod input;
+pub mod language;
 pub mod mcp_client;
 pub mod prompts;
+pub mod provider;
+pub mod warmup;
 
 pub struct WorkshopService {
-    pub client: Client<async_openai::config::OpenAIConfig>,
+    // OpenAI-compatible providers, in order of preference
+    pub providers: provider::Providers,
     pub prompts: WorkshopPrompts,
     // Manager for request coalescing of streaming responses
     pub ongoing_prompts: OngoingPromptManager,
     // MCP client manager for AI tool calling
     pub mcp_client: Arc<RwLock<mcp_client::McpClientManager>>,
+    // Total tokens a single user may spend, unlimited when unset
+    pub token_budget: Option<i64>,
+    // Models offered to users
+    pub models: catalog::ModelCatalog,
+    // When a topic has grown enough for its summary to be considered outdated
+    pub summary_staleness: SummaryStaleness,
+    // Language summaries are written in
+    pub summary_language: language::SummaryLanguageMode,
+    // Which posts of a topic fit in its summary prompt
+    pub summary_input: input::SummaryInput,
+    // Short-circuits prompts while the provider keeps failing
+    pub breaker: breaker::CircuitBreaker,
+}
+
+/// A summary goes stale once its topic gained `posts` new posts or grew by `ratio`, whichever comes first
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummaryStaleness {
+    #[serde(default = "default_stale_posts")]
+    pub posts: i32,
+    #[serde(default = "default_stale_ratio")]
+    pub ratio: f64,
+    /// Regenerate stale summaries right away instead of on the next read
+    #[serde(default)]
+    pub auto_regenerate: bool,
+}
+
+fn default_stale_posts() -> i32 {
+    10
+}
+
+fn default_stale_ratio() -> f64 {
+    0.25
+}
+
+impl Default for SummaryStaleness {
+    fn default() -> Self {
+        Self {
+            posts: default_stale_posts(),
+            ratio: default_stale_ratio(),
+            auto_regenerate: false,
+        }
+    }
+}
+
+impl SummaryStaleness {
+    pub fn load() -> Self {
+        Figment::new()
+            .merge(Env::prefixed("SUMMARY_STALE_"))
+            .extract::<SummaryStaleness>()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Invalid summary staleness config, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn is_stale(&self, summarized_post_count: i32, post_count: i32) -> bool {
+        let new_posts = post_count - summarized_post_count;
+        if new_posts <= 0 {
+            return false;
+        }
+
+        new_posts >= self.posts
+            || (summarized_post_count > 0
+                && new_posts as f64 / summarized_post_count as f64 >= self.ratio)
+    }
 }
 
 pub struct WorkshopPrompts {
     pub summerize: ChatCompletionRequestMessage,
     pub shortsum: ChatCompletionRequestMessage,
+    pub chunk_summary: ChatCompletionRequestMessage,
 }
 
 impl Default for WorkshopPrompts {
@@ -56,29 +130,20 @@ impl Default for WorkshopPrompts {
                 content: prompts::SHORTSUM_PROMPT.to_string().into(),
                 name: None,
             }),
+            chunk_summary: ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                content: prompts::CHUNK_SUMMARY_PROMPT.to_string().into(),
+                name: None,
+            }),
         }
     }
 }
 
 impl WorkshopService {
-    pub async fn init() -> Self {
-        let api_key =
-            std::env::var("WORKSHOP_INTELLIGENCE_KEY").expect("WORKSHOP_INTELLIGENCE_KEY not set");
-
-        let base_url = std::env::var("WORKSHOP_INTELLIGENCE_BASE_URL")
-            .unwrap_or_else(|_| "https://openrouter.ai/api/v1".to_string());
-
+    pub async fn init(metrics: &Metrics) -> Self {
         tracing::info!("🔧 Workshop Service Init:");
-        tracing::info!("  API Key present: {}", !api_key.is_empty());
-        tracing::info!("  API Key length: {}", api_key.len());
-        tracing::info!("  Base URL: {}", base_url);
-
-        let config = async_openai::config::OpenAIConfig::new()
-            .with_api_key(api_key)
-            .with_api_base(base_url);
 
-        let client = Client::with_config(config);
-        tracing::info!("  OpenAI client configured successfully");
+        let providers = provider::Providers::load(metrics.ai.clone());
+        tracing::info!("  OpenAI clients configured successfully");
 
         // Initialize MCP client manager
         let mut mcp_client = mcp_client::McpClientManager::new();
@@ -89,50 +154,140 @@ impl WorkshopService {
             tracing::warn!("Failed to initialize MCP client: {}", e);
         }
 
+        let token_budget = std::env::var("WORKSHOP_USER_TOKEN_BUDGET")
+            .ok()
+            .and_then(|budget| budget.parse::<i64>().ok())
+            .filter(|budget| *budget > 0);
+        tracing::info!("  User token budget: {:?}", token_budget);
+
         Self {
-            client,
+            providers,
             prompts: WorkshopPrompts::default(),
             ongoing_prompts: OngoingPromptManager::new(),
             mcp_client: Arc::new(RwLock::new(mcp_client)),
+            token_budget,
+            models: catalog::ModelCatalog::new(),
+            summary_staleness: SummaryStaleness::load(),
+            summary_language: language::SummaryLanguageMode::load(),
+            summary_input: input::SummaryInput::load(),
+            breaker: breaker::CircuitBreaker::load(metrics.ai.clone()),
         }
     }
 
-    pub async fn create_workshop_summary(
+    /// Build the summary prompt for a topic, along with the tokens spent summarizing the windows
+    /// of a long topic
+    async fn summary_messages(
         topic: &Topic,
         state: &AppState,
-    ) -> Result<String, HttpError> {
-        let posts =
-            Post::find_by_topic_id(&topic.discourse_id, topic.topic_id, 1, Some(512), state).await;
+    ) -> (Vec<ChatCompletionRequestMessage>, Option<CompletionUsage>) {
+        let detected = Self::topic_language(topic, state).await;
 
-        let (posts, _) = posts.unwrap_or_default();
-        let posts: Vec<WorkshopPost> = posts.into_iter().map(|x| {
-            x.into()
-        }).collect();
+        let mut messages = vec![state.workshop.prompts.summerize.clone()];
+        if let Some(instruction) =
+            language::language_instruction(detected, state.workshop.summary_language)
+        {
+            messages.push(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                content: instruction.into(),
+                name: None,
+            }));
+        }
 
-        let messages = vec![
-            state.workshop.prompts.summerize.clone(),
-            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
-                content: serde_json::to_string(&json!({
+        let chunks = if state.workshop.summary_input.uses_map_reduce(topic) {
+            chunks::summarize_chunks(topic, state)
+                .await
+                .inspect_err(|e| {
+                    tracing::warn!(
+                        "Map-reduce summary of topic {} failed, summarizing selected posts: {:?}",
+                        topic.topic_id,
+                        e
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+
+        let mut usage = None;
+        let content = match chunks {
+            Some((chunks, chunks_usage)) => {
+                usage = chunks_usage;
+                messages.push(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                    content: chunks::REDUCE_INSTRUCTION.into(),
+                    name: None,
+                }));
+                json!({
                     "topic_info": topic,
-                    "posts": posts,
-                }))
-                .unwrap()
-                .into(),
-                name: None,
-            }),
-        ];
+                    "chunk_summaries": chunks,
+                })
+            }
+            None => {
+                let selected = state
+                    .workshop
+                    .summary_input
+                    .select_posts(topic, state)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::error!("Error selecting posts of topic {} for summary: {:?}", topic.topic_id, e);
+                        input::SelectedPosts::default()
+                    });
+
+                let mut content = json!({
+                    "topic_info": topic,
+                    "posts": selected.posts,
+                });
+                if selected.omitted > 0 {
+                    // tell the model the discussion has gaps
+                    content["omitted_posts"] = selected.omitted.into();
+                }
+                content
+            }
+        };
+        messages.push(ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+            content: serde_json::to_string(&content).unwrap().into(),
+            name: None,
+        }));
+
+        (messages, usage)
+    }
+
+    /// Language of a topic, detected from its first posts
+    async fn topic_language(topic: &Topic, state: &AppState) -> Option<whatlang::Lang> {
+        let posts: Vec<WorkshopPost> =
+            Post::find_page_from_end(&topic.discourse_id, topic.topic_id, false, 100, 0, state)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect();
+
+        language::detect_language(&topic.title, &posts)
+    }
+
+    /// Detected language of a topic as an ISO 639-3 code, recorded with its summary
+    pub async fn detect_topic_language(topic: &Topic, state: &AppState) -> Option<String> {
+        Self::topic_language(topic, state)
+            .await
+            .map(|language| language.code().to_string())
+    }
+
+    /// Generate a summary without streaming, returns the summary and the token usage
+    pub async fn create_workshop_summary(
+        topic: &Topic,
+        state: &AppState,
+    ) -> Result<(String, Option<CompletionUsage>), HttpError> {
+        let (messages, chunks_usage) = Self::summary_messages(topic, state).await;
 
         // Apply token limits to prevent excessive costs
         let truncated_messages = truncate_messages_to_token_limit(messages, &None);
 
-        let request = CreateChatCompletionRequest {
-            model: SUMMARY_MODEL.to_string(),
-            messages: truncated_messages,
-            max_completion_tokens: Some(2000), // Limit output to 2k tokens for summaries
-            ..Default::default()
-        };
+        let request = PromptConfig::summary().request(truncated_messages);
 
-        let chat_completion = state.workshop.client.chat().create(request).await?;
+        state.workshop.breaker.try_acquire()?;
+        let (chat_completion, provider) = state
+            .workshop
+            .breaker
+            .observe(state.workshop.providers.create(request).await)?;
+        tracing::info!("Summary for topic {} served by {}", topic.topic_id, provider);
 
         let response = chat_completion.choices.first().unwrap().message.clone();
 
@@ -146,7 +301,53 @@ impl WorkshopService {
             );
         }
 
-        Ok(response.content.unwrap_or_default())
+        Ok((
+            response.content.unwrap_or_default(),
+            add_usage(chat_completion.usage, chunks_usage),
+        ))
+    }
+
+    /// Condense a topic into a context message for a chat
+    ///
+    /// Short topics are passed along as-is, long topics are replaced by their summary
+    pub async fn build_topic_context(topic: &Topic, state: &AppState) -> Result<String, HttpError> {
+        let posts =
+            Post::find_by_topic_id(&topic.discourse_id, topic.topic_id, 1, Some(512), state)
+                .await
+                .unwrap_or_default()
+                .items;
+        let posts: Vec<WorkshopPost> = posts.into_iter().map(|x| x.into()).collect();
+
+        let full = serde_json::to_string(&json!({
+            "topic_info": topic,
+            "posts": posts,
+        }))
+        .unwrap_or_default();
+
+        let content = if estimate_tokens_in_text(&full) <= TOPIC_CONTEXT_MAX_TOKENS {
+            full
+        } else {
+            tracing::info!(
+                "Topic {} on {} is too long for context, summarizing",
+                topic.topic_id,
+                topic.discourse_id
+            );
+            let summary =
+                Topic::get_summary_by_topic_id(&topic.discourse_id, topic.topic_id, state).await?;
+            serde_json::to_string(&json!({
+                "topic_info": topic,
+                "summary": summary.summary_text,
+            }))
+            .unwrap_or_default()
+        };
+
+        Ok(format!(
+            "The user attached forum topic #{} \"{}\" from {} to this conversation:\n{}",
+            topic.topic_id,
+            topic.title,
+            topic.discourse_id,
+            content
+        ))
     }
 
     /// Process next message with default model
@@ -184,7 +385,7 @@ impl WorkshopService {
                 .map(|m| m.into())
                 .collect();
 
-        tracing::info!("📝 Retrieved {} messages for context", messages.len());
+        tracing::debug!("📝 Retrieved {} messages for context", messages.len());
 
         // Process MCP tool calls from the conversation
         match mcp_client::ToolCallHelper::process_tool_calls(
@@ -197,7 +398,10 @@ impl WorkshopService {
                 if !tool_results.is_empty() {
                     let tool_context =
                         mcp_client::ToolCallHelper::format_tool_results(&tool_results);
-                    info!("Adding tool results to context: {}", tool_context);
+                    tracing::debug!(
+                        "Adding tool results to context: {}",
+                        redact::preview(&tool_context, 200)
+                    );
 
                     // Add tool results as a system message
                     let tool_message =
@@ -221,19 +425,31 @@ impl WorkshopService {
 
         messages.insert(0, system_message);
 
+        // Topics attached to the chat go right after the system prompt
+        let attached = WorkshopChatContext::find_by_chat_id(chat_id, state).await?;
+        for (index, context) in attached.into_iter().enumerate() {
+            messages.insert(
+                index + 1,
+                ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+                    content: context.context.into(),
+                    name: None,
+                }),
+            );
+        }
+
         // Get available MCP tools for the chat completion
-        tracing::info!("🔧 Getting MCP tools...");
+        tracing::debug!("🔧 Getting MCP tools...");
         let mut mcp_client_lock_result = state.workshop.mcp_client.write().await;
-        tracing::info!("🔓 MCP client lock acquired successfully");
+        tracing::debug!("🔓 MCP client lock acquired successfully");
 
         let tools = match mcp_client_lock_result.get_openai_tools().await {
             Ok(mut tools) if !tools.is_empty() => {
-                tracing::info!("✅ Got {} MCP tools", tools.len());
+                tracing::debug!("✅ Got {} MCP tools", tools.len());
 
                 Some(tools)
             }
             Ok(_) => {
-                tracing::info!("ℹ️ No MCP tools available");
+                tracing::debug!("ℹ️ No MCP tools available");
                 None
             }
             Err(e) => {
@@ -243,18 +459,18 @@ impl WorkshopService {
         };
 
         drop(mcp_client_lock_result); // Explicitly drop the lock
-        tracing::info!("🔒 MCP client lock released");
+        tracing::debug!("🔒 MCP client lock released");
 
         // Use chat_id + message_id as the coalescing key
         let key = format!("{}-{}", chat_id, message_id);
-        tracing::info!("🔑 Using coalescing key: {}", key);
+        tracing::debug!("🔑 Using coalescing key: {}", key);
 
         // Get or create the ongoing prompt
-        tracing::info!("🚀 Creating OngoingPrompt...");
+        tracing::debug!("🚀 Creating OngoingPrompt...");
         let ongoing_prompt = state
             .workshop
             .ongoing_prompts
-            .get_or_create(key.clone(), state, messages, tools, model)
+            .get_or_create(key.clone(), state, messages, tools, PromptConfig::chat(model))
             .await
             .map_err(|e| {
                 tracing::error!("❌ Failed to create OngoingPrompt: {}", e);
@@ -277,7 +493,7 @@ impl WorkshopService {
             .ongoing_prompts
             .insert_additional_key(system_message_key.clone(), ongoing_prompt.clone())
             .await;
-        tracing::info!(
+        tracing::debug!(
             "Also stored ongoing prompt with system key: {}",
             system_message_key
         );
@@ -289,7 +505,7 @@ impl WorkshopService {
         let system_message_key_clone = system_message_key.clone();
 
         task::spawn(async move {
-            tracing::info!("⏳ Waiting for prompt completion...");
+            tracing::debug!("⏳ Waiting for prompt completion...");
             match prompt_clone.await_completion().await {
                 Ok(content) => {
                     tracing::info!(
@@ -299,7 +515,7 @@ impl WorkshopService {
 
                     // Collect all streaming events
                     let streaming_events = prompt_clone.get_all_events().await;
-                    tracing::info!("📊 Collected {} streaming events", streaming_events.len());
+                    tracing::debug!("📊 Collected {} streaming events", streaming_events.len());
 
                     // Get usage data and model information
                     let usage_data = prompt_clone.get_usage_data().await;
@@ -321,7 +537,7 @@ impl WorkshopService {
                     {
                         tracing::error!("❌ Error updating message with token usage: {:?}", e);
                     } else {
-                        tracing::info!(
+                        tracing::debug!(
                             "✅ Updated message with empty content field (using streaming_events as source of truth)"
                         );
                         if let Some(usage) = &usage_data {
@@ -344,7 +560,7 @@ impl WorkshopService {
                     {
                         tracing::error!("❌ Error updating chat: {:?}", e);
                     } else {
-                        tracing::info!("✅ Updated chat last message successfully");
+                        tracing::debug!("✅ Updated chat last message successfully");
                         // Trigger background summarization agent after successful update
                         Self::shortsum_agent(system_response_clone.chat_id, state_clone.clone())
                             .await;
@@ -356,7 +572,7 @@ impl WorkshopService {
 
                     // Collect streaming events even on error (may contain partial tool calls)
                     let streaming_events = prompt_clone.get_all_events().await;
-                    tracing::info!(
+                    tracing::debug!(
                         "📊 Collected {} streaming events (with error)",
                         streaming_events.len()
                     );
@@ -385,7 +601,7 @@ impl WorkshopService {
                             update_err
                         );
                     } else {
-                        tracing::info!(
+                        tracing::debug!(
                             "📝 Updated message with error content, streaming events, and token usage"
                         );
                         if let Some(usage) = &usage_data {
@@ -407,7 +623,7 @@ impl WorkshopService {
                 .ongoing_prompts
                 .remove(&system_message_key_clone)
                 .await;
-            tracing::info!(
+            tracing::debug!(
                 "Cleaned up system message key: {}",
                 system_message_key_clone
             );
@@ -423,7 +639,7 @@ impl WorkshopService {
         message_id: Uuid,
     ) -> Option<OngoingPrompt> {
         let key = format!("{}-{}", chat_id, message_id);
-        info!("Getting ongoing prompt for key: {}", key);
+        tracing::debug!("Getting ongoing prompt for key: {}", key);
         self.ongoing_prompts.get(&key).await
     }
 
@@ -433,32 +649,23 @@ impl WorkshopService {
         topic: &Topic,
         state: &AppState,
     ) -> Result<OngoingPrompt, Box<dyn std::error::Error + Send + Sync>> {
-        let posts =
-            Post::find_by_topic_id(&topic.discourse_id, topic.topic_id, 1, Some(512), state).await;
-        let (posts, _) = posts.unwrap_or_default();
-        let posts: Vec<WorkshopPost> = posts.into_iter().map(|x| {
-            x.into()
-        }).collect();
-
-        let messages = vec![
-            state.workshop.prompts.summerize.clone(),
-            ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
-                content: serde_json::to_string(&json!({
-                    "topic_info": topic,
-                    "posts": posts,
-                }))
-                .unwrap()
-                .into(),
-                name: None,
-            }),
-        ];
+        // Use topic_id as the coalescing key for summaries
+        let key = Self::summary_key(&topic.discourse_id, topic.topic_id);
+
+        // join a generation in flight instead of summarizing the windows again, finished ones
+        // stay registered and make room for the new generation
+        if let Some(ongoing_prompt) = state.workshop.ongoing_prompts.get(&key).await {
+            if !ongoing_prompt.is_complete().await {
+                return Ok(ongoing_prompt);
+            }
+            state.workshop.ongoing_prompts.remove(&key).await;
+        }
+
+        let (messages, chunks_usage) = Self::summary_messages(topic, state).await;
 
         // Apply token limits to prevent excessive costs
         let truncated_messages = truncate_messages_to_token_limit(messages, &None);
 
-        // Use topic_id as the coalescing key for summaries
-        let key = Self::summary_key(&topic.discourse_id, topic.topic_id);
-
         // Get or create the ongoing prompt (no tools needed for summaries)
         let ongoing_prompt = state
             .workshop
@@ -468,13 +675,57 @@ impl WorkshopService {
                 state,
                 truncated_messages,
                 None,
-                Some(SUMMARY_MODEL.to_string()),
+                PromptConfig::summary(),
             )
             .await?;
+        ongoing_prompt.add_preparation_usage(chunks_usage).await;
 
         Ok(ongoing_prompt)
     }
 
+    /// Store the summary a streamed generation produces once it completes
+    pub fn store_summary_when_complete(topic: Topic, ongoing_prompt: OngoingPrompt, state: AppState) {
+        task::spawn(async move {
+            let content = match ongoing_prompt.await_completion().await {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::error!("Error in summary completion: {:?}", e);
+                    return;
+                }
+            };
+
+            let based_on = topic
+                .last_post_at
+                .map(|dt| dt.timestamp())
+                .unwrap_or_else(|| chrono::Utc::now().timestamp());
+            let based_on_datetime = chrono::DateTime::from_timestamp(based_on, 0)
+                .unwrap_or_else(chrono::Utc::now);
+
+            let generation = SummaryGeneration::new(
+                &topic,
+                &PromptConfig::summary(),
+                ongoing_prompt.get_model_used().await,
+                ongoing_prompt.get_usage_data().await,
+            )
+            .with_language(Self::detect_topic_language(&topic, &state).await);
+
+            if let Err(e) = TopicSummary::create(
+                &topic.discourse_id,
+                topic.topic_id,
+                based_on_datetime,
+                &content,
+                &generation,
+                &state,
+            )
+            .await
+            {
+                tracing::error!("Error saving topic summary: {:?}", e);
+            } else {
+                tracing::info!("Saved new summary for topic_id: {}", topic.topic_id);
+            }
+        });
+    }
+
     fn summary_key(discourse_id: &str, topic_id: i32) -> String {
         format!("summary-{}-{}", discourse_id, topic_id)
     }
@@ -486,13 +737,13 @@ impl WorkshopService {
         topic_id: i32,
     ) -> Option<OngoingPrompt> {
         let key = Self::summary_key(discourse_id, topic_id);
-        info!("Getting ongoing summary prompt for key: {}", key);
+        tracing::debug!("Getting ongoing summary prompt for key: {}", key);
         self.ongoing_prompts.get(&key).await
     }
 
     /// Background agent that summarizes conversations after last message updates
     pub async fn shortsum_agent(chat_id: Uuid, state: AppState) {
-        tracing::info!("🔄 Starting shortsum agent for chat: {}", chat_id);
+        tracing::debug!("🔄 Starting shortsum agent for chat: {}", chat_id);
 
         // Spawn the task to run in the background
         task::spawn(async move {
@@ -586,14 +837,13 @@ impl WorkshopService {
         let truncated_summary_messages = truncate_messages_to_token_limit(summary_messages, &None);
 
         // Generate the summary using async-openai
-        let request = CreateChatCompletionRequest {
-            model: SHORTSUM_MODEL.to_string(),
-            messages: truncated_summary_messages,
-            max_completion_tokens: Some(40),
-            ..Default::default()
-        };
+        let request = PromptConfig::shortsum().request(truncated_summary_messages);
 
-        let chat_completion = state.workshop.client.chat().create(request).await?;
+        state.workshop.breaker.try_acquire()?;
+        let (chat_completion, _) = state
+            .workshop
+            .breaker
+            .observe(state.workshop.providers.create(request).await)?;
 
         let summary = chat_completion
             .choices
@@ -612,12 +862,30 @@ impl WorkshopService {
             );
         }
 
-        tracing::info!(
+        tracing::debug!(
             "📝 Generated summary for chat {}: {}",
             chat_id,
-            &summary[..summary.len().min(100)]
+            redact::preview(&summary, 100)
         );
 
         Ok(summary)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_staleness() {
+        let staleness = SummaryStaleness::default();
+
+        assert!(!staleness.is_stale(40, 40));
+        assert!(!staleness.is_stale(40, 45));
+        // +10 posts
+        assert!(staleness.is_stale(100, 110));
+        // +25%
+        assert!(staleness.is_stale(8, 10));
+        assert!(!staleness.is_stale(8, 9));
+    }
+}
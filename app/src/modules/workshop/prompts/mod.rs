@@ -1,10 +1,10 @@
 use async_std::task;
 use futures::{Stream, StreamExt, stream};
 use async_openai::{
-    types::{ChatCompletionRequestMessage, CreateChatCompletionRequest, ChatCompletionTool,
+    types::{ChatCompletionRequestMessage, CreateChatCompletionRequest, ChatCompletionTool, Stop,
         ChatCompletionRequestAssistantMessage, ChatCompletionRequestToolMessage,
         ChatCompletionRequestAssistantMessageContent, ChatCompletionMessageToolCall,
-        ChatCompletionToolType, FunctionCall},
+        ChatCompletionToolType, CreateChatCompletionStreamResponse, FunctionCall},
 };
 use std::collections::{VecDeque, HashMap};
 use std::sync::Arc;
@@ -14,6 +14,7 @@ use tracing;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 
+use crate::modules::redact;
 use crate::state::AppState;
 
 /// Helper function to normalize tool arguments by converting string numbers to actual numbers
@@ -87,15 +88,123 @@ pub const WORKSHOP_MODEL: &str = "google/gemini-2.5-flash-preview-05-20";
 pub const SHORTSUM_PROMPT: &str = include_str!("./shortsum.md");
 pub const SHORTSUM_MODEL: &str = "mistralai/mistral-7b-instruct:free";
 
+pub const CHUNK_SUMMARY_PROMPT: &str = include_str!("./chunk_summary.md");
+
+/// Sampling settings for a single prompt request
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptConfig {
+    pub model: String,
+    /// 0.0 to 2.0, lower is more deterministic
+    pub temperature: Option<f32>,
+    /// 0.0 (exclusive) to 1.0
+    pub top_p: Option<f32>,
+    /// At most 4 sequences, generation stops when one is produced
+    pub stop: Option<Vec<String>>,
+    pub max_completion_tokens: Option<u32>,
+    /// Best-effort determinism, identical inputs with the same seed should produce the same output
+    pub seed: Option<i64>,
+}
+
+/// Seed used for topic summaries, so regenerating an unchanged topic is stable
+pub const SUMMARY_SEED: i64 = 1559;
+
+impl PromptConfig {
+    /// Defaults for interactive workshop chats
+    pub fn chat(model: Option<String>) -> Self {
+        Self {
+            model: model.unwrap_or_else(|| WORKSHOP_MODEL.to_string()),
+            temperature: Some(0.7),
+            top_p: None,
+            stop: None,
+            max_completion_tokens: Some(4000), // Limit output tokens to 4k to prevent excessive generation costs
+            seed: None,
+        }
+    }
+
+    /// Defaults for topic summaries, kept close to deterministic
+    pub fn summary() -> Self {
+        Self {
+            model: SUMMARY_MODEL.to_string(),
+            temperature: Some(0.2),
+            top_p: None,
+            stop: None,
+            max_completion_tokens: Some(2000), // Limit output to 2k tokens for summaries
+            seed: Some(SUMMARY_SEED),
+        }
+    }
+
+    /// Defaults for the short chat titles
+    pub fn shortsum() -> Self {
+        Self {
+            model: SHORTSUM_MODEL.to_string(),
+            temperature: Some(0.3),
+            top_p: None,
+            stop: Some(vec!["\n".to_string()]),
+            max_completion_tokens: Some(40),
+            seed: None,
+        }
+    }
+
+    /// Defaults for summarizing a window of a large topic, combined by the summary prompt
+    pub fn chunk_summary() -> Self {
+        Self {
+            model: SHORTSUM_MODEL.to_string(),
+            temperature: Some(0.2),
+            top_p: None,
+            stop: None,
+            max_completion_tokens: Some(600),
+            seed: Some(SUMMARY_SEED),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(format!("temperature must be between 0 and 2, got {}", temperature));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(top_p > 0.0 && top_p <= 1.0) {
+                return Err(format!("top_p must be greater than 0 and at most 1, got {}", top_p));
+            }
+        }
+        if let Some(stop) = &self.stop {
+            if stop.len() > 4 {
+                return Err(format!("at most 4 stop sequences are allowed, got {}", stop.len()));
+            }
+            if stop.iter().any(|sequence| sequence.is_empty()) {
+                return Err("stop sequences must not be empty".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a request for these settings
+    pub fn request(&self, messages: Vec<ChatCompletionRequestMessage>) -> CreateChatCompletionRequest {
+        CreateChatCompletionRequest {
+            model: self.model.clone(),
+            messages,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            stop: self.stop.clone().map(Stop::StringArray),
+            max_completion_tokens: self.max_completion_tokens,
+            seed: self.seed,
+            ..Default::default()
+        }
+    }
+}
+
 /// Constants for token limits
 const MAX_INPUT_TOKENS: usize = 180000; // Limit input to 32k tokens to prevent excessive costs
 const TOKENS_PER_MESSAGE_OVERHEAD: usize = 4; // Overhead tokens per message (role, formatting, etc.)
 const TOKENS_PER_NAME: usize = 1; // Additional tokens if name is present
+/// Topics attached to a chat are summarized when their posts exceed this many tokens
+pub const TOPIC_CONTEXT_MAX_TOKENS: usize = 8000;
 
 /// Simple token estimation function
 /// This is a rough estimate - for exact counts you'd need the actual tokenizer
 /// But this is good enough for preventing runaway costs
-fn estimate_tokens_in_text(text: &str) -> usize {
+pub fn estimate_tokens_in_text(text: &str) -> usize {
     // Rough estimate: ~4 characters per token for English text
     // This errs on the side of overestimating to be safe
     (text.len() as f64 / 3.5).ceil() as usize
@@ -206,12 +315,28 @@ pub fn truncate_messages_to_token_limit(mut messages: Vec<ChatCompletionRequestM
             total_tokens
         );
     } else {
-        tracing::info!("✅ Messages within token limit. Estimated tokens: {}", total_tokens);
+        tracing::debug!("✅ Messages within token limit. Estimated tokens: {}", total_tokens);
     }
     
     kept_messages
 }
 
+/// Sum of the token usage of two requests, token details are kept from the first
+pub fn add_usage(
+    usage: Option<async_openai::types::CompletionUsage>,
+    other: Option<async_openai::types::CompletionUsage>,
+) -> Option<async_openai::types::CompletionUsage> {
+    match (usage, other) {
+        (Some(mut usage), Some(other)) => {
+            usage.prompt_tokens += other.prompt_tokens;
+            usage.completion_tokens += other.completion_tokens;
+            usage.total_tokens += other.total_tokens;
+            Some(usage)
+        }
+        (usage, other) => usage.or(other),
+    }
+}
+
 /// Enhanced state for streaming with tool call support
 #[derive(Clone)]
 pub struct OngoingPromptState {
@@ -223,7 +348,11 @@ pub struct OngoingPromptState {
     pub conversation_history: Arc<RwLock<Vec<ChatCompletionRequestMessage>>>,
     pub tools: Arc<RwLock<Option<Vec<ChatCompletionTool>>>>,
     pub usage_data: Arc<RwLock<Option<async_openai::types::CompletionUsage>>>,
+    /// Tokens spent building the prompt, like the window summaries of a long topic
+    pub preparation_usage: Arc<RwLock<Option<async_openai::types::CompletionUsage>>>,
     pub model_used: Arc<RwLock<Option<String>>>,
+    pub provider_used: Arc<RwLock<Option<String>>>,
+    pub cancelled: Arc<RwLock<bool>>,
 }
 
 /// Streaming entry types to support different kinds of streaming content
@@ -233,6 +362,8 @@ pub struct StreamingEntry {
     #[serde(rename = "type")]
     pub entry_type: StreamingEntryType,
     pub tool_call: Option<ToolCallEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<StreamingUsage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -241,6 +372,18 @@ pub enum StreamingEntryType {
     ToolCallStart,
     ToolCallResult,
     ToolCallError,
+    /// Last entry of a prompt, carrying its token usage
+    Usage,
+}
+
+/// Token usage of a finished prompt, counts are missing when the provider didn't report them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingUsage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -261,6 +404,74 @@ pub enum ToolCallStatus {
     Error,
 }
 
+/// Drops content a provider replays after reconnecting mid-stream
+///
+/// Chunks carry no offset, so the content received so far is tracked per choice. A reconnect
+/// shows as chunks of another response (new `id` or `created`) mid-turn; content after it is
+/// dropped while it repeats what was already received and passed through from where it goes
+/// beyond that. Within one response nothing is dropped, repeated tokens are legitimate. Role
+/// deltas say nothing here, some providers send one with every chunk.
+#[derive(Default)]
+struct ChunkDeduper {
+    /// `id` and `created` of the response chunks currently come from
+    response: Option<(String, u32)>,
+    choices: HashMap<u32, ChoiceProgress>,
+}
+
+#[derive(Default)]
+struct ChoiceProgress {
+    received: String,
+    /// Position in `received` a replay has reached
+    replayed: Option<usize>,
+}
+
+impl ChunkDeduper {
+    /// Strip replayed content from the chunk, returns whether any was dropped
+    fn dedupe(&mut self, chunk: &mut CreateChatCompletionStreamResponse) -> bool {
+        let mut dropped = false;
+
+        let response = (chunk.id.clone(), chunk.created);
+        if self.response.as_ref().is_some_and(|current| *current != response) {
+            for progress in self.choices.values_mut() {
+                progress.replayed = (!progress.received.is_empty()).then_some(0);
+            }
+        }
+        self.response = Some(response);
+
+        for choice in &mut chunk.choices {
+            let progress = self.choices.entry(choice.index).or_default();
+
+            let Some(content) = &mut choice.delta.content else {
+                continue;
+            };
+
+            if let Some(offset) = progress.replayed {
+                let ahead = &progress.received[offset..];
+                let repeated = content
+                    .char_indices()
+                    .zip(ahead.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, c), _)| i + c.len_utf8());
+
+                if repeated == content.len() && repeated < ahead.len() {
+                    progress.replayed = Some(offset + repeated);
+                } else {
+                    progress.replayed = None;
+                }
+                if repeated > 0 {
+                    content.replace_range(..repeated, "");
+                    dropped = true;
+                }
+            }
+
+            progress.received.push_str(content);
+        }
+
+        dropped
+    }
+}
+
 /// Enhanced OngoingPrompt with tool calling support
 #[derive(Clone)]
 pub struct OngoingPrompt {
@@ -268,23 +479,20 @@ pub struct OngoingPrompt {
 }
 
 impl OngoingPrompt {
-    pub async fn new(state: &AppState, messages: Vec<ChatCompletionRequestMessage>, tools: Option<Vec<ChatCompletionTool>>, model: Option<String>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        tracing::info!("🚀 Creating new OngoingPrompt with {} messages and {} tools", 
-            messages.len(), tools.as_ref().map(|t| t.len()).unwrap_or(0));
-        
-        let model = model.unwrap_or_else(|| WORKSHOP_MODEL.to_string());
-        
-        tracing::info!("📡 API Request Details:");
-        tracing::info!("  Model: {}", model);
-        tracing::info!("  Messages count: {}", messages.len());
-        tracing::info!("  Tools count: {}", tools.as_ref().map(|t| t.len()).unwrap_or(0));
-        tracing::info!("  Stream: true");
+    pub async fn new(state: &AppState, messages: Vec<ChatCompletionRequestMessage>, tools: Option<Vec<ChatCompletionTool>>, config: PromptConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        config.validate()?;
+        state.workshop.breaker.try_acquire()?;
+        let model = config.model.clone();
+
+        tracing::info!("🚀 Creating new OngoingPrompt for {} with {} messages and {} tools",
+            model, messages.len(), tools.as_ref().map(|t| t.len()).unwrap_or(0));
+        tracing::debug!("  Temperature: {:?}, top_p: {:?}, stop: {:?}, seed: {:?}", config.temperature, config.top_p, config.stop, config.seed);
         
         // Debug log the tools being sent to identify potential issues
         if let Some(ref tools_list) = tools {
-            tracing::info!("🔧 Tools being sent to LLM:");
+            tracing::debug!("🔧 Tools being sent to LLM:");
             for (idx, tool) in tools_list.iter().enumerate() {
-                tracing::info!("  Tool {}: {} - {:?}", 
+                tracing::debug!("  Tool {}: {} - {:?}", 
                     idx + 1, 
                     &tool.function.name, 
                     &tool.function.description
@@ -294,14 +502,14 @@ impl OngoingPrompt {
                 if let Some(ref params) = tool.function.parameters {
                     let params_str = serde_json::to_string_pretty(params)
                         .unwrap_or_else(|_| "Failed to serialize".to_string());
-                    let truncated = if params_str.len() > 500 {
-                        format!("{}... [truncated]", &params_str[..500])
+                    let truncated = if params_str.chars().count() > 500 {
+                        format!("{}... [truncated]", redact::truncate(&params_str, 500))
                     } else {
                         params_str
                     };
-                    tracing::info!("    Parameters: {}", truncated);
+                    tracing::debug!("    Parameters: {}", truncated);
                 } else {
-                    tracing::info!("    Parameters: None");
+                    tracing::debug!("    Parameters: None");
                 }
             }
         }
@@ -310,24 +518,24 @@ impl OngoingPrompt {
         if let Some(first_msg) = messages.first() {
             match first_msg {
                 ChatCompletionRequestMessage::System(sys_msg) => {
-                    tracing::info!("  First message (System): {}...", 
+                    tracing::debug!("  First message (System): {}...", 
                         match &sys_msg.content {
                             async_openai::types::ChatCompletionRequestSystemMessageContent::Text(text) => 
-                                text.chars().take(100).collect::<String>(),
+                                redact::preview(text, 100),
                             _ => "[Complex content]".to_string(),
                         }
                     );
                 },
                 ChatCompletionRequestMessage::User(user_msg) => {
-                    tracing::info!("  First message (User): {}...", 
+                    tracing::debug!("  First message (User): {}...", 
                         match &user_msg.content {
                             async_openai::types::ChatCompletionRequestUserMessageContent::Text(text) => 
-                                text.chars().take(100).collect::<String>(),
+                                redact::preview(text, 100),
                             _ => "[Complex content]".to_string(),
                         }
                     );
                 },
-                _ => tracing::info!("  First message: [Other type]"),
+                _ => tracing::debug!("  First message: [Other type]"),
             }
         }
         
@@ -340,6 +548,8 @@ impl OngoingPrompt {
         let tools_arc = Arc::new(RwLock::new(tools.clone()));
         let usage_data = Arc::new(RwLock::new(None));
         let model_used = Arc::new(RwLock::new(Some(model.clone())));
+        let provider_used = Arc::new(RwLock::new(None));
+        let cancelled = Arc::new(RwLock::new(false));
         
         let ongoing_state = OngoingPromptState {
             buffer: buffer.clone(),
@@ -350,7 +560,10 @@ impl OngoingPrompt {
             conversation_history: conversation_history.clone(),
             tools: tools_arc.clone(),
             usage_data: usage_data.clone(),
+            preparation_usage: Arc::new(RwLock::new(None)),
             model_used: model_used.clone(),
+            provider_used: provider_used.clone(),
+            cancelled: cancelled.clone(),
         };
 
         // Clone everything needed for the background task
@@ -363,13 +576,16 @@ impl OngoingPrompt {
         let conversation_history_clone = conversation_history.clone();
         let tools_clone = tools_arc.clone();
         let usage_data_clone = usage_data.clone();
+        let cancelled_clone = cancelled.clone();
+        let provider_used_clone = provider_used.clone();
+        let model_used_clone = model_used.clone();
         
         task::spawn(async move {
             let mut accumulated_content = String::new();
             let mut conversation_complete = false;
             let mut completion_error: Option<String> = None;
 
-            tracing::info!("🔄 Starting enhanced stream processing with tool call support...");
+            tracing::debug!("🔄 Starting enhanced stream processing with tool call support...");
             
             while !conversation_complete && completion_error.is_none() {
                 // Get current conversation state
@@ -388,22 +604,20 @@ impl OngoingPrompt {
 
                 // Create request for this iteration
                 let request = CreateChatCompletionRequest {
-                    model: model.clone(),
-                    messages: truncated_messages,
                     tools: current_tools,
                     tool_choice: None,
                     stream: Some(true),
-                    max_completion_tokens: Some(4000), // Limit output tokens to 4k to prevent excessive generation costs
-                    ..Default::default()
+                    ..config.request(truncated_messages)
                 };
 
-                tracing::info!("📞 Making API call for conversation turn...");
-                let mut stream = match state_clone.workshop.client
-                    .chat()
-                    .create_stream(request)
-                    .await
-                {
-                    Ok(stream) => stream,
+                tracing::debug!("📞 Making API call for conversation turn...");
+                let result = state_clone.workshop.providers.create_stream(request).await;
+                let mut stream = match state_clone.workshop.breaker.observe(result) {
+                    Ok((stream, provider)) => {
+                        tracing::debug!("📡 Conversation turn served by provider: {}", provider);
+                        *provider_used_clone.write().await = Some(provider);
+                        stream
+                    }
                     Err(e) => {
                         tracing::error!("❌ Failed to create chat completion stream: {:?}", e);
                         completion_error = Some(e.to_string());
@@ -415,20 +629,31 @@ impl OngoingPrompt {
                 let mut current_tool_call: Option<ChatCompletionMessageToolCall> = None;
                 let mut chunk_count = 0;
                 let mut tools_executed_this_turn = false;
+                let mut deduper = ChunkDeduper::default();
 
                 // Process the stream for this conversation turn
                 while let Some(result) = stream.next().await {
                     chunk_count += 1;
+
+                    if *cancelled_clone.read().await {
+                        tracing::info!("🛑 Prompt cancelled by client after {} chunks", chunk_count);
+                        completion_error = Some("Cancelled".to_string());
+                        break;
+                    }
                     
                     match result {
-                        Ok(chunk) => {
+                        Ok(mut chunk) => {
                             // tracing::debug!("📦 Received chunk #{}: {:?}", chunk_count, chunk);
+
+                            if deduper.dedupe(&mut chunk) {
+                                tracing::warn!("🔁 Dropping replayed content in chunk #{}", chunk_count);
+                            }
                             
                             // Capture usage data if present
                             if let Some(usage) = &chunk.usage {
                                 let mut usage_lock = usage_data_clone.write().await;
                                 *usage_lock = Some(usage.clone());
-                                tracing::info!("💰 Captured usage data: prompt_tokens={}, completion_tokens={}, total_tokens={}", 
+                                tracing::debug!("💰 Captured usage data: prompt_tokens={}, completion_tokens={}, total_tokens={}", 
                                     usage.prompt_tokens, usage.completion_tokens, usage.total_tokens);
                             }
                             
@@ -436,7 +661,7 @@ impl OngoingPrompt {
                                 // Handle content
                                 if let Some(content) = &choice.delta.content {
                                     if !content.is_empty() {
-                                        tracing::debug!("📝 Content from chunk #{}: '{}'", chunk_count, content);
+                                        tracing::trace!("📝 Content from chunk #{}: '{}'", chunk_count, redact::preview(content, 100));
                                         
                                         // Buffer the content
                                         {
@@ -445,6 +670,7 @@ impl OngoingPrompt {
                                                 content: content.clone(),
                                                 entry_type: StreamingEntryType::Content,
                                                 tool_call: None,
+                                                usage: None,
                                             });
                                         }
                                         
@@ -456,6 +682,7 @@ impl OngoingPrompt {
                                                     content: content.clone(),
                                                     entry_type: StreamingEntryType::Content,
                                                     tool_call: None,
+                                                    usage: None,
                                                 })).is_ok()
                                             });
                                         }
@@ -467,13 +694,13 @@ impl OngoingPrompt {
 
                                 // Handle tool calls - process them immediately as they complete
                                 if let Some(ref tool_calls_chunk) = choice.delta.tool_calls {
-                                    tracing::info!("🔧 TOOL CALL DETECTED in chunk #{}", chunk_count);
+                                    tracing::debug!("🔧 TOOL CALL DETECTED in chunk #{}", chunk_count);
                                     for tool_call_chunk in tool_calls_chunk {
                                         if let Some(id) = &tool_call_chunk.id {
                                             // If we have a previous tool call that was being built, execute it now
                                             if let Some(completed_call) = current_tool_call.take() {
-                                                tracing::info!("📋 EXECUTING COMPLETED TOOL CALL: {} with args: {}", 
-                                                    completed_call.function.name, completed_call.function.arguments);
+                                                tracing::debug!("📋 EXECUTING COMPLETED TOOL CALL: {} with args: {}", 
+                                                    completed_call.function.name, redact::preview(&completed_call.function.arguments, 200));
                                                 
                                                 // Execute the tool call immediately
                                                 let tool_execution_result = Self::execute_tool_call(
@@ -491,7 +718,7 @@ impl OngoingPrompt {
                                                 }
                                             }
                                             
-                                            tracing::info!("🆕 NEW TOOL CALL STARTED: ID={}", id);
+                                            tracing::debug!("🆕 NEW TOOL CALL STARTED: ID={}", id);
                                             current_tool_call = Some(ChatCompletionMessageToolCall {
                                                 id: id.clone(),
                                                 r#type: ChatCompletionToolType::Function,
@@ -506,11 +733,11 @@ impl OngoingPrompt {
                                             if let Some(ref function) = tool_call_chunk.function {
                                                 if let Some(ref name) = function.name {
                                                     call.function.name.push_str(name);
-                                                    tracing::debug!("🔧 Tool name fragment: '{}'", name);
+                                                    tracing::trace!("🔧 Tool name fragment: '{}'", name);
                                                 }
                                                 if let Some(ref args) = function.arguments {
                                                     call.function.arguments.push_str(args);
-                                                    tracing::debug!("📝 Tool args fragment: '{}'", args);
+                                                    tracing::trace!("📝 Tool args fragment: '{}'", redact::preview(args, 100));
                                                 }
                                             }
                                         }
@@ -519,12 +746,12 @@ impl OngoingPrompt {
 
                                 // Check for finish reason
                                 if let Some(finish_reason) = &choice.finish_reason {
-                                    tracing::info!("🏁 Turn finished with reason: {:?}", finish_reason);
+                                    tracing::debug!("🏁 Turn finished with reason: {:?}", finish_reason);
                                     
                                     // Execute any remaining tool call
                                     if let Some(completed_call) = current_tool_call.take() {
-                                        tracing::info!("📋 EXECUTING FINAL TOOL CALL: {} with args: {}", 
-                                            completed_call.function.name, completed_call.function.arguments);
+                                        tracing::debug!("📋 EXECUTING FINAL TOOL CALL: {} with args: {}", 
+                                            completed_call.function.name, redact::preview(&completed_call.function.arguments, 200));
                                         
                                         let tool_execution_result = Self::execute_tool_call(
                                             &completed_call,
@@ -555,7 +782,7 @@ impl OngoingPrompt {
                                 if let Some(completed_call) = current_tool_call.take() {
                                     if !completed_call.function.name.is_empty() {
                                         tracing::info!("🔄 RECOVERING TOOL CALL: {} with args: {}", 
-                                            completed_call.function.name, completed_call.function.arguments);
+                                            completed_call.function.name, redact::preview(&completed_call.function.arguments, 200));
                                         
                                         let tool_execution_result = Self::execute_tool_call(
                                             &completed_call,
@@ -597,27 +824,27 @@ impl OngoingPrompt {
                             audio: None,
                         }
                     ));
-                    tracing::info!("💾 Added assistant message with content to conversation");
+                    tracing::debug!("💾 Added assistant message with content to conversation");
                 }
 
                 // Check if conversation should continue based on whether we executed any tools
                 // during this specific turn
                 if tools_executed_this_turn {
-                    tracing::info!("🔄 Continuing conversation after tool execution...");
+                    tracing::debug!("🔄 Continuing conversation after tool execution...");
                     continue;
                 } else {
-                    tracing::info!("🔚 No tools executed this turn - conversation complete");
+                    tracing::debug!("🔚 No tools executed this turn - conversation complete");
                     conversation_complete = true;
                 }
             }
 
-            tracing::info!("🏁 Enhanced stream processing finished. Final content length: {}", accumulated_content.len());
+            tracing::debug!("🏁 Enhanced stream processing finished. Final content length: {}", accumulated_content.len());
 
             // Store final content
             {
                 let mut final_content_lock = final_content_clone.write().await;
                 *final_content_lock = Some(accumulated_content.clone());
-                tracing::info!("💾 Stored final content: {} characters", accumulated_content.len());
+                tracing::debug!("💾 Stored final content: {} characters", accumulated_content.len());
             }
 
             // Store any error that occurred
@@ -627,11 +854,32 @@ impl OngoingPrompt {
                 tracing::error!("💾 Stored error: {}", err);
             }
 
+            // Send the usage as the last entry, buffered for clients that reconnect later
+            {
+                let usage = usage_data_clone.read().await.clone();
+                let usage_entry = StreamingEntry {
+                    content: String::new(),
+                    entry_type: StreamingEntryType::Usage,
+                    tool_call: None,
+                    usage: Some(StreamingUsage {
+                        prompt_tokens: usage.as_ref().map(|usage| usage.prompt_tokens),
+                        completion_tokens: usage.as_ref().map(|usage| usage.completion_tokens),
+                        total_tokens: usage.as_ref().map(|usage| usage.total_tokens),
+                        model: model_used_clone.read().await.clone(),
+                        provider: provider_used_clone.read().await.clone(),
+                    }),
+                };
+
+                buffer_clone.write().await.push_back(usage_entry.clone());
+                let mut senders_lock = senders_clone.lock().await;
+                senders_lock.retain(|sender| sender.try_send(Ok(usage_entry.clone())).is_ok());
+            }
+
             // Mark as complete and close all senders
             {
                 let mut complete = is_complete_clone.write().await;
                 *complete = true;
-                tracing::info!("✅ Marked prompt as complete");
+                tracing::debug!("✅ Marked prompt as complete");
             }
             
             // Close all remaining senders
@@ -639,13 +887,14 @@ impl OngoingPrompt {
                 let mut senders_lock = senders_clone.lock().await;
                 let sender_count = senders_lock.len();
                 senders_lock.clear();
-                tracing::info!("📡 Closed {} remaining senders", sender_count);
+                tracing::debug!("📡 Closed {} remaining senders", sender_count);
             }
 
             if let Some(error) = completion_error {
                 tracing::error!("❌ Enhanced chat completion finished with error: \"{}\"", error);
             } else {
-                tracing::info!("✅ Enhanced chat completion finished successfully with data: \"{}\"", &accumulated_content[..accumulated_content.len().min(100)]);
+                tracing::info!("✅ Enhanced chat completion finished successfully with {} characters", accumulated_content.len());
+                tracing::debug!("  Content: \"{}\"", redact::preview(&accumulated_content, 100));
             }
         });
             
@@ -705,6 +954,23 @@ impl OngoingPrompt {
             )
     }
     
+    /// Stop generating, the prompt completes with a "Cancelled" error at the next chunk
+    pub async fn cancel(&self) {
+        let mut cancelled = self.state.cancelled.write().await;
+        *cancelled = true;
+    }
+
+    /// Drop senders whose receiving stream has gone away (e.g. a disconnected client)
+    pub async fn prune_closed_senders(&self) {
+        let mut senders_lock = self.state.senders.lock().await;
+        let before = senders_lock.len();
+        senders_lock.retain(|sender| !sender.is_closed());
+        let pruned = before - senders_lock.len();
+        if pruned > 0 {
+            tracing::debug!("📡 Pruned {} closed senders", pruned);
+        }
+    }
+
     /// Check if the prompt is complete
     pub async fn is_complete(&self) -> bool {
         *self.state.is_complete.read().await
@@ -748,10 +1014,16 @@ impl OngoingPrompt {
         buffer.iter().cloned().collect()
     }
 
-    /// Get the usage data captured from the API response
+    /// Get the usage data captured from the API response, plus the tokens spent preparing it
     pub async fn get_usage_data(&self) -> Option<async_openai::types::CompletionUsage> {
-        let usage_lock = self.state.usage_data.read().await;
-        usage_lock.clone()
+        let usage = self.state.usage_data.read().await.clone();
+        add_usage(usage, self.state.preparation_usage.read().await.clone())
+    }
+
+    /// Count tokens spent building the prompt in its usage
+    pub async fn add_preparation_usage(&self, usage: Option<async_openai::types::CompletionUsage>) {
+        let mut preparation_usage = self.state.preparation_usage.write().await;
+        *preparation_usage = add_usage(preparation_usage.take(), usage);
     }
 
     /// Get the model used for this request
@@ -760,6 +1032,11 @@ impl OngoingPrompt {
         model_lock.clone()
     }
 
+    /// Get the provider that served the latest turn of this request
+    pub async fn get_provider_used(&self) -> Option<String> {
+        self.state.provider_used.read().await.clone()
+    }
+
     /// Execute a single tool call and handle streaming of results
     async fn execute_tool_call(
         tool_call: &ChatCompletionMessageToolCall,
@@ -771,9 +1048,9 @@ impl OngoingPrompt {
         let tool_name = &tool_call.function.name;
         let tool_args = &tool_call.function.arguments;
         
-        tracing::info!("🟢🟢🟢 EXECUTING TOOL: {} 🟢🟢🟢", tool_name);
-        tracing::info!("🆔 Call ID: {}", tool_call.id);
-        tracing::info!("📋 Args: {}", tool_args);
+        tracing::info!("🔧 Executing tool: {}", tool_name);
+        tracing::debug!("🆔 Call ID: {}", tool_call.id);
+        tracing::debug!("📋 Args: {}", redact::preview(tool_args, 500));
         
         // Stream tool call start to user
         let tool_start_entry = StreamingEntry {
@@ -786,6 +1063,7 @@ impl OngoingPrompt {
                 result: None,
                 status: ToolCallStatus::Starting,
             }),
+            usage: None,
         };
         
         {
@@ -802,11 +1080,11 @@ impl OngoingPrompt {
         // Parse arguments and call the tool
         let tool_result = match serde_json::from_str(tool_args) {
             Ok(mut args_json) => {
-                tracing::info!("✅ Tool arguments parsed successfully");
+                tracing::debug!("✅ Tool arguments parsed successfully");
                 
                 // Normalize numeric arguments (convert string numbers to actual numbers)
                 args_json = normalize_tool_arguments(tool_name, args_json);
-                tracing::info!("🔢 Tool arguments after normalization: {}", args_json);
+                tracing::debug!("🔢 Tool arguments after normalization: {}", redact::preview(&args_json.to_string(), 500));
                 
                 // Send executing status
                 let executing_entry = StreamingEntry {
@@ -819,6 +1097,7 @@ impl OngoingPrompt {
                         result: None,
                         status: ToolCallStatus::Executing,
                     }),
+                    usage: None,
                 };
                 
                 {
@@ -840,10 +1119,8 @@ impl OngoingPrompt {
                             .collect::<Vec<_>>()
                             .join("\n");
                         
-                        tracing::info!("✅ TOOL EXECUTION SUCCESS: {}", tool_name);
-                        tracing::info!("📤 Tool result length: {} characters", content.len());
-                        tracing::info!("📄 Tool result preview: {}...", 
-                            content.chars().take(200).collect::<String>());
+                        tracing::info!("✅ Tool {} succeeded with {} characters", tool_name, content.len());
+                        tracing::debug!("📄 Tool result preview: {}...", redact::preview(&content, 200));
                         
                         // Send success result
                         let success_entry = StreamingEntry {
@@ -856,6 +1133,7 @@ impl OngoingPrompt {
                                 result: Some(content.clone()),
                                 status: ToolCallStatus::Success,
                             }),
+                            usage: None,
                         };
                         
                         {
@@ -886,6 +1164,7 @@ impl OngoingPrompt {
                                 result: Some(error_msg.clone()),
                                 status: ToolCallStatus::Error,
                             }),
+                            usage: None,
                         };
                         
                         {
@@ -918,6 +1197,7 @@ impl OngoingPrompt {
                         result: Some(error_msg.clone()),
                         status: ToolCallStatus::Error,
                     }),
+                    usage: None,
                 };
                 
                 {
@@ -948,7 +1228,7 @@ impl OngoingPrompt {
                     audio: None,
                 }
             ));
-            tracing::info!("💾 Added assistant message with tool call to conversation");
+            tracing::debug!("💾 Added assistant message with tool call to conversation");
         }
 
         // Add tool result to conversation history
@@ -960,10 +1240,10 @@ impl OngoingPrompt {
                     tool_call_id: tool_call.id.clone(),
                 }
             ));
-            tracing::info!("💾 Added tool result to conversation for call ID: {}", tool_call.id);
+            tracing::debug!("💾 Added tool result to conversation for call ID: {}", tool_call.id);
         }
         
-        tracing::info!("🟢🟢🟢 TOOL EXECUTION COMPLETED: {} 🟢🟢🟢", tool_name);
+        tracing::debug!("🔧 Tool execution completed: {}", tool_name);
         Ok(())
     }
 }
@@ -995,22 +1275,22 @@ impl OngoingPromptManager {
         state: &AppState,
         messages: Vec<ChatCompletionRequestMessage>,
         tools: Option<Vec<ChatCompletionTool>>,
-        model: Option<String>,
+        config: PromptConfig,
     ) -> Result<OngoingPrompt, Box<dyn std::error::Error + Send + Sync>> {
         // First check if we already have this prompt
         {
             let prompts = self.prompts.read().await;
             if let Some(existing) = prompts.get(&key) {
-                tracing::info!("🔄 Returning existing prompt for key: {} (tools provided: {})", 
+                tracing::debug!("🔄 Returning existing prompt for key: {} (tools provided: {})", 
                     key, tools.as_ref().map(|t| t.len()).unwrap_or(0));
                 return Ok(existing.clone());
             }
         }
 
         // Create new prompt
-        tracing::info!("🆕 Creating new prompt for key: {} (tools provided: {})", 
+        tracing::debug!("🆕 Creating new prompt for key: {} (tools provided: {})", 
             key, tools.as_ref().map(|t| t.len()).unwrap_or(0));
-        let prompt = OngoingPrompt::new(state, messages, tools, model).await?;
+        let prompt = OngoingPrompt::new(state, messages, tools, config).await?;
         
         // Store it
         {
@@ -1018,7 +1298,7 @@ impl OngoingPromptManager {
             prompts.insert(key.clone(), prompt.clone());
         }
         
-        tracing::info!("Stored ongoing prompt with key: {}", key);
+        tracing::debug!("Stored ongoing prompt with key: {}", key);
         Ok(prompt)
     }
 
@@ -1046,3 +1326,61 @@ impl OngoingPromptManager {
         prompts.insert(key, prompt);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_config_validation() {
+        assert!(PromptConfig::chat(None).validate().is_ok());
+        assert!(PromptConfig::summary().validate().is_ok());
+        assert!(PromptConfig::shortsum().validate().is_ok());
+        assert!(PromptConfig::chunk_summary().validate().is_ok());
+
+        let mut config = PromptConfig::summary();
+        config.temperature = Some(2.5);
+        assert!(config.validate().is_err());
+
+        let mut config = PromptConfig::summary();
+        config.top_p = Some(0.0);
+        assert!(config.validate().is_err());
+
+        let mut config = PromptConfig::summary();
+        config.stop = Some(vec!["a", "b", "c", "d", "e"].into_iter().map(String::from).collect());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_replayed_chunks_are_dropped() {
+        // like OpenRouter, every delta carries the role
+        let chunk = |id: &str, content: &str| -> CreateChatCompletionStreamResponse {
+            serde_json::from_value(serde_json::json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": 1750000000,
+                "model": "test",
+                "choices": [{ "index": 0, "delta": { "role": "assistant", "content": content }, "finish_reason": null }],
+            }))
+            .unwrap()
+        };
+        let mut deduper = ChunkDeduper::default();
+        let mut content = |mut chunk: CreateChatCompletionStreamResponse| {
+            deduper.dedupe(&mut chunk);
+            chunk.choices[0].delta.content.clone().unwrap_or_default()
+        };
+
+        // a reply repeating its own opening words loses nothing
+        assert_eq!(content(chunk("a", "")), "");
+        assert_eq!(content(chunk("a", "The")), "The");
+        assert_eq!(content(chunk("a", " very")), " very");
+        assert_eq!(content(chunk("a", " very")), " very");
+        assert_eq!(content(chunk("a", ". ")), ". ");
+        assert_eq!(content(chunk("a", "The")), "The");
+
+        // after a reconnect only what goes beyond the received content passes
+        assert_eq!(content(chunk("b", "The very very")), "");
+        assert_eq!(content(chunk("b", ". The good")), " good");
+        assert_eq!(content(chunk("b", " good")), " good");
+    }
+}
@@ -0,0 +1,161 @@
+use async_openai::{
+    Client,
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{ChatCompletionResponseStream, CreateChatCompletionRequest, CreateChatCompletionResponse},
+};
+use futures::{StreamExt, stream};
+
+use crate::modules::metrics::AiMetrics;
+
+const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// An OpenAI-compatible endpoint prompts can be sent to
+pub struct Provider {
+    pub name: String,
+    pub client: Client<OpenAIConfig>,
+}
+
+impl Provider {
+    fn new(name: &str, api_key: String, base_url: String) -> Self {
+        tracing::info!("  Provider {}: {} (key present: {})", name, base_url, !api_key.is_empty());
+
+        let config = OpenAIConfig::new().with_api_key(api_key).with_api_base(base_url);
+
+        Self {
+            name: name.to_string(),
+            client: Client::with_config(config),
+        }
+    }
+}
+
+/// Ordered list of providers, a request moves on to the next one when a provider fails
+pub struct Providers {
+    providers: Vec<Provider>,
+    metrics: AiMetrics,
+}
+
+impl Providers {
+    /// The primary provider is read from `WORKSHOP_INTELLIGENCE_KEY` and `WORKSHOP_INTELLIGENCE_BASE_URL`,
+    /// fallbacks from `WORKSHOP_FALLBACK_PROVIDERS` (comma separated names, in order)
+    /// with `WORKSHOP_PROVIDER_<NAME>_KEY` and `WORKSHOP_PROVIDER_<NAME>_BASE_URL`
+    ///
+    /// # Panics
+    /// Panics if `WORKSHOP_INTELLIGENCE_KEY` is not set.
+    pub fn load(metrics: AiMetrics) -> Self {
+        let api_key =
+            std::env::var("WORKSHOP_INTELLIGENCE_KEY").expect("WORKSHOP_INTELLIGENCE_KEY not set");
+        let base_url = std::env::var("WORKSHOP_INTELLIGENCE_BASE_URL")
+            .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+
+        let mut providers = vec![Provider::new("primary", api_key, base_url)];
+
+        let fallbacks = std::env::var("WORKSHOP_FALLBACK_PROVIDERS").unwrap_or_default();
+        for name in fallbacks.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let var = |suffix: &str| {
+                std::env::var(format!("WORKSHOP_PROVIDER_{}_{}", name.to_uppercase(), suffix)).ok()
+            };
+
+            match (var("KEY"), var("BASE_URL")) {
+                (Some(api_key), Some(base_url)) => {
+                    providers.push(Provider::new(name, api_key, base_url))
+                }
+                _ => tracing::warn!(
+                    "Fallback provider {} is missing its key or base URL, skipping",
+                    name
+                ),
+            }
+        }
+
+        Self { providers, metrics }
+    }
+
+    pub fn primary(&self) -> &Provider {
+        &self.providers[0]
+    }
+
+    /// Create a completion, returning the name of the provider that served it
+    pub async fn create(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<(CreateChatCompletionResponse, String), OpenAIError> {
+        let mut providers = self.providers.iter().peekable();
+
+        while let Some(provider) = providers.next() {
+            match provider.client.chat().create(request.clone()).await {
+                Ok(response) => {
+                    self.metrics.provider_served(&provider.name);
+                    return Ok((response, provider.name.clone()));
+                }
+                Err(e) if providers.peek().is_some() && is_provider_failure(&e) => {
+                    self.failed_over(provider, &e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("there is always a primary provider")
+    }
+
+    /// Create a completion stream, returning the name of the provider that serves it
+    ///
+    /// Most provider errors only surface as the first item of the stream,
+    /// so that is awaited before committing to a provider.
+    pub async fn create_stream(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<(ChatCompletionResponseStream, String), OpenAIError> {
+        let mut providers = self.providers.iter().peekable();
+
+        while let Some(provider) = providers.next() {
+            let has_next = providers.peek().is_some();
+
+            let mut stream = match provider.client.chat().create_stream(request.clone()).await {
+                Ok(stream) => stream,
+                Err(e) if has_next && is_provider_failure(&e) => {
+                    self.failed_over(provider, &e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            match stream.next().await {
+                Some(Err(e)) if has_next && is_provider_failure(&e) => {
+                    self.failed_over(provider, &e);
+                }
+                Some(first) => {
+                    self.metrics.provider_served(&provider.name);
+                    let stream: ChatCompletionResponseStream =
+                        Box::pin(stream::once(async move { first }).chain(stream));
+                    return Ok((stream, provider.name.clone()));
+                }
+                None => {
+                    self.metrics.provider_served(&provider.name);
+                    return Ok((stream, provider.name.clone()));
+                }
+            }
+        }
+
+        unreachable!("there is always a primary provider")
+    }
+
+    fn failed_over(&self, provider: &Provider, e: &OpenAIError) {
+        tracing::warn!("AI provider {} failed, trying the next one: {}", provider.name, e);
+        self.metrics.provider_failed_over(&provider.name);
+    }
+}
+
+/// Whether an error is the provider's fault (unreachable, auth, rate limits, server errors)
+/// rather than something that would fail on any provider
+fn is_provider_failure(e: &OpenAIError) -> bool {
+    match e {
+        OpenAIError::Reqwest(_) | OpenAIError::StreamError(_) | OpenAIError::JSONDeserialize(_) => {
+            true
+        }
+        OpenAIError::ApiError(api) => {
+            api.r#type.as_deref() != Some("invalid_request_error")
+                || api.code.as_deref() == Some("invalid_api_key")
+        }
+        _ => false,
+    }
+}
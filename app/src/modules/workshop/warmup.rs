@@ -0,0 +1,156 @@
+//! Background summaries for trending topics
+//!
+//! When `SUMMARY_WARMUP_ENABLED` is set, trending topics whose summary is missing or out of
+//! date are summarized ahead of time so readers don't wait on generation. Each run is capped
+//! at `max_per_run` summaries, generated `concurrency` at a time, to keep provider costs bounded.
+
+use chrono::{TimeDelta, Utc};
+use figment::{Figment, providers::Env};
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::breaker::BreakerState;
+use crate::{
+    models::topics::{Topic, TopicSummary, TrendingWindow, quarantine::QuarantinedTopic},
+    modules::schedule::Schedule,
+    state::AppState,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummaryWarmup {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// How many of the trending topics to consider
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+    /// Summaries generated at the same time
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Summaries generated per run at most
+    #[serde(default = "default_max_per_run")]
+    pub max_per_run: usize,
+}
+
+fn default_interval_secs() -> u64 {
+    1800
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+fn default_concurrency() -> usize {
+    2
+}
+
+fn default_max_per_run() -> usize {
+    5
+}
+
+impl Default for SummaryWarmup {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval_secs(),
+            top_n: default_top_n(),
+            concurrency: default_concurrency(),
+            max_per_run: default_max_per_run(),
+        }
+    }
+}
+
+impl SummaryWarmup {
+    pub fn load() -> Self {
+        Figment::new()
+            .merge(Env::prefixed("SUMMARY_WARMUP_"))
+            .extract::<SummaryWarmup>()
+            .unwrap_or_else(|e| {
+                warn!("Invalid summary warmup config, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    /// Summarize trending topics every `interval_secs`, forever
+    pub async fn run(self, state: AppState) {
+        info!(
+            "Summary warmup enabled, every {}s for the top {} trending topics",
+            self.interval_secs, self.top_n
+        );
+
+        let schedule = Schedule::every(TimeDelta::seconds(self.interval_secs.max(60) as i64));
+        loop {
+            self.run_once(&state).await;
+            info!("Next summary warmup at: {:?}", schedule.next_after(Utc::now()));
+            schedule.sleep().await;
+        }
+    }
+
+    async fn run_once(&self, state: &AppState) {
+        if state.workshop.breaker.state() == BreakerState::Open {
+            info!("Skipping summary warmup while the AI provider is unavailable");
+            return;
+        }
+
+        let trending = match Topic::get_by_trending(TrendingWindow::default(), state).await {
+            Ok(trending) => trending,
+            Err(e) => {
+                warn!("Error loading trending topics for summary warmup: {:?}", e);
+                return;
+            }
+        };
+        let quarantined = match QuarantinedTopic::keys(state).await {
+            Ok(quarantined) => quarantined,
+            Err(e) => {
+                warn!("Error loading quarantined topics for summary warmup: {:?}", e);
+                return;
+            }
+        };
+        let trending = trending
+            .into_iter()
+            .filter(|topic| !quarantined.contains(&(topic.discourse_id.clone(), topic.topic_id)));
+
+        let mut outdated = Vec::new();
+        for topic in trending.take(self.top_n) {
+            if outdated.len() >= self.max_per_run {
+                break;
+            }
+
+            match TopicSummary::latest(&topic.discourse_id, topic.topic_id, state).await {
+                Ok(Some(summary)) if is_current(&summary, &topic) => {}
+                Ok(_) => outdated.push(topic),
+                Err(e) => warn!(
+                    "Error loading summary of topic {} for warmup: {:?}",
+                    topic.topic_id, e
+                ),
+            }
+        }
+
+        if outdated.is_empty() {
+            return;
+        }
+
+        info!("Warming up summaries for {} trending topics", outdated.len());
+
+        stream::iter(outdated)
+            .for_each_concurrent(self.concurrency.max(1), |topic| async move {
+                // coalesces with a generation already started by a reader
+                if let Err(e) =
+                    TopicSummary::get_summary_by_topic_id(&topic.discourse_id, topic.topic_id, state)
+                        .await
+                {
+                    warn!("Error warming up summary of topic {}: {:?}", topic.topic_id, e);
+                }
+            })
+            .await;
+    }
+}
+
+/// Same check a read does, the summary covers the topic's latest post
+fn is_current(summary: &TopicSummary, topic: &Topic) -> bool {
+    topic
+        .last_post_at
+        .is_some_and(|last_post_at| summary.based_on.timestamp() == last_post_at.timestamp())
+}
@@ -0,0 +1,210 @@
+//! Network level access control for operational endpoints
+//!
+//! `/metrics` exposes token counts, usage and queue sizes, admin endpoints can read and change
+//! anything, so neither should be reachable by the public. Both are meant to sit behind
+//! the reverse proxy with a restricted route, this module adds defense in depth:
+//!
+//! - `/metrics` can be moved to an internal listener (`METRICS_BIND_ADDR`), limited to source
+//!   networks (`METRICS_ALLOWED_IPS`) and/or protected by basic auth (`METRICS_BASIC_AUTH`).
+//!   With none of these set it is served publicly like before.
+//! - Admin endpoints can be limited to source networks (`ADMIN_ALLOWED_IPS`), checked before
+//!   and in addition to the admin key or SSO token.
+//!
+//! Source addresses are taken from the connection. `X-Forwarded-For`/`X-Real-IP` are only
+//! trusted when `TRUST_PROXY_HEADERS=true`, as anyone can send them otherwise. Even then only
+//! the `X-Forwarded-For` entries appended by our own proxies are used, `TRUSTED_PROXY_HOPS`
+//! (default 1) counted from the right, whatever the client put in front is ignored.
+
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+use poem::{
+    Endpoint, IntoResponse, Middleware, Request, Response,
+    http::StatusCode,
+    web::headers::{Authorization, HeaderMapExt, authorization::Basic},
+};
+
+/// Networks allowed to reach an endpoint, from a comma separated list of IPs and CIDRs
+#[derive(Debug, Clone)]
+pub struct IpAllowList(Vec<IpNet>);
+
+impl IpAllowList {
+    pub fn parse(value: &str) -> Self {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let network = entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from));
+                if network.is_err() {
+                    tracing::warn!("Ignoring invalid allow-list entry: {}", entry);
+                }
+                network.ok()
+            })
+            .collect();
+
+        Self(networks)
+    }
+
+    /// `None` when the variable is unset, meaning every address is allowed
+    pub fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var).ok().map(|value| Self::parse(&value))
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+}
+
+/// Address of the client, honouring proxy headers only when `TRUST_PROXY_HEADERS=true`
+pub fn client_ip(req: &Request) -> Option<IpAddr> {
+    let trust_proxy = std::env::var("TRUST_PROXY_HEADERS").is_ok_and(|value| value == "true");
+
+    if trust_proxy {
+        let hops = std::env::var("TRUSTED_PROXY_HOPS")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let forwarded = match req.headers().get("X-Forwarded-For") {
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(|value| forwarded_client(value, hops)),
+            None => req
+                .headers()
+                .get("X-Real-IP")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok()),
+        };
+
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+
+    req.remote_addr().as_socket_addr().map(|addr| addr.ip())
+}
+
+/// The entry `hops` from the right of an `X-Forwarded-For` list, the address our outermost
+/// proxy saw. Entries further left were sent by the client and can be anything.
+fn forwarded_client(value: &str, hops: usize) -> Option<IpAddr> {
+    let entries: Vec<&str> = value.split(',').map(str::trim).collect();
+    let index = entries.len().checked_sub(hops.max(1))?;
+
+    entries[index].parse::<IpAddr>().ok()
+}
+
+/// Whether the request comes from an allowed address, always true without an allow-list
+pub fn is_allowed(allow_list: Option<&IpAllowList>, req: &Request) -> bool {
+    match allow_list {
+        None => true,
+        Some(allow_list) => client_ip(req).is_some_and(|ip| allow_list.allows(ip)),
+    }
+}
+
+/// Compare two byte strings in time independent of where they differ
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Guards `/metrics` with `METRICS_ALLOWED_IPS` and `METRICS_BASIC_AUTH` (`user:password`)
+#[derive(Clone)]
+pub struct MetricsAccess {
+    allow_list: Option<IpAllowList>,
+    basic_auth: Option<(String, String)>,
+}
+
+impl MetricsAccess {
+    pub fn from_env() -> Self {
+        let basic_auth = std::env::var("METRICS_BASIC_AUTH").ok().and_then(|value| {
+            value
+                .split_once(':')
+                .map(|(user, password)| (user.to_string(), password.to_string()))
+        });
+
+        Self {
+            allow_list: IpAllowList::from_env("METRICS_ALLOWED_IPS"),
+            basic_auth,
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for MetricsAccess {
+    type Output = MetricsAccessImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MetricsAccessImpl {
+            ep,
+            access: self.clone(),
+        }
+    }
+}
+
+pub struct MetricsAccessImpl<E> {
+    ep: E,
+    access: MetricsAccess,
+}
+
+impl<E: Endpoint> Endpoint for MetricsAccessImpl<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        if !is_allowed(self.access.allow_list.as_ref(), &req) {
+            tracing::warn!("Rejected metrics scrape from {:?}", client_ip(&req));
+            return Ok(StatusCode::FORBIDDEN.into_response());
+        }
+
+        if let Some((user, password)) = &self.access.basic_auth {
+            let authorized = req
+                .headers()
+                .typed_get::<Authorization<Basic>>()
+                .is_some_and(|auth| {
+                    constant_time_eq(auth.username().as_bytes(), user.as_bytes())
+                        & constant_time_eq(auth.password().as_bytes(), password.as_bytes())
+                });
+
+            if !authorized {
+                return Ok(Response::builder()
+                    .status(StatusCode::UNAUTHORIZED)
+                    .header("WWW-Authenticate", "Basic realm=\"metrics\"")
+                    .finish());
+            }
+        }
+
+        Ok(self.ep.call(req).await?.into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_list() {
+        let allow_list = IpAllowList::parse("10.0.0.0/8, 127.0.0.1, ::1, nonsense");
+
+        assert!(allow_list.allows("10.1.2.3".parse().unwrap()));
+        assert!(allow_list.allows("127.0.0.1".parse().unwrap()));
+        assert!(allow_list.allows("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(allow_list.allows("::1".parse().unwrap()));
+        assert!(!allow_list.allows("192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_forwarded_client_ignores_spoofed_entries() {
+        // the client sent `X-Forwarded-For: 127.0.0.1`, the proxy appended the real address
+        let spoofed = "127.0.0.1, 203.0.113.7";
+
+        assert_eq!(forwarded_client(spoofed, 1), "203.0.113.7".parse().ok());
+        assert_eq!(forwarded_client("127.0.0.1, 203.0.113.7, 10.0.0.2", 2), "203.0.113.7".parse().ok());
+        assert_eq!(forwarded_client("203.0.113.7", 2), None);
+    }
+}
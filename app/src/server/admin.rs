@@ -1,37 +1,60 @@
-use crate::models::topics::{Topic, post::Post};
-use crate::models::workshop::usage::UserUsageOverview;
-use crate::models::workshop::usage::get_all_users_usage_overview;
-use crate::modules::discourse::{DiscourseService, ForumSearchDocument};
+use crate::models::admin::AdminAuditLog;
+use crate::models::topics::dead_letter::IndexerDeadLetter;
+use crate::models::topics::parse_failure::ResponseParseFailure;
+use crate::models::topics::quarantine::QuarantinedTopic;
+use crate::models::webhooks::{WebhookDelivery, WebhookEndpoint};
+use crate::models::workshop::usage::{
+    DailyUsage, ModelUsage, UserUsageOverview, get_all_users_usage_overview,
+    get_user_daily_usage_in_range, get_user_usage_by_model_in_range,
+};
+use crate::modules::discourse::IndexerStatus;
+use crate::modules::dump::{self, ExportFilter, ImportReport};
+use crate::modules::ical::ICalSyncStats;
+use crate::modules::meili::{self, configure_forum_index};
+use crate::modules::notifications::{self, events};
+use crate::modules::reindex::{self, ReindexError, ReindexJobStatus};
 use crate::server::ApiTags;
+use crate::server::access::{self, IpAllowList, constant_time_eq};
+use crate::server::discourse_id::DiscourseId;
 use crate::state::AppState;
-use poem::Result;
 use poem::web::Data;
-use poem_openapi::param::Header;
-use poem_openapi::payload::Json;
-use poem_openapi::{Object, OpenApi};
+use poem::{Request, Result, http::header};
+use poem_openapi::param::{Header, Path, Query};
+use futures::{StreamExt, stream::BoxStream};
+use poem_openapi::payload::{Binary, EventStream, Json};
+use poem_openapi::{ApiResponse, Object, OpenApi};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use sqlx::query_as;
-use strip_tags::strip_tags;
 use tracing::{error, info, warn};
 
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct AdminApi;
 
-#[derive(Debug, Serialize, Deserialize, Object)]
-pub struct ReindexResponse {
-    pub success: bool,
-    pub message: String,
-    pub topics_processed: i32,
-    pub posts_processed: i32,
-    pub errors: i32,
-}
-
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct AdminStatsResponse {
     pub database_topics: i64,
     pub database_posts: i64,
     pub meilisearch_documents: Option<i64>,
+    pub ical_last_sync: Option<ICalSyncStats>,
+    /// Topics whose stored posts don't add up to the post count seen at the last fetch
+    pub topics_behind_on_posts: i64,
+    /// Topics in the database without a topic document in Meilisearch
+    pub topics_missing_from_search: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct MeiliRecreateResponse {
+    pub documents_before: Option<i64>,
+    pub documents_after: Option<i64>,
+    /// The reindex job rebuilding the index, its result reports the final document count
+    pub reindex_job: ReindexJobStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminAuditResponse {
+    pub entries: Vec<AdminAuditLog>,
+    pub total: i64,
+    pub has_more: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Object)]
@@ -44,201 +67,1075 @@ pub struct AdminUsageResponse {
     pub users: Vec<UserUsageOverview>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminUserUsageResponse {
+    pub user_id: uuid::Uuid,
+    /// First day of the range, inclusive
+    pub from: chrono::NaiveDate,
+    /// Last day of the range, inclusive
+    pub to: chrono::NaiveDate,
+    pub total_tokens: i64,
+    pub total_prompt_tokens: i64,
+    pub total_completion_tokens: i64,
+    pub total_reasoning_tokens: i64,
+    pub message_count: i64,
+    pub daily: Vec<DailyUsage>,
+    pub models: Vec<ModelUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminDeadLettersResponse {
+    pub dead_letters: Vec<IndexerDeadLetter>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminParseFailuresResponse {
+    pub parse_failures: Vec<ResponseParseFailure>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminWebhookEndpointRequest {
+    /// https endpoint receiving the events
+    pub url: String,
+    /// Events to deliver, `*` for all of them
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    /// Signing secret, generated when left out
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminQuarantineRequest {
+    /// Why the topic is hidden, for other admins
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminWebhookEndpointCreated {
+    pub endpoint: WebhookEndpoint,
+    /// Signing secret for `X-Forum-Signature`, not shown again
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AdminWebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+#[derive(ApiResponse)]
+pub enum ExportResponse {
+    /// Newline delimited JSON, one row per line
+    #[oai(status = 200)]
+    Ok(
+        Binary<poem::Body>,
+        #[oai(header = "Content-Disposition")] String,
+    ),
+}
+
+/// Who performed an admin request, used for logging without exposing the key
+#[derive(Debug, Clone)]
+pub struct AdminIdentity(pub String);
+
+impl std::fmt::Display for AdminIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AdminApi {
+    /// Verify the request carries a valid admin key or an SSO token of an admin
+    ///
+    /// Keys are read from `ADMIN_API_KEYS` as comma separated `name:key` or `key` entries, so
+    /// keys can be rotated, with `ADMIN_API_KEY` as a single unnamed fallback.
+    /// SSO users whose email is listed in `ADMIN_EMAILS` may use their bearer token instead.
+    ///
+    /// When `ADMIN_ALLOWED_IPS` is set, requests from other addresses are refused before
+    /// any credentials are looked at (see `server::access`).
+    fn verify_admin(
+        state: &AppState,
+        api_key: Option<String>,
+        req: &Request,
+    ) -> Result<AdminIdentity> {
+        if !access::is_allowed(IpAllowList::from_env("ADMIN_ALLOWED_IPS").as_ref(), req) {
+            warn!("Rejected admin request from {:?}", access::client_ip(req));
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let admin_keys = admin_keys();
+
+        if let Some(api_key) = api_key {
+            if admin_keys.is_empty() {
+                return Err(poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+
+            // compare against every key so the match position isn't leaked through timing
+            let mut identity = None;
+            for (name, key) in &admin_keys {
+                if constant_time_eq(api_key.as_bytes(), key.as_bytes()) && identity.is_none() {
+                    identity = Some(AdminIdentity(format!("key:{}", name)));
+                }
+            }
+
+            return identity.ok_or_else(|| poem::Error::from_status(StatusCode::UNAUTHORIZED));
+        }
+
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        if let (Some(token), Some(sso)) = (token, &state.sso) {
+            let claims = sso.validate_jwt_token(token).map_err(|e| {
+                warn!("Invalid admin bearer token: {}", e);
+                poem::Error::from_status(StatusCode::UNAUTHORIZED)
+            })?;
+
+            if claims.exp <= chrono::Utc::now().timestamp() {
+                return Err(poem::Error::from_status(StatusCode::UNAUTHORIZED));
+            }
+
+            let is_admin = admin_emails()
+                .iter()
+                .any(|email| email.eq_ignore_ascii_case(&claims.email));
+            if is_admin {
+                return Ok(AdminIdentity(format!("sso:{}", claims.email)));
+            }
+
+            warn!("Non-admin user {} attempted an admin request", claims.email);
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        Err(poem::Error::from_status(StatusCode::UNAUTHORIZED))
+    }
+}
+
 impl AdminApi {
-    fn verify_admin_key(api_key: Option<String>) -> Result<()> {
-        let expected_key = std::env::var("ADMIN_API_KEY")
-            .map_err(|_| poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+    /// Record an admin endpoint invocation in the audit log, failures are logged but not fatal
+    async fn audit(
+        state: &AppState,
+        admin: &AdminIdentity,
+        action: &str,
+        parameters: serde_json::Value,
+    ) {
+        if let Err(e) = AdminAuditLog::record(&admin.0, action, parameters, state).await {
+            error!("Failed to record admin action {} by {}: {}", action, admin, e);
+        }
+    }
+}
+
+/// Configured admin keys as (name, key), unnamed keys are named by position
+fn admin_keys() -> Vec<(String, String)> {
+    let mut keys: Vec<(String, String)> = std::env::var("ADMIN_API_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(index, entry)| match entry.split_once(':') {
+            Some((name, key)) => (name.to_string(), key.to_string()),
+            None => (index.to_string(), entry.to_string()),
+        })
+        .collect();
 
-        match api_key {
-            Some(key) if key == expected_key => Ok(()),
-            _ => Err(poem::Error::from_status(StatusCode::UNAUTHORIZED)),
+    if let Ok(key) = std::env::var("ADMIN_API_KEY") {
+        if !key.is_empty() {
+            keys.push(("default".to_string(), key));
         }
     }
+
+    keys
+}
+
+fn admin_emails() -> Vec<String> {
+    std::env::var("ADMIN_EMAILS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|email| !email.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Turn inclusive dates into the half-open range used by the exports
+fn export_filter(
+    discourse_id: Option<String>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+) -> ExportFilter {
+    ExportFilter {
+        discourse_id,
+        from: from.map(|from| from.and_time(chrono::NaiveTime::MIN).and_utc()),
+        to: to.map(|to| (to + chrono::Days::new(1)).and_time(chrono::NaiveTime::MIN).and_utc()),
+    }
 }
 
 #[OpenApi]
 impl AdminApi {
     /// /admin/reindex
     ///
-    /// Trigger a full reindex of all topics and posts from database to Meilisearch
+    /// Start a full reindex of all topics and posts from database to Meilisearch
+    /// The reindex runs in the background, poll /admin/reindex/:job_id for progress
     #[oai(path = "/admin/reindex", method = "post", tag = "ApiTags::Admin")]
     async fn reindex_all(
         &self,
         state: Data<&AppState>,
+        req: &Request,
         #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
-    ) -> Result<Json<ReindexResponse>> {
-        Self::verify_admin_key(admin_key.0)?;
+    ) -> Result<Json<ReindexJobStatus>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "reindex", serde_json::json!({})).await;
+        info!("Admin {} requested a full reindex", admin);
 
-        let Some(meili) = &state.meili else {
-            return Ok(Json(ReindexResponse {
-                success: false,
-                message: "Meilisearch is not configured".to_string(),
-                topics_processed: 0,
-                posts_processed: 0,
-                errors: 0,
-            }));
-        };
+        let job = state.reindex.start(&state).await.map_err(|e| match e {
+            ReindexError::MeiliNotConfigured => poem::Error::from_string(
+                "Meilisearch is not configured",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            ReindexError::AlreadyRunning(job_id) => poem::Error::from_string(
+                format!("A reindex is already running: {}", job_id),
+                StatusCode::CONFLICT,
+            ),
+        })?;
 
-        info!("Starting full reindex of all topics and posts");
+        Ok(Json(job.status().await))
+    }
 
-        let mut topics_processed = 0i32;
-        let mut posts_processed = 0i32;
-        let mut errors = 0i32;
+    /// /admin/reindex/:job_id
+    ///
+    /// Get the progress of a reindex job, including its result once done
+    #[oai(path = "/admin/reindex/:job_id", method = "get", tag = "ApiTags::Admin")]
+    async fn get_reindex_job(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] job_id: Path<String>,
+    ) -> Result<Json<ReindexJobStatus>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "reindex_status",
+            serde_json::json!({ "job_id": job_id.0 }),
+        )
+        .await;
 
-        // Get all topics from database
-        let topics = match query_as!(Topic, "SELECT * FROM topics ORDER BY topic_id ASC")
-            .fetch_all(&state.database.pool)
+        let job = state
+            .reindex
+            .get(&job_id.0)
             .await
-        {
-            Ok(topics) => topics,
-            Err(e) => {
-                error!("Failed to fetch topics from database: {}", e);
-                return Ok(Json(ReindexResponse {
-                    success: false,
-                    message: format!("Database error: {}", e),
-                    topics_processed: 0,
-                    posts_processed: 0,
-                    errors: 1,
-                }));
-            }
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(job.status().await))
+    }
+
+    /// /admin/reindex/:job_id/stream
+    ///
+    /// Stream the progress of a reindex job as server-sent events
+    /// The stream ends with a final event containing the result of the job
+    #[oai(
+        path = "/admin/reindex/:job_id/stream",
+        method = "get",
+        tag = "ApiTags::Admin"
+    )]
+    async fn stream_reindex_job(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] job_id: Path<String>,
+    ) -> Result<EventStream<BoxStream<'static, ReindexJobStatus>>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "reindex_stream",
+            serde_json::json!({ "job_id": job_id.0 }),
+        )
+        .await;
+
+        let job = state
+            .reindex
+            .get(&job_id.0)
+            .await
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(EventStream::new(job.subscribe().await.boxed()))
+    }
+
+    /// /admin/meili/recreate
+    ///
+    /// Delete the forum index, recreate it with the configured settings and start a full reindex
+    /// This is destructive, pass `?confirm=forum` to proceed
+    #[oai(path = "/admin/meili/recreate", method = "post", tag = "ApiTags::Admin")]
+    async fn recreate_meili_index(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        confirm: Query<Option<String>>,
+    ) -> Result<Json<MeiliRecreateResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "meili_recreate",
+            serde_json::json!({ "confirm": confirm.0 }),
+        )
+        .await;
+
+        if confirm.0.as_deref() != Some("forum") {
+            return Err(poem::Error::from_string(
+                "Recreating the index deletes all documents, pass ?confirm=forum to proceed",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let Some(meili) = &state.meili else {
+            return Err(poem::Error::from_string(
+                "Meilisearch is not configured",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ));
         };
 
-        info!("Found {} topics to reindex", topics.len());
-
-        // Index all topics
-        let forum_index = meili.index("forum");
-        let mut topic_docs = Vec::new();
-
-        for topic in &topics {
-            topic_docs.push(ForumSearchDocument {
-                entity_type: "topic".to_string(),
-                discourse_id: Some(topic.discourse_id.clone()),
-                topic_id: Some(topic.topic_id),
-                post_id: None,
-                post_number: None,
-                user_id: None,
-                username: None,
-                title: Some(topic.title.clone()),
-                slug: Some(topic.slug.clone()),
-                pm_issue: topic.pm_issue,
-                cooked: None,
-                entity_id: format!("topic_{}", topic.topic_id),
-            });
-            topics_processed += 1;
+        if state.reindex.is_running().await {
+            return Err(poem::Error::from_string(
+                "A reindex is already running",
+                StatusCode::CONFLICT,
+            ));
         }
 
-        // Batch insert topics
-        if !topic_docs.is_empty() {
-            match forum_index
-                .add_documents(&topic_docs, Some("entity_id"))
-                .await
-            {
-                Ok(_) => info!("Successfully indexed {} topics", topic_docs.len()),
+        let count_documents = || async {
+            match meili.index("forum").get_stats().await {
+                Ok(stats) => Some(stats.number_of_documents as i64),
                 Err(e) => {
-                    error!("Failed to index topics: {}", e);
-                    errors += 1;
+                    warn!("Failed to get Meilisearch stats: {}", e);
+                    None
                 }
             }
-        }
+        };
+
+        let documents_before = count_documents().await;
+        warn!(
+            "Admin {} is recreating the forum index, {:?} documents will be deleted",
+            admin, documents_before
+        );
 
-        // Get all posts from database
-        let posts = match query_as!(Post, "SELECT * FROM posts ORDER BY post_id ASC")
-            .fetch_all(&state.database.pool)
+        meili
+            .index("forum")
+            .delete()
             .await
-        {
-            Ok(posts) => posts,
-            Err(e) => {
-                error!("Failed to fetch posts from database: {}", e);
-                errors += 1;
-                return Ok(Json(ReindexResponse {
-                    success: false,
-                    message: format!("Database error fetching posts: {}", e),
-                    topics_processed,
-                    posts_processed: 0,
-                    errors,
-                }));
-            }
-        };
+            .map_err(|e| {
+                error!("Failed to delete forum index: {}", e);
+                poem::Error::from_status(StatusCode::BAD_GATEWAY)
+            })?
+            .wait_for_completion(meili, None, None)
+            .await
+            .map_err(|e| {
+                error!("Failed to wait for forum index deletion: {}", e);
+                poem::Error::from_status(StatusCode::BAD_GATEWAY)
+            })?;
 
-        info!("Found {} posts to reindex", posts.len());
-
-        // Build user mapping from post extras for more efficient username lookup
-        let user_mapping = build_user_mapping_from_posts(&posts);
-        info!("Built user mapping for {} users", user_mapping.len());
-
-        // Index all posts in batches to avoid memory issues
-        const BATCH_SIZE: usize = 100;
-        let post_batches = posts.chunks(BATCH_SIZE);
-
-        for batch in post_batches {
-            let mut post_docs = Vec::new();
-
-            for post in batch {
-                // Try to get username from our mapping first, then fallback to API
-                let username = user_mapping
-                    .get(&post.user_id)
-                    .map(|u| u.clone())
-                    .or_else(|| {
-                        // Fallback to API lookup (currently returns None for efficiency)
-                        None
-                    });
-
-                post_docs.push(ForumSearchDocument {
-                    entity_type: "post".to_string(),
-                    discourse_id: Some(post.discourse_id.clone()),
-                    topic_id: Some(post.topic_id),
-                    post_id: Some(post.post_id),
-                    post_number: Some(post.post_number),
-                    user_id: Some(post.user_id),
-                    username,
-                    title: None,
-                    slug: None,
-                    pm_issue: None,
-                    cooked: post.cooked.as_deref().map(strip_tags),
-                    entity_id: format!("post_{}", post.post_id),
-                });
-                posts_processed += 1;
-            }
+        configure_forum_index(meili)
+            .await
+            .map_err(|e| {
+                error!("Failed to configure recreated forum index: {}", e);
+                poem::Error::from_status(StatusCode::BAD_GATEWAY)
+            })?
+            .wait_for_completion(meili, None, None)
+            .await
+            .map_err(|e| {
+                error!("Failed to wait for forum index settings: {}", e);
+                poem::Error::from_status(StatusCode::BAD_GATEWAY)
+            })?;
 
-            // Batch insert posts
-            if !post_docs.is_empty() {
-                match forum_index
-                    .add_documents(&post_docs, Some("entity_id"))
-                    .await
-                {
-                    Ok(_) => info!("Successfully indexed batch of {} posts", post_docs.len()),
-                    Err(e) => {
-                        error!("Failed to index post batch: {}", e);
-                        errors += 1;
-                    }
-                }
-            }
-        }
+        let documents_after = count_documents().await;
 
-        let success = errors == 0;
-        let message = if success {
-            format!(
-                "Successfully reindexed {} topics and {} posts",
-                topics_processed, posts_processed
+        let job = state.reindex.start(&state).await.map_err(|e| {
+            error!("Failed to start reindex after recreating index: {:?}", e);
+            poem::Error::from_status(StatusCode::CONFLICT)
+        })?;
+
+        info!("Recreated forum index, reindexing as job {}", job.status().await.job_id);
+
+        Ok(Json(MeiliRecreateResponse {
+            documents_before,
+            documents_after,
+            reindex_job: job.status().await,
+        }))
+    }
+
+    /// /admin/audit
+    ///
+    /// List admin actions, newest first
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(path = "/admin/audit", method = "get", tag = "ApiTags::Admin")]
+    async fn get_audit_log(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<AdminAuditResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(50).clamp(1, 200);
+        Self::audit(
+            &state,
+            &admin,
+            "audit_list",
+            serde_json::json!({ "page": page, "size": size }),
+        )
+        .await;
+
+        let entries = AdminAuditLog::list(page, size, &state).await.map_err(|e| {
+            error!("Failed to list admin audit log: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        let total = AdminAuditLog::count(&state).await.map_err(|e| {
+            error!("Failed to count admin audit log: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(AdminAuditResponse {
+            has_more: page * size < total,
+            entries,
+            total,
+        }))
+    }
+
+    /// /admin/indexers
+    ///
+    /// Get the queue depth and freshness of every Discourse indexer
+    #[oai(path = "/admin/indexers", method = "get", tag = "ApiTags::Admin")]
+    async fn get_indexers(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+    ) -> Result<Json<Vec<IndexerStatus>>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "indexers", serde_json::json!({})).await;
+
+        Ok(Json(state.discourse.indexer_statuses().await))
+    }
+
+    /// /admin/indexer/dead_letters
+    ///
+    /// List topic pages the indexer gave up on, newest first
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(path = "/admin/indexer/dead_letters", method = "get", tag = "ApiTags::Admin")]
+    async fn get_dead_letters(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<AdminDeadLettersResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(50).clamp(1, 200);
+        Self::audit(
+            &state,
+            &admin,
+            "dead_letters_list",
+            serde_json::json!({ "page": page, "size": size }),
+        )
+        .await;
+
+        let dead_letters = IndexerDeadLetter::list(page, size, &state).await.map_err(|e| {
+            error!("Failed to list dead letters: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        let total = IndexerDeadLetter::count(&state).await.map_err(|e| {
+            error!("Failed to count dead letters: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(AdminDeadLettersResponse {
+            has_more: page * size < total,
+            dead_letters,
+            total,
+        }))
+    }
+
+    /// /admin/indexer/dead_letters/:dead_letter_id/requeue
+    ///
+    /// Put a dead topic page back on its indexer queue
+    #[oai(
+        path = "/admin/indexer/dead_letters/:dead_letter_id/requeue",
+        method = "post",
+        tag = "ApiTags::Admin"
+    )]
+    async fn requeue_dead_letter(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] dead_letter_id: Path<i64>,
+    ) -> Result<Json<IndexerDeadLetter>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "dead_letter_requeue",
+            serde_json::json!({ "dead_letter_id": dead_letter_id.0 }),
+        )
+        .await;
+
+        let dead_letter = IndexerDeadLetter::find(dead_letter_id.0, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to find dead letter: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        state
+            .discourse
+            .enqueue(
+                &dead_letter.discourse_id,
+                dead_letter.topic_id,
+                dead_letter.page as u32,
             )
-        } else {
+            .await
+            .map_err(|e| {
+                warn!("Failed to requeue dead letter {}: {}", dead_letter.dead_letter_id, e);
+                poem::Error::from_string(e.to_string(), StatusCode::SERVICE_UNAVAILABLE)
+            })?;
+
+        IndexerDeadLetter::delete(dead_letter.dead_letter_id, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to delete dead letter: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        info!(
+            "Admin {} requeued topic {} page {} for {}",
+            admin, dead_letter.topic_id, dead_letter.page, dead_letter.discourse_id
+        );
+
+        Ok(Json(dead_letter))
+    }
+
+    /// /admin/indexer/parse_failures
+    ///
+    /// List Discourse responses that failed to parse, newest first, with a preview of each
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(path = "/admin/indexer/parse_failures", method = "get", tag = "ApiTags::Admin")]
+    async fn get_parse_failures(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<AdminParseFailuresResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(50).clamp(1, 200);
+        Self::audit(
+            &state,
+            &admin,
+            "parse_failures_list",
+            serde_json::json!({ "page": page, "size": size }),
+        )
+        .await;
+
+        let parse_failures = ResponseParseFailure::list(page, size, &state).await.map_err(|e| {
+            error!("Failed to list parse failures: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        let total = ResponseParseFailure::count(&state).await.map_err(|e| {
+            error!("Failed to count parse failures: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(AdminParseFailuresResponse {
+            has_more: page * size < total,
+            parse_failures,
+            total,
+        }))
+    }
+
+    /// /admin/indexer/parse_failures/:failure_id
+    ///
+    /// Get a response that failed to parse, with its whole payload
+    #[oai(
+        path = "/admin/indexer/parse_failures/:failure_id",
+        method = "get",
+        tag = "ApiTags::Admin"
+    )]
+    async fn get_parse_failure(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] failure_id: Path<i64>,
+    ) -> Result<Json<ResponseParseFailure>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "parse_failure_get",
+            serde_json::json!({ "failure_id": failure_id.0 }),
+        )
+        .await;
+
+        let failure = ResponseParseFailure::find(failure_id.0, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to find parse failure: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(failure))
+    }
+
+    /// /admin/webhooks
+    ///
+    /// List the endpoints receiving platform events
+    #[oai(path = "/admin/webhooks", method = "get", tag = "ApiTags::Admin")]
+    async fn list_webhook_endpoints(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+    ) -> Result<Json<Vec<WebhookEndpoint>>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "webhooks_list", serde_json::json!({})).await;
+
+        let endpoints = WebhookEndpoint::list(&state).await.map_err(|e| {
+            error!("Failed to list webhook endpoints: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(endpoints))
+    }
+
+    /// /admin/webhooks
+    ///
+    /// Register an endpoint for platform events (topic.created, summary.ready)
+    /// Deliveries are signed with the returned secret as `X-Forum-Signature: sha256=<hex>`
+    #[oai(path = "/admin/webhooks", method = "post", tag = "ApiTags::Admin")]
+    async fn create_webhook_endpoint(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        request: Json<AdminWebhookEndpointRequest>,
+    ) -> Result<Json<AdminWebhookEndpointCreated>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        let request = request.0;
+
+        let url = notifications::validate_webhook_url(&request.url)
+            .map_err(|e| poem::Error::from_string(e, StatusCode::BAD_REQUEST))?;
+
+        if request.events.is_empty() {
+            return Err(poem::Error::from_string(
+                "At least one event is required",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        if let Some(unknown) = request
+            .events
+            .iter()
+            .find(|event| *event != "*" && !events::EVENTS.contains(&event.as_str()))
+        {
+            return Err(poem::Error::from_string(
+                format!("Unknown event {}, expected one of {}", unknown, events::EVENTS.join(", ")),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        Self::audit(
+            &state,
+            &admin,
+            "webhook_create",
+            serde_json::json!({ "url": url.as_str(), "events": request.events }),
+        )
+        .await;
+
+        let secret = request.secret.filter(|secret| !secret.is_empty()).unwrap_or_else(|| {
             format!(
-                "Reindexing completed with {} errors. Processed {} topics and {} posts",
-                errors, topics_processed, posts_processed
+                "{}{}",
+                uuid::Uuid::new_v4().simple(),
+                uuid::Uuid::new_v4().simple()
             )
-        };
+        });
 
-        info!("{}", message);
+        let endpoint = WebhookEndpoint::create(
+            url.as_str(),
+            &secret,
+            &request.events,
+            request.description.as_deref(),
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to create webhook endpoint: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        info!("Admin {} registered webhook endpoint {}", admin, endpoint.endpoint_id);
+
+        Ok(Json(AdminWebhookEndpointCreated { endpoint, secret }))
+    }
 
-        Ok(Json(ReindexResponse {
-            success,
-            message,
-            topics_processed,
-            posts_processed,
-            errors,
+    /// /admin/webhooks/:endpoint_id
+    ///
+    /// Remove an endpoint along with its delivery log
+    #[oai(path = "/admin/webhooks/:endpoint_id", method = "delete", tag = "ApiTags::Admin")]
+    async fn delete_webhook_endpoint(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] endpoint_id: Path<i64>,
+    ) -> Result<Json<serde_json::Value>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "webhook_delete",
+            serde_json::json!({ "endpoint_id": endpoint_id.0 }),
+        )
+        .await;
+
+        let deleted = WebhookEndpoint::delete(endpoint_id.0, &state).await.map_err(|e| {
+            error!("Failed to delete webhook endpoint: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        if !deleted {
+            return Err(poem::Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        Ok(Json(serde_json::json!({})))
+    }
+
+    /// /admin/webhooks/:endpoint_id/deliveries
+    ///
+    /// Delivery log of an endpoint, newest first
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(
+        path = "/admin/webhooks/:endpoint_id/deliveries",
+        method = "get",
+        tag = "ApiTags::Admin"
+    )]
+    async fn list_webhook_deliveries(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] endpoint_id: Path<i64>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<AdminWebhookDeliveriesResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(50).clamp(1, 200);
+        Self::audit(
+            &state,
+            &admin,
+            "webhook_deliveries_list",
+            serde_json::json!({ "endpoint_id": endpoint_id.0, "page": page, "size": size }),
+        )
+        .await;
+
+        let deliveries = WebhookDelivery::list_by_endpoint(endpoint_id.0, page, size, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to list webhook deliveries: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let total = WebhookDelivery::count_by_endpoint(endpoint_id.0, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to count webhook deliveries: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(AdminWebhookDeliveriesResponse {
+            has_more: page * size < total,
+            deliveries,
+            total,
         }))
     }
 
+    /// /admin/quarantine
+    ///
+    /// List quarantined topics, newest first
+    #[oai(path = "/admin/quarantine", method = "get", tag = "ApiTags::Admin")]
+    async fn list_quarantined_topics(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+    ) -> Result<Json<Vec<QuarantinedTopic>>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "quarantine_list", serde_json::json!({})).await;
+
+        let topics = QuarantinedTopic::list(&state).await.map_err(|e| {
+            error!("Failed to list quarantined topics: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(topics))
+    }
+
+    /// /admin/quarantine/:discourse_id/:topic_id
+    ///
+    /// Quarantine a topic, it is no longer served (410), indexed or searchable
+    /// Its search documents are deleted, the database rows are kept
+    #[oai(
+        path = "/admin/quarantine/:discourse_id/:topic_id",
+        method = "put",
+        tag = "ApiTags::Admin"
+    )]
+    async fn quarantine_topic(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        request: Json<AdminQuarantineRequest>,
+    ) -> Result<Json<QuarantinedTopic>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "quarantine_topic",
+            serde_json::json!({
+                "discourse_id": discourse_id.0,
+                "topic_id": topic_id.0,
+                "reason": request.0.reason,
+            }),
+        )
+        .await;
+
+        let quarantined = QuarantinedTopic::create(
+            &discourse_id,
+            topic_id.0,
+            request.0.reason.as_deref(),
+            &admin.0,
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to quarantine topic: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        if let Some(meili) = &state.meili {
+            if let Err(e) =
+                meili::delete_topic_documents(&meili.index("forum"), &discourse_id, topic_id.0).await
+            {
+                error!("Failed to delete quarantined topic from Meilisearch: {}", e);
+            }
+        }
+        state
+            .cache
+            .invalidate_topic_responses(&discourse_id, topic_id.0);
+
+        info!(
+            "Admin {} quarantined topic {} on {}",
+            admin, topic_id.0, discourse_id.0
+        );
+
+        Ok(Json(quarantined))
+    }
+
+    /// /admin/quarantine/:discourse_id/:topic_id
+    ///
+    /// Lift a quarantine, the topic is served again and put back into search
+    #[oai(
+        path = "/admin/quarantine/:discourse_id/:topic_id",
+        method = "delete",
+        tag = "ApiTags::Admin"
+    )]
+    async fn unquarantine_topic(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+    ) -> Result<Json<serde_json::Value>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "unquarantine_topic",
+            serde_json::json!({ "discourse_id": discourse_id.0, "topic_id": topic_id.0 }),
+        )
+        .await;
+
+        let deleted = QuarantinedTopic::delete(&discourse_id, topic_id.0, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to unquarantine topic: {}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if !deleted {
+            return Err(poem::Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        if let Err(e) = reindex::reindex_topic(&discourse_id, topic_id.0, &state).await {
+            error!("Failed to reindex unquarantined topic: {}", e);
+        }
+        // catch up on posts made while the indexer was skipping the topic
+        if let Err(e) = state.discourse.enqueue(&discourse_id, topic_id.0, 1).await {
+            warn!("Failed to enqueue unquarantined topic: {}", e);
+        }
+
+        info!(
+            "Admin {} lifted the quarantine of topic {} on {}",
+            admin, topic_id.0, discourse_id.0
+        );
+
+        Ok(Json(serde_json::json!({})))
+    }
+
+    /// /admin/export/topics.jsonl
+    ///
+    /// Export topics as JSON lines, optionally filtered by instance and creation date
+    /// Dates are inclusive
+    #[oai(path = "/admin/export/topics.jsonl", method = "get", tag = "ApiTags::Admin")]
+    async fn export_topics(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        discourse_id: Query<Option<String>>,
+        from: Query<Option<chrono::NaiveDate>>,
+        to: Query<Option<chrono::NaiveDate>>,
+    ) -> Result<ExportResponse> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        let filter = export_filter(discourse_id.0, from.0, to.0);
+        Self::audit(
+            &state,
+            &admin,
+            "export_topics",
+            serde_json::json!({ "discourse_id": filter.discourse_id, "from": from.0, "to": to.0 }),
+        )
+        .await;
+
+        Ok(ExportResponse::Ok(
+            Binary(dump::export_topics(state.database.pool.clone(), filter)),
+            "attachment; filename=\"topics.jsonl\"".to_string(),
+        ))
+    }
+
+    /// /admin/export/posts.jsonl
+    ///
+    /// Export posts as JSON lines, optionally filtered by instance and creation date
+    /// Dates are inclusive
+    #[oai(path = "/admin/export/posts.jsonl", method = "get", tag = "ApiTags::Admin")]
+    async fn export_posts(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        discourse_id: Query<Option<String>>,
+        from: Query<Option<chrono::NaiveDate>>,
+        to: Query<Option<chrono::NaiveDate>>,
+    ) -> Result<ExportResponse> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        let filter = export_filter(discourse_id.0, from.0, to.0);
+        Self::audit(
+            &state,
+            &admin,
+            "export_posts",
+            serde_json::json!({ "discourse_id": filter.discourse_id, "from": from.0, "to": to.0 }),
+        )
+        .await;
+
+        Ok(ExportResponse::Ok(
+            Binary(dump::export_posts(state.database.pool.clone(), filter)),
+            "attachment; filename=\"posts.jsonl\"".to_string(),
+        ))
+    }
+
+    /// /admin/import/topics.jsonl
+    ///
+    /// Import topics from a JSON lines dump in the format of the topic export
+    /// Set ?index=true to also add them to the search index
+    #[oai(path = "/admin/import/topics.jsonl", method = "post", tag = "ApiTags::Admin")]
+    async fn import_topics(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        index: Query<Option<bool>>,
+        body: Binary<poem::Body>,
+    ) -> Result<Json<ImportReport>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        let index = index.0.unwrap_or(false);
+        Self::audit(&state, &admin, "import_topics", serde_json::json!({ "index": index })).await;
+
+        let report = dump::import_topics(body.0, index, &state).await.map_err(|e| {
+            error!("Failed to read topic import: {}", e);
+            poem::Error::from_status(StatusCode::BAD_REQUEST)
+        })?;
+        info!(
+            "Admin {} imported topics: {} inserted, {} updated, {} skipped",
+            admin, report.inserted, report.updated, report.skipped
+        );
+
+        Ok(Json(report))
+    }
+
+    /// /admin/import/posts.jsonl
+    ///
+    /// Import posts from a JSON lines dump in the format of the post export
+    /// Set ?index=true to also add them to the search index
+    #[oai(path = "/admin/import/posts.jsonl", method = "post", tag = "ApiTags::Admin")]
+    async fn import_posts(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        index: Query<Option<bool>>,
+        body: Binary<poem::Body>,
+    ) -> Result<Json<ImportReport>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        let index = index.0.unwrap_or(false);
+        Self::audit(&state, &admin, "import_posts", serde_json::json!({ "index": index })).await;
+
+        let report = dump::import_posts(body.0, index, &state).await.map_err(|e| {
+            error!("Failed to read post import: {}", e);
+            poem::Error::from_status(StatusCode::BAD_REQUEST)
+        })?;
+        info!(
+            "Admin {} imported posts: {} inserted, {} updated, {} skipped",
+            admin, report.inserted, report.updated, report.skipped
+        );
+
+        Ok(Json(report))
+    }
+
     /// /admin/stats
     ///
     /// Get indexing statistics
@@ -246,9 +1143,11 @@ impl AdminApi {
     async fn get_stats(
         &self,
         state: Data<&AppState>,
+        req: &Request,
         #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
     ) -> Result<Json<AdminStatsResponse>> {
-        Self::verify_admin_key(admin_key.0)?;
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "stats", serde_json::json!({})).await;
 
         // Get database counts
         let database_topics = match sqlx::query_scalar!("SELECT COUNT(*) FROM topics")
@@ -273,24 +1172,62 @@ impl AdminApi {
             }
         };
 
+        let topics_behind_on_posts = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM topics t WHERE t.post_count <> (SELECT COUNT(*) FROM posts p WHERE p.discourse_id = t.discourse_id AND p.topic_id = t.topic_id)",
+        )
+        .fetch_one(&state.database.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to count topics behind on posts: {}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
         // Get Meilisearch document count
-        let meilisearch_documents = if let Some(meili) = &state.meili {
+        let (meilisearch_documents, topics_missing_from_search) = if let Some(meili) = &state.meili {
             let forum_index = meili.index("forum");
-            match forum_index.get_stats().await {
+            let documents = match forum_index.get_stats().await {
                 Ok(stats) => Some(stats.number_of_documents as i64),
                 Err(e) => {
                     warn!("Failed to get Meilisearch stats: {}", e);
                     None
                 }
-            }
+            };
+
+            // Every topic has one topic document, so comparing counts gives the size of the gap
+            let topic_documents = forum_index
+                .search()
+                .with_filter("entity_type = topic")
+                .with_page(1)
+                .with_hits_per_page(0)
+                .execute::<serde_json::Value>()
+                .await;
+            let missing = match topic_documents {
+                Ok(results) => results
+                    .total_hits
+                    .map(|hits| (database_topics - hits as i64).max(0)),
+                Err(e) => {
+                    warn!("Failed to count topic documents in Meilisearch: {}", e);
+                    None
+                }
+            };
+
+            (documents, missing)
         } else {
-            None
+            (None, None)
+        };
+
+        let ical_last_sync = match &state.ical {
+            Some(ical) => ical.last_sync_stats().await,
+            None => None,
         };
 
         Ok(Json(AdminStatsResponse {
             database_topics,
             database_posts,
             meilisearch_documents,
+            ical_last_sync,
+            topics_behind_on_posts,
+            topics_missing_from_search,
         }))
     }
 
@@ -301,9 +1238,11 @@ impl AdminApi {
     async fn get_usage_stats(
         &self,
         state: Data<&AppState>,
+        req: &Request,
         #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
     ) -> Result<Json<AdminUsageResponse>> {
-        Self::verify_admin_key(admin_key.0)?;
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(&state, &admin, "usage", serde_json::json!({})).await;
 
         // Get all users' usage overview
         let users = get_all_users_usage_overview(&state).await.map_err(|e| {
@@ -328,6 +1267,70 @@ impl AdminApi {
         }))
     }
 
+    /// /admin/usage/:user_id
+    ///
+    /// Get a user's workshop usage per day and per model
+    /// Dates are inclusive and default to the last 30 days
+    #[oai(path = "/admin/usage/:user_id", method = "get", tag = "ApiTags::Admin")]
+    async fn get_user_usage(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
+        #[oai(style = "simple")] user_id: Path<uuid::Uuid>,
+        from: Query<Option<chrono::NaiveDate>>,
+        to: Query<Option<chrono::NaiveDate>>,
+    ) -> Result<Json<AdminUserUsageResponse>> {
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+
+        let to = to.0.unwrap_or_else(|| chrono::Utc::now().date_naive());
+        let from = from.0.unwrap_or(to - chrono::Days::new(29));
+        if from > to {
+            return Err(poem::Error::from_string(
+                "from must not be after to",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        Self::audit(
+            &state,
+            &admin,
+            "user_usage",
+            serde_json::json!({ "user_id": user_id.0, "from": from, "to": to }),
+        )
+        .await;
+
+        let start = from.and_time(chrono::NaiveTime::MIN).and_utc();
+        let end = (to + chrono::Days::new(1)).and_time(chrono::NaiveTime::MIN).and_utc();
+
+        let daily = get_user_daily_usage_in_range(user_id.0, start, end, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to get daily usage for user {}: {}", user_id.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let models = get_user_usage_by_model_in_range(user_id.0, start, end, &state)
+            .await
+            .map_err(|e| {
+                error!("Failed to get model usage for user {}: {}", user_id.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(AdminUserUsageResponse {
+            user_id: user_id.0,
+            from,
+            to,
+            total_tokens: models.iter().map(|m| m.total_tokens).sum(),
+            total_prompt_tokens: models.iter().map(|m| m.prompt_tokens).sum(),
+            total_completion_tokens: models.iter().map(|m| m.completion_tokens).sum(),
+            total_reasoning_tokens: models.iter().map(|m| m.reasoning_tokens).sum(),
+            message_count: models.iter().map(|m| m.message_count).sum(),
+            daily,
+            models,
+        }))
+    }
+
     #[oai(
         path = "/admin/topic_summary",
         method = "delete",
@@ -336,11 +1339,23 @@ impl AdminApi {
     async fn delete_topic_summary(
         &self,
         state: Data<&AppState>,
+        req: &Request,
         #[oai(name = "X-Admin-Key")] admin_key: Header<Option<String>>,
         #[oai(name = "topic_id")] topic_id: poem_openapi::param::Query<i32>,
         #[oai(name = "discourse_id")] discourse_id: poem_openapi::param::Query<String>,
     ) -> Result<()> {
-        Self::verify_admin_key(admin_key.0)?;
+        let admin = Self::verify_admin(&state, admin_key.0, req)?;
+        Self::audit(
+            &state,
+            &admin,
+            "delete_topic_summary",
+            serde_json::json!({ "topic_id": topic_id.0, "discourse_id": discourse_id.0 }),
+        )
+        .await;
+        info!(
+            "Admin {} is deleting the topic summary for topic_id {}",
+            admin, topic_id.0
+        );
 
         let result = sqlx::query!(
             "DELETE FROM topic_summaries WHERE topic_id = $1 AND discourse_id = $2",
@@ -376,26 +1391,3 @@ impl AdminApi {
         }
     }
 }
-
-/// Helper function to get username for a user_id using Discourse API with caching
-async fn get_username_for_user_id(_user_id: i32, _discourse: &DiscourseService) -> Option<String> {
-    // For reindexing, we try to get usernames from Discourse, but don't block on failures
-    // This is best-effort - new posts will have usernames from the API
-    None
-}
-
-/// Build a comprehensive user mapping by extracting user info from post extras
-fn build_user_mapping_from_posts(posts: &[Post]) -> std::collections::HashMap<i32, String> {
-    let mut user_map = std::collections::HashMap::new();
-
-    for post in posts {
-        if let Some(extra) = &post.extra {
-            // Try to extract username from the extra JSON data
-            if let Some(username) = extra.get("username").and_then(|u| u.as_str()) {
-                user_map.insert(post.user_id, username.to_string());
-            }
-        }
-    }
-
-    user_map
-}
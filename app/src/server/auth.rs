@@ -4,6 +4,7 @@
 //! 
 //! ## Features:
 //! - JWT Bearer token validation via poem_openapi SecurityScheme
+//! - Session cookie authentication for browser logins via SSO
 //! - Automatic user lookup and authentication state management
 //! - Token expiration checking with early warning
 //! - Resource ownership validation helpers
@@ -16,7 +17,7 @@
 //! ```rust
 //! #[oai(path = "/protected", method = "get")]
 //! async fn protected_endpoint(&self, auth_user: AuthUser) -> Result<Json<Response>> {
-//!     let user_id = auth_user.0.user_id();
+//!     let user_id = auth_user.user_id();
 //!     // User is guaranteed to be authenticated
 //! }
 //! ```
@@ -33,11 +34,11 @@
 //! ```
 
 use poem::Request;
-use poem_openapi::{auth::Bearer, SecurityScheme};
+use poem_openapi::{auth::{ApiKey, Bearer}, SecurityScheme};
 use uuid::Uuid;
 
 use crate::models::user::User;
-use crate::modules::sso::JWTClaims;
+use crate::modules::sso::{JWTClaims, SESSION_COOKIE};
 use crate::state::AppState;
 
 /// Represents an authenticated user with their information
@@ -91,8 +92,28 @@ impl AuthenticatedUser {
 )]
 pub struct JWTAuth(pub AuthenticatedUser);
 
+/// Session cookie authentication scheme, set by the SSO callback
+#[derive(SecurityScheme)]
+#[oai(
+    ty = "api_key",
+    key_name = "forum_session",
+    key_in = "cookie",
+    checker = "validate_session_cookie"
+)]
+pub struct SessionAuth(pub AuthenticatedUser);
+
 /// Parse and validate JWT token from Authorization header
 async fn validate_bearer_token(req: &Request, bearer: Bearer) -> Option<AuthenticatedUser> {
+    authenticate_token(req, &bearer.token).await
+}
+
+/// Parse and validate JWT token from the session cookie
+async fn validate_session_cookie(req: &Request, api_key: ApiKey) -> Option<AuthenticatedUser> {
+    authenticate_token(req, &api_key.key).await
+}
+
+/// Validate a JWT token and look up the user it belongs to
+pub async fn authenticate_token(req: &Request, token: &str) -> Option<AuthenticatedUser> {
     // Try different ways to access the app state
     let state = if let Some(state) = req.extensions().get::<poem::web::Data<AppState>>() {
         state
@@ -115,7 +136,7 @@ async fn validate_bearer_token(req: &Request, bearer: Bearer) -> Option<Authenti
     };
 
     // Validate JWT token
-    let claims = match sso_service.validate_jwt_token(&bearer.token) {
+    let claims = match sso_service.validate_jwt_token(token) {
         Ok(claims) => claims,
         Err(e) => {
             tracing::warn!("❌ JWT validation failed: {}", e);
@@ -158,8 +179,36 @@ async fn validate_bearer_token(req: &Request, bearer: Bearer) -> Option<Authenti
     Some(AuthenticatedUser { user, claims })
 }
 
-// Convenience type alias for easier usage in handlers
-pub type AuthUser = JWTAuth;
+/// Accepts either a bearer token or the session cookie
+#[derive(SecurityScheme)]
+pub enum AuthUser {
+    Bearer(JWTAuth),
+    Session(SessionAuth),
+}
+
+impl std::ops::Deref for AuthUser {
+    type Target = AuthenticatedUser;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            AuthUser::Bearer(JWTAuth(user)) => user,
+            AuthUser::Session(SessionAuth(user)) => user,
+        }
+    }
+}
+
+/// Read the session token from the Cookie header
+pub(crate) fn session_token_from_cookies(req: &Request) -> Option<&str> {
+    req.headers()
+        .get_all("cookie")
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
 
 /// Authentication error types for better error handling
 #[derive(Debug)]
@@ -188,16 +237,18 @@ impl std::error::Error for AuthError {}
 /// Helper function to extract authenticated user from request without using SecurityScheme
 /// Useful for optional authentication scenarios
 pub async fn extract_user_from_request(req: &Request) -> Result<Option<AuthenticatedUser>, AuthError> {
-    let auth_header = match req.headers().get("authorization") {
-        Some(header) => header,
-        None => return Ok(None),
-    };
-
-    let token = match auth_header.to_str() {
-        Ok(header_str) if header_str.starts_with("Bearer ") => {
-            &header_str[7..] // Remove "Bearer " prefix
-        }
-        _ => return Err(AuthError::TokenInvalid),
+    let token = match req.headers().get("authorization") {
+        Some(auth_header) => match auth_header.to_str() {
+            Ok(header_str) if header_str.starts_with("Bearer ") => {
+                &header_str[7..] // Remove "Bearer " prefix
+            }
+            _ => return Err(AuthError::TokenInvalid),
+        },
+        // Fall back to the session cookie set by the SSO callback
+        None => match session_token_from_cookies(req) {
+            Some(token) => token,
+            None => return Ok(None),
+        },
     };
 
     let state = req.extensions()
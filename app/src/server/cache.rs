@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use figment::{Figment, providers::Env};
+use poem::http::{Method, StatusCode, header};
+use poem::{Endpoint, IntoResponse, Request, Response, middleware::Middleware};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::server::auth::session_token_from_cookies;
+use crate::server::topic::etag_matches;
+use crate::state::AppState;
+use crate::tmp::CachedResponse;
+
+/// Routes cached by default, as `pattern=ttl_seconds` where `*` matches a single path segment
+const DEFAULT_ROUTES: &str = "/topics=60,/topics/trending=300,/t/*/*/summary=60,/eip/*=300";
+
+#[derive(Debug, Deserialize)]
+pub struct ResponseCacheConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_routes")]
+    pub routes: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_routes() -> String {
+    DEFAULT_ROUTES.to_string()
+}
+
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    segments: Vec<String>,
+    ttl: Duration,
+}
+
+impl CachedRoute {
+    fn parse(raw: &str) -> Option<Self> {
+        let (pattern, ttl) = raw.trim().split_once('=')?;
+        let ttl = ttl.trim().parse::<u64>().ok()?;
+
+        Some(Self {
+            segments: split_path(pattern.trim()),
+            ttl: Duration::from_secs(ttl),
+        })
+    }
+
+    fn matches(&self, segments: &[String]) -> bool {
+        self.segments.len() == segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(segments)
+                .all(|(pattern, segment)| pattern == "*" || pattern == segment)
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Caches successful GET responses of hot read endpoints in the `CacheService`
+#[derive(Clone)]
+pub struct ResponseCache {
+    state: AppState,
+    enabled: bool,
+    routes: Vec<CachedRoute>,
+}
+
+impl ResponseCache {
+    pub fn new(state: &AppState) -> Self {
+        let config = Figment::new()
+            .merge(Env::prefixed("RESPONSE_CACHE_"))
+            .extract::<ResponseCacheConfig>()
+            .unwrap_or_else(|e| {
+                warn!("Invalid response cache config, using defaults: {}", e);
+                ResponseCacheConfig {
+                    enabled: default_enabled(),
+                    routes: default_routes(),
+                }
+            });
+
+        let routes: Vec<CachedRoute> = config
+            .routes
+            .split(',')
+            .filter(|route| !route.trim().is_empty())
+            .filter_map(|route| {
+                let parsed = CachedRoute::parse(route);
+                if parsed.is_none() {
+                    warn!("Ignoring invalid response cache route: {}", route);
+                }
+                parsed
+            })
+            .collect();
+
+        info!(
+            "Response cache {} for {} routes",
+            if config.enabled { "enabled" } else { "disabled" },
+            routes.len()
+        );
+
+        Self {
+            state: state.clone(),
+            enabled: config.enabled,
+            routes,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Endpoint> Middleware<E> for ResponseCache {
+    type Output = ResponseCacheImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ResponseCacheImpl {
+            ep,
+            cache: self.clone(),
+        }
+    }
+}
+
+pub struct ResponseCacheImpl<E> {
+    ep: E,
+    cache: ResponseCache,
+}
+
+impl<E> ResponseCacheImpl<E> {
+    fn ttl_for(&self, req: &Request) -> Option<Duration> {
+        if !self.cache.enabled
+            || req.method() != Method::GET
+            || req.headers().contains_key(header::AUTHORIZATION)
+            || session_token_from_cookies(req).is_some()
+        {
+            return None;
+        }
+
+        let segments = split_path(req.uri().path());
+        self.cache
+            .routes
+            .iter()
+            .find(|route| route.matches(&segments))
+            .map(|route| route.ttl)
+    }
+}
+
+impl<E: Endpoint> Endpoint for ResponseCacheImpl<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let Some(ttl) = self.ttl_for(&req) else {
+            return Ok(self.ep.call(req).await?.into_response());
+        };
+
+        let key = req
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.to_string())
+            .unwrap_or_else(|| req.uri().path().to_string());
+
+        let response_cache = &self.cache.state.cache.response_cache;
+
+        if let Some(cached) = response_cache.get(&key).await {
+            if cached.expires_at > Instant::now() {
+                let if_none_match = req
+                    .headers()
+                    .get(header::IF_NONE_MATCH)
+                    .and_then(|value| value.to_str().ok());
+                let etag = cached
+                    .headers
+                    .get(header::ETAG)
+                    .and_then(|value| value.to_str().ok());
+                if let Some(etag) = etag {
+                    if etag_matches(if_none_match, etag) {
+                        return Ok(StatusCode::NOT_MODIFIED.into_response());
+                    }
+                }
+
+                let mut response = Response::builder()
+                    .status(cached.status)
+                    .body(cached.body.clone());
+                response.headers_mut().extend(cached.headers.clone());
+                response
+                    .headers_mut()
+                    .insert("X-Cache", "HIT".parse().unwrap());
+                return Ok(response);
+            }
+
+            response_cache.invalidate(&key).await;
+        }
+
+        let response = self.ep.call(req).await?.into_response();
+        if response.status() != StatusCode::OK {
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = body.into_vec().await?;
+
+        response_cache
+            .insert(
+                key,
+                CachedResponse {
+                    status: parts.status,
+                    headers: parts.headers.clone(),
+                    body: body.clone(),
+                    expires_at: Instant::now() + ttl,
+                },
+            )
+            .await;
+
+        let mut response = Response::from_parts(parts, body.into());
+        response
+            .headers_mut()
+            .insert("X-Cache", "MISS".parse().unwrap());
+        Ok(response)
+    }
+}
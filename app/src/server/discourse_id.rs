@@ -0,0 +1,68 @@
+use std::ops::Deref;
+
+use poem::{Request, RequestBody, Result, http::StatusCode};
+use poem_openapi::{
+    ApiExtractor, ApiExtractorType, ExtractParamOptions,
+    param::Path,
+    registry::{MetaParamIn, MetaSchemaRef, Registry},
+    types::Type,
+};
+
+use crate::state::AppState;
+
+/// `:discourse_id` path parameter, requests for instances that aren't configured get a 404
+/// before the handler runs
+#[derive(Debug, Clone)]
+pub struct DiscourseId(pub String);
+
+impl Deref for DiscourseId {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> ApiExtractor<'a> for DiscourseId {
+    const TYPES: &'static [ApiExtractorType] = &[ApiExtractorType::Parameter];
+    const PARAM_IS_REQUIRED: bool = true;
+
+    type ParamType = String;
+    type ParamRawType = String;
+
+    fn register(registry: &mut Registry) {
+        String::register(registry);
+    }
+
+    fn param_in() -> Option<MetaParamIn> {
+        Some(MetaParamIn::Path)
+    }
+
+    fn param_schema_ref() -> Option<MetaSchemaRef> {
+        Some(String::schema_ref())
+    }
+
+    fn param_raw_type(&self) -> Option<&Self::ParamRawType> {
+        Some(&self.0)
+    }
+
+    async fn from_request(
+        request: &'a Request,
+        body: &mut RequestBody,
+        param_opts: ExtractParamOptions<Self::ParamType>,
+    ) -> Result<Self> {
+        let Path(discourse_id) = Path::<String>::from_request(request, body, param_opts).await?;
+
+        let known = request
+            .data::<AppState>()
+            .is_some_and(|state| state.discourse.get_discourse_url(&discourse_id).is_some());
+        if !known {
+            return Err(poem::Error::from_string(
+                format!("Unknown discourse_id {}", discourse_id),
+                StatusCode::NOT_FOUND,
+            ));
+        }
+
+        Ok(Self(discourse_id))
+    }
+}
@@ -0,0 +1,79 @@
+use poem::{Result, web::Data};
+use poem_openapi::param::Path;
+use poem_openapi::{Object, OpenApi, payload::Json};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::models::pm::PMMeeting;
+use crate::models::topics::{Topic, entity::TopicEntity};
+use crate::modules::entities;
+use crate::server::ApiTags;
+use crate::server::topic::without_quarantined;
+use crate::state::AppState;
+
+/// Topics included in the overview, the full list is at /entities/eip/:number/topics
+const EIP_TOPICS_LIMIT: i64 = 20;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct EipApi;
+
+/// Everything known about an EIP across the forum and ethereum/pm
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct EipOverview {
+    pub number: u32,
+    pub url: String,
+    /// Forum topics mentioning the EIP
+    pub topic_count: i64,
+    /// Most recently active topics mentioning the EIP
+    pub topics: Vec<Topic>,
+    /// Calls whose ethereum/pm issue mentions the EIP, absent when ethereum/pm is unreachable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meetings: Option<Vec<PMMeeting>>,
+}
+
+#[OpenApi]
+impl EipApi {
+    /// /eip/:number
+    ///
+    /// Forum topics and protocol calls mentioning an EIP
+    #[oai(path = "/eip/:number", method = "get", operation_id = "get_eip", tag = "ApiTags::Eip")]
+    async fn get_eip(
+        &self,
+        state: Data<&AppState>,
+        #[oai(style = "simple")] number: Path<u32>,
+    ) -> Result<Json<EipOverview>> {
+        let value = number.0.to_string();
+
+        let topic_count = TopicEntity::count_topics(entities::EIP, &value, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error counting topics mentioning EIP-{}: {:?}", number.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let topics = TopicEntity::find_topics(entities::EIP, &value, 1, EIP_TOPICS_LIMIT, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting topics mentioning EIP-{}: {:?}", number.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        // the forum side is still worth serving while GitHub is down
+        let meetings = match state.pm.meetings_mentioning(entities::EIP, &value, &state).await {
+            Ok(meetings) => Some(meetings),
+            Err(e) => {
+                warn!("Error getting calls mentioning EIP-{}: {:?}", number.0, e);
+                None
+            }
+        };
+
+        Ok(Json(EipOverview {
+            number: number.0,
+            url: format!("https://eips.ethereum.org/EIPS/eip-{}", number.0),
+            topic_count,
+            topics: without_quarantined(topics, &state).await?,
+            meetings,
+        }))
+    }
+}
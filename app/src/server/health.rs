@@ -0,0 +1,45 @@
+use poem::web::Data;
+use poem_openapi::{Object, OpenApi, payload::Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    modules::{discourse::IndexerStatus, workshop::breaker::BreakerState},
+    server::ApiTags,
+    state::AppState,
+};
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct HealthApi;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct HealthResponse {
+    /// `ok`, or `degraded` when an indexer went stale or the AI provider is unavailable
+    pub status: String,
+    pub indexers: Vec<IndexerStatus>,
+    /// Circuit breaker around the AI provider
+    pub ai: BreakerState,
+}
+
+#[OpenApi]
+impl HealthApi {
+    /// /health
+    ///
+    /// Service health, including the freshness of every Discourse indexer
+    /// and the state of the AI provider
+    #[oai(path = "/health", method = "get", tag = "ApiTags::Health")]
+    async fn health(&self, state: Data<&AppState>) -> Json<HealthResponse> {
+        let indexers = state.discourse.indexer_statuses().await;
+        let ai = state.workshop.breaker.state();
+        let status = if indexers.iter().any(|indexer| indexer.stale) || ai == BreakerState::Open {
+            "degraded"
+        } else {
+            "ok"
+        };
+
+        Json(HealthResponse {
+            status: status.to_string(),
+            indexers,
+            ai,
+        })
+    }
+}
@@ -11,6 +11,7 @@ use crate::{
         topics::{post::Post, Topic},
     },
     modules::discourse::{ForumSearchDocument, LResult},
+    server::topic::ensure_not_quarantined,
     state::AppState,
 };
 
@@ -43,6 +44,8 @@ impl ForumTools {
             slug: None,
             pm_issue: None,
             cooked: Some(error_message),
+            code: None,
+            entities: None,
             entity_id: "error".to_string(),
         }
     }
@@ -72,6 +75,10 @@ impl ForumTools {
     /// - "What is topic 1234 about?" → Use this tool with topic_id=1234
     /// - "Can you summarize the discussion on EIP-4844?" → First search for the topic, then summarize it
     async fn get_topic_summary(&self, discourse_id: String, topic_id: i32) -> Text<String> {
+        if let Err(err) = ensure_not_quarantined(&discourse_id, topic_id, &self.state).await {
+            return Text(format!("error: {err}"));
+        }
+
         match Topic::get_summary_by_topic_id(&discourse_id, topic_id, &self.state).await {
             Ok(summary) => Text(summary.summary_text),
             Err(err) => Text(format!("error: {err}")),
@@ -111,8 +118,15 @@ impl ForumTools {
         size: Option<i32>,
     ) -> Json<Vec<Post>> {
         let page = page.unwrap_or(1);
-        match Post::find_by_topic_id(&discourse_id, topic_id, page, size, &self.state).await {
-            Ok((posts, _)) => Json(posts),
+        let posts = match ensure_not_quarantined(&discourse_id, topic_id, &self.state).await {
+            Ok(()) => Post::find_by_topic_id(&discourse_id, topic_id, page, size, &self.state)
+                .await
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        match posts {
+            Ok(posts) => Json(posts.items),
             Err(err) => Json(vec![Post {
                 discourse_id,
                 post_id: -1,
@@ -367,6 +381,17 @@ impl ForumTools {
         limit: Option<usize>,
         offset: Option<usize>,
     ) -> Json<Vec<ForumSearchDocument>> {
+        // the id ends up in a filter expression, only configured instances are safe to put there
+        if self.state.discourse.get_discourse_url(&discourse_id).is_none() {
+            return Json(vec![Self::create_error_document(
+                format!("Unknown discourse_id {}", discourse_id),
+                None,
+                Some(topic_id),
+                None,
+                None,
+            )]);
+        }
+
         let Some(meili) = &self.state.meili else {
             return Json(vec![Self::create_error_document(
                 "Meilisearch is not configured".to_string(),
@@ -381,7 +406,10 @@ impl ForumTools {
         let limit = limit.unwrap_or(20);
         let offset = offset.unwrap_or(0);
 
-        let filter = format!("entity_type = post AND topic_id = {}", topic_id);
+        let filter = format!(
+            "entity_type = post AND discourse_id = \"{}\" AND topic_id = {}",
+            discourse_id, topic_id
+        );
 
         match forum
             .search()
@@ -436,6 +464,16 @@ impl ForumTools {
         limit: Option<usize>,
         offset: Option<usize>,
     ) -> Json<Vec<ForumSearchDocument>> {
+        if self.state.discourse.get_discourse_url(&discourse_id).is_none() {
+            return Json(vec![Self::create_error_document(
+                format!("Unknown discourse_id {}", discourse_id),
+                None,
+                None,
+                Some(user_id),
+                None,
+            )]);
+        }
+
         let Some(meili) = &self.state.meili else {
             return Json(vec![Self::create_error_document(
                 "Meilisearch is not configured".to_string(),
@@ -450,7 +488,7 @@ impl ForumTools {
         let limit = limit.unwrap_or(20);
         let offset = offset.unwrap_or(0);
 
-        let filter = format!("user_id = {} AND discourse_id = {}", user_id, discourse_id);
+        let filter = format!("user_id = {} AND discourse_id = \"{}\"", user_id, discourse_id);
 
         let result = if let Some(q) = query {
             forum
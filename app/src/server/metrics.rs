@@ -0,0 +1,20 @@
+use poem::{Response, handler, http::StatusCode, web::Data};
+use prometheus::TEXT_FORMAT;
+
+use crate::state::AppState;
+
+/// /metrics
+///
+/// Prometheus scrape endpoint
+#[handler]
+pub async fn get_metrics(state: Data<&AppState>) -> poem::Result<Response> {
+    // the breaker only half-opens when asked, refresh its gauge before scraping
+    state.workshop.breaker.state();
+
+    let body = state.metrics.render().map_err(|e| {
+        tracing::error!("Error rendering metrics: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    Ok(Response::builder().content_type(TEXT_FORMAT).body(body))
+}
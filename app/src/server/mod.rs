@@ -1,20 +1,25 @@
+use access::MetricsAccess;
 use admin::AdminApi;
+use cache::ResponseCache;
+use eip::EipApi;
 use events::EventsApi;
 use governor::Quota;
+use health::HealthApi;
 use opengraph::OpenGraph;
 use pm::PMApi;
 use poem::{
     EndpointExt, Route, Server,
     endpoint::StaticFilesEndpoint,
     get, handler,
-    listener::TcpListener,
+    listener::{Acceptor, Listener, TcpListener},
     middleware::{Cors, OpenTelemetryMetrics},
 };
 use poem_openapi::{OpenApi, OpenApiService, Tags, payload::Html};
 use ratelimit::GovRateLimitMiddleware;
+use request_id::{REQUEST_ID_HEADER, RequestIdMiddleware};
 use std::num::NonZero;
 use topic::TopicApi;
-use tracing::info;
+use tracing::{error, info};
 use user::UserApi;
 use webhooks::WebhookApi;
 
@@ -24,13 +29,20 @@ use crate::{
 };
 // use tracing_mw::TraceId;
 
+pub mod access;
 pub mod admin;
 pub mod auth;
+pub mod cache;
+pub mod discourse_id;
+pub mod eip;
 pub mod events;
+pub mod health;
 pub mod mcp;
+pub mod metrics;
 pub mod opengraph;
 pub mod pm;
 pub mod ratelimit;
+pub mod request_id;
 pub mod search;
 pub mod topic;
 pub mod user;
@@ -45,6 +57,8 @@ pub enum ApiTags {
     User,
     /// Events Related Operations
     Events,
+    /// EIP Related Operations
+    Eip,
     /// Workshop Related Operations
     Workshop,
     /// Search Related Operations
@@ -53,6 +67,8 @@ pub enum ApiTags {
     Admin,
     /// Webhooks Related Operations
     Webhooks,
+    /// Service Health
+    Health,
 }
 
 fn get_api(_state: AppState) -> impl OpenApi {
@@ -61,10 +77,12 @@ fn get_api(_state: AppState) -> impl OpenApi {
         UserApi,
         EventsApi,
         PMApi,
+        EipApi,
         WorkshopApi,
         SearchApi,
         AdminApi,
         WebhookApi,
+        HealthApi,
     )
 }
 
@@ -76,6 +94,8 @@ pub async fn start_http(state: AppState) {
         .description("Ethereum Forum API with JWT Bearer Token Authentication");
 
     let spec = api_service.spec_endpoint();
+    let spec_yaml = api_service.spec_endpoint_yaml();
+    let swagger_ui = api_service.swagger_ui();
 
     let limiter = GovRateLimitMiddleware::new(
         Quota::per_minute(NonZero::new(120).unwrap()),
@@ -84,7 +104,12 @@ pub async fn start_http(state: AppState) {
 
     let opengraph = OpenGraph::new(&state);
 
-    let api_service = api_service
+    let response_cache = ResponseCache::new(&state);
+
+    // the chat socket isn't part of the OpenAPI spec, but is limited and measured like the rest
+    let api = Route::new()
+        .at("/ws/chat/:chat_id/ws", get(workshop::socket::chat_socket))
+        .nest("/", api_service.with(response_cache))
         .with(limiter)
         // .with(TraceId::new(Arc::new(global::tracer("ethereum-forum"))))
         .with(OpenTelemetryMetrics::new());
@@ -99,20 +124,91 @@ pub async fn start_http(state: AppState) {
         .no_cache_index()
         .with(opengraph);
 
-    let app = Route::new()
+    // `/metrics` stays off the public listener when it has an internal one, see `access`
+    let metrics_bind_addr = std::env::var("METRICS_BIND_ADDR").ok();
+    let metrics_endpoint = get(metrics::get_metrics).with(MetricsAccess::from_env());
+
+    let mut app = Route::new()
         .nest("/assets", assets_endpoint)
         .nest("/", spa_endpoint)
         .nest("/openapi.json", spec)
+        .nest("/openapi.yaml", spec_yaml)
         .nest("/docs", get(get_openapi_docs))
-        .nest("/api", api_service)
-        .nest("/mcp", mcp::endpoint(state.clone()))
+        .nest("/swagger", swagger_ui)
+        .nest("/api", api)
+        .nest("/mcp", mcp::endpoint(state.clone()));
+
+    match &metrics_bind_addr {
+        Some(metrics_bind_addr) => {
+            let metrics_app = Route::new().at("/metrics", metrics_endpoint).data(state.clone());
+            let listener = TcpListener::bind(metrics_bind_addr.clone());
+            info!("Serving metrics on {}", metrics_bind_addr);
+            async_std::task::spawn(async move {
+                if let Err(e) = Server::new(listener).run(metrics_app).await {
+                    error!("Metrics listener failed: {}", e);
+                }
+            });
+        }
+        None => app = app.at("/metrics", metrics_endpoint),
+    }
+
+    let app = app
         .data(state)
-        .with(Cors::new());
+        .with(cors())
+        .with(RequestIdMiddleware);
+
+    let bind_addr = bind_address();
+    let acceptor = match TcpListener::bind(bind_addr.as_str()).into_acceptor().await {
+        Ok(acceptor) => acceptor,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            error!("Cannot listen on {}: address is already in use", bind_addr);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("Cannot listen on {}: {}", bind_addr, e);
+            std::process::exit(1);
+        }
+    };
+
+    for addr in acceptor.local_addr() {
+        info!("Listening on {}", addr);
+    }
+
+    Server::new_with_acceptor(acceptor).run(app).await.unwrap();
+}
+
+/// `BIND_ADDR` when set, otherwise `HOST`:`PORT`, defaulting to 0.0.0.0:3000
+fn bind_address() -> String {
+    if let Ok(bind_addr) = std::env::var("BIND_ADDR") {
+        return bind_addr;
+    }
+
+    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
+
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Any origin by default, or only the comma separated origins in `CORS_ALLOWED_ORIGINS`
+///
+/// Admin and metrics access is enforced server side (keys, `server::access`), CORS only
+/// keeps other sites' scripts from reading responses in a browser.
+fn cors() -> Cors {
+    let cors = Cors::new().expose_header(REQUEST_ID_HEADER);
 
-    Server::new(TcpListener::bind("0.0.0.0:3000"))
-        .run(app)
-        .await
-        .unwrap();
+    match std::env::var("CORS_ALLOWED_ORIGINS") {
+        Ok(origins) => cors.allow_origins(
+            origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty()),
+        ),
+        Err(_) => cors,
+    }
 }
 
 #[handler]
@@ -1,11 +1,15 @@
 use async_trait::async_trait;
 use poem::IntoResponse;
-use poem::web::Html;
+use poem::http::{StatusCode, header};
 use poem::{Endpoint, Request, Response, middleware::Middleware};
-use regex::Regex;
-use tracing::info;
+use lol_html::html_content::ContentType;
+use lol_html::{ElementContentHandlers, RewriteStrSettings, Selector, element, rewrite_str};
+use std::borrow::Cow;
+use tracing::{info, warn};
 
-use crate::models::topics::Topic;
+use crate::models::topics::{Topic, quarantine::QuarantinedTopic};
+use crate::modules::discourse::LResult;
+use crate::modules::excerpt;
 use crate::state::AppState;
 
 #[derive(Clone)]
@@ -45,90 +49,297 @@ where
     type Output = Response;
 
     async fn call(&self, req: Request) -> poem::Result<Self::Output> {
-        let route = req.uri().to_string();
+        let route = req.uri().path().to_string();
+
+        // Process the request normally.
+        let response = self.ep.call(req).await?.into_response();
+
+        // only pages get tags, API responses and assets pass through untouched
+        if !is_html(&response) {
+            return Ok(response);
+        }
 
         info!("OpenGraph request to route: {}", route);
 
-        let mut opengraph_title: Option<String> = None;
-        let mut opengraph_description: Option<String> = None;
-        let mut opengraph_image: Option<String> = None;
-
-        if route.starts_with("/t/") {
-            let split = route.split("/").collect::<Vec<&str>>();
-            // TODO: Update parsing of parameters
-            let discourse_id = split.get(2).unwrap_or(&"magicians").to_string();
-            let topic_id = split.get(3).unwrap_or(&"").to_string();
-            let topic_id = topic_id.parse::<i32>().ok();
-            info!("Topic ID: {:?}", topic_id);
-            if let Some(topic_id) = topic_id {
-                let topic = Topic::get_by_topic_id("magicians", topic_id, &self.state).await;
-
-                if let Ok(topic) = topic {
-                    let first_post = topic.get_first_post(&self.state).await.ok();
-
-                    //
-                    info!("OpenGraph request to topic: {}", topic.title);
-                    opengraph_title = Some(topic.title);
-                    opengraph_description = first_post.and_then(|post| post.cooked).map(|cooked| {
-                        let regex = Regex::new(r#"<[^>]*?>"#).unwrap();
-                        regex.replace_all(&cooked, "").to_string()
-                    });
-                    opengraph_image = topic.image_url;
-                }
-            }
+        // pages of instances that aren't configured don't exist, same as their API routes
+        if unknown_instance(&route, &self.state) {
+            let mut response = response;
+            response.set_status(StatusCode::NOT_FOUND);
+            return Ok(response);
         }
 
-        // Process the request normally.
-        let x = self.ep.call(req).await?;
-        let mut response = x.into_response();
-
-        if opengraph_title.is_some() || opengraph_description.is_some() || opengraph_image.is_some()
-        {
-            // modify the html in the body of the response such that it has opengraph head tags
-            let body = response.take_body();
-            let body = body.into_bytes().await.unwrap();
-            let mut body = String::from_utf8(body.to_vec()).unwrap();
-
-            if let Some(title) = opengraph_title {
-                body = Regex::new(r#"property="og:title" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("property=\"og:title\" content=\"{}\"", title))
-                    .to_string();
-                body = Regex::new(r#"name="twitter:title" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("name=\"twitter:title\" content=\"{}\"", title))
-                    .to_string();
-                body = Regex::new(r#"<title>[^<]*?</title>"#)
-                    .unwrap()
-                    .replace(&body, format!("<title>{}</title>", title))
-                    .to_string();
+        let tags = if route.starts_with("/t/") {
+            topic_tags(&route, &self.state).await
+        } else if route.starts_with("/u/") {
+            user_tags(&route, &self.state).await
+        } else if route.starts_with("/pm/") {
+            pm_tags(&route, &self.state).await
+        } else {
+            OpenGraphTags::default()
+        };
+
+        Ok(tags.apply(response).await)
+    }
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .content_type()
+        .is_some_and(|content_type| content_type.trim_start().starts_with("text/html"))
+}
+
+/// `/t/:discourse_id/...` or `/u/:discourse_id/...` for an instance that isn't configured
+fn unknown_instance(route: &str, state: &AppState) -> bool {
+    if !route.starts_with("/t/") && !route.starts_with("/u/") {
+        return false;
+    }
+
+    match route.split('/').nth(2) {
+        Some(discourse_id) if !discourse_id.is_empty() => {
+            state.discourse.get_discourse_url(discourse_id).is_none()
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Default)]
+struct OpenGraphTags {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+}
+
+impl OpenGraphTags {
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+
+    /// Inject the tags into an HTML response, anything else is returned as is
+    async fn apply(self, mut response: Response) -> Response {
+        if self.is_empty() || !is_html(&response) {
+            return response;
+        }
+
+        let body = match response.take_body().into_bytes().await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to read response body for OpenGraph tags: {}", e);
+                return response;
             }
+        };
 
-            if let Some(description) = opengraph_description {
-                body = Regex::new(r#"property="og:description" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("property=\"og:description\" content=\"{}\"", description))
-                    .to_string();
-                body = Regex::new(r#"name="twitter:description" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("name=\"twitter:description\" content=\"{}\"", description))
-                    .to_string();
+        match String::from_utf8(body.to_vec()) {
+            Ok(html) => {
+                // the length of the static file no longer applies
+                response.headers_mut().remove(header::CONTENT_LENGTH);
+                response.set_body(self.inject(html));
             }
+            Err(_) => response.set_body(body),
+        }
+
+        response
+    }
+
+    /// Set the title and the og/twitter meta tags, values are escaped by the rewriter
+    fn inject(self, body: String) -> String {
+        let mut handlers = vec![];
+
+        if let Some(title) = &self.title {
+            handlers.push(element!("title", move |el| {
+                el.set_inner_content(title, ContentType::Text);
+                Ok(())
+            }));
+            handlers.push(set_meta_content(r#"meta[property="og:title"]"#, title));
+            handlers.push(set_meta_content(r#"meta[name="twitter:title"]"#, title));
+        }
+
+        if let Some(description) = &self.description {
+            handlers.push(set_meta_content(r#"meta[property="og:description"]"#, description));
+            handlers.push(set_meta_content(r#"meta[name="twitter:description"]"#, description));
+        }
+
+        if let Some(image) = &self.image {
+            handlers.push(set_meta_content(r#"meta[property="og:image"]"#, image));
+            handlers.push(set_meta_content(r#"meta[name="twitter:image"]"#, image));
+        }
 
-            if let Some(image) = opengraph_image {
-                body = Regex::new(r#"property="og:image" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("property=\"og:image\" content=\"{}\"", image))
-                    .to_string();
-                body = Regex::new(r#"name="twitter:image" content="[^"]*?""#)
-                    .unwrap()
-                    .replace(&body, format!("name=\"twitter:image\" content=\"{}\"", image))
-                    .to_string();
+        let settings = RewriteStrSettings {
+            element_content_handlers: handlers,
+            ..RewriteStrSettings::new()
+        };
+
+        match rewrite_str(&body, settings) {
+            Ok(rewritten) => rewritten,
+            Err(e) => {
+                warn!("Failed to inject OpenGraph tags: {}", e);
+                body
             }
+        }
+    }
+}
+
+fn set_meta_content<'h>(
+    selector: &'static str,
+    content: &'h str,
+) -> (Cow<'static, Selector>, ElementContentHandlers<'h>) {
+    element!(selector, move |el| {
+        el.set_attribute("content", content)?;
+        Ok(())
+    })
+}
+
+/// /t/:discourse_id/:topic_id
+async fn topic_tags(route: &str, state: &AppState) -> OpenGraphTags {
+    let split = route.split('/').collect::<Vec<&str>>();
+    let discourse_id = split.get(2).copied();
+    let topic_id = split.get(3).and_then(|topic_id| topic_id.parse::<i32>().ok());
+    info!("Topic ID: {:?}", topic_id);
+
+    let (Some(discourse_id), Some(topic_id)) = (discourse_id, topic_id) else {
+        return OpenGraphTags::default();
+    };
+    if QuarantinedTopic::is_quarantined(discourse_id, topic_id, state)
+        .await
+        .unwrap_or(true)
+    {
+        return OpenGraphTags::default();
+    }
+    let Ok(topic) = Topic::get_by_topic_id(discourse_id, topic_id, state).await else {
+        return OpenGraphTags::default();
+    };
 
-            response = Html(body).into_response();
+    let first_post = topic.get_first_post(state).await.ok();
+
+    info!("OpenGraph request to topic: {}", topic.title);
+    OpenGraphTags {
+        title: Some(topic.title),
+        description: first_post
+            .and_then(|post| post.cooked)
+            .map(|cooked| excerpt::excerpt(&cooked, excerpt::max_chars())),
+        image: topic.image_url,
+    }
+}
+
+/// /u/:discourse_id/:username
+async fn user_tags(route: &str, state: &AppState) -> OpenGraphTags {
+    let split = route.split('/').collect::<Vec<&str>>();
+    let (Some(discourse_id), Some(username)) = (split.get(2), split.get(3)) else {
+        return OpenGraphTags::default();
+    };
+
+    let profile = match state
+        .discourse
+        .fetch_discourse_user_cached(discourse_id, username)
+        .await
+    {
+        Ok(LResult::Success(profile)) => profile,
+        _ => return OpenGraphTags::default(),
+    };
+    let user = profile.user;
+
+    let title = match &user.name {
+        Some(name) if !name.is_empty() => format!("{} (@{})", name, user.username),
+        _ => format!("@{}", user.username),
+    };
+    let image = user.avatar_template.as_ref().map(|template| {
+        let avatar = template.replace("{size}", "240");
+        match state.discourse.get_discourse_url(discourse_id) {
+            Some(discourse_url) if avatar.starts_with('/') => format!("{}{}", discourse_url, avatar),
+            _ => avatar,
         }
+    });
+
+    OpenGraphTags {
+        title: Some(title),
+        description: user
+            .bio_raw
+            .filter(|bio| !bio.is_empty())
+            .map(|bio| excerpt::excerpt(&bio, excerpt::max_chars())),
+        image,
+    }
+}
+
+/// /pm/:issue_id
+async fn pm_tags(route: &str, state: &AppState) -> OpenGraphTags {
+    let Some(issue_number) = route
+        .split('/')
+        .nth(2)
+        .and_then(|issue_number| issue_number.parse::<u32>().ok())
+    else {
+        return OpenGraphTags::default();
+    };
+
+    let meeting = match state.pm.get_meetings_from_cache(state).await {
+        Ok(meetings) => meetings
+            .into_iter()
+            .find(|meeting| meeting.issue_number == Some(issue_number)),
+        Err(_) => None,
+    };
+    let Some(meeting) = meeting else {
+        return OpenGraphTags::default();
+    };
+
+    let title = meeting
+        .title
+        .unwrap_or_else(|| format!("ethereum/pm #{}", issue_number));
+    let description = match meeting.start {
+        Some(start) => format!(
+            "Protocol call on {} (ethereum/pm #{})",
+            start.format("%B %-d, %Y %H:%M UTC"),
+            issue_number
+        ),
+        None => format!("Protocol call (ethereum/pm #{})", issue_number),
+    };
+
+    OpenGraphTags {
+        title: Some(title),
+        description: Some(description),
+        image: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head><title>Ethereum Forum</title><meta property="og:title" content="Ethereum Forum" /><meta name="twitter:title" content="Ethereum Forum" /><meta property="og:image" content="/docs/opengraph.png" /></head><body></body></html>"#;
+
+    #[async_std::test]
+    async fn test_non_html_response_is_untouched() {
+        let json = r#"{"title":"Ethereum Forum"}"#;
+        let response = Response::builder()
+            .content_type("application/json; charset=utf-8")
+            .body(json);
+
+        let tags = OpenGraphTags {
+            title: Some("EIP-1559".to_string()),
+            description: Some("Fee market change".to_string()),
+            image: None,
+        };
+
+        let mut response = tags.apply(response).await;
+
+        assert_eq!(response.content_type(), Some("application/json; charset=utf-8"));
+        assert_eq!(response.take_body().into_string().await.unwrap(), json);
+    }
+
+    #[test]
+    fn test_inject_escapes_values() {
+        let tags = OpenGraphTags {
+            title: Some(r#"EIP-7702 "set code" for <EOAs>" onload="alert(1)"#.to_string()),
+            description: None,
+            image: Some("https://example.com/a.png?x=\"y".to_string()),
+        };
+
+        let body = tags.inject(PAGE.to_string());
 
-        Ok(response)
+        // quotes are what close an attribute, `<` is left as is inside one
+        assert!(!body.contains(r#"" onload=""#));
+        assert!(body.contains(
+            r#"<meta property="og:title" content="EIP-7702 &quot;set code&quot; for <EOAs>&quot; onload=&quot;alert(1)" />"#
+        ));
+        assert!(body.contains(r#"<meta name="twitter:title" content="EIP-7702 &quot;set code&quot;"#));
+        assert!(body.contains("<title>EIP-7702 \"set code\" for &lt;EOAs&gt;"));
+        assert!(body.contains(r#"content="https://example.com/a.png?x=&quot;y""#));
     }
 }
@@ -1,19 +1,66 @@
+use chrono::Utc;
 use poem::{Result, web::Data};
 use poem_openapi::param::Path;
 use poem_openapi::{Object, OpenApi, payload::Json};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{error, info};
 
-use crate::models::pm::PMMeetingData;
+use crate::models::pm::{PMMeeting, PMMeetingData};
+use crate::models::topics::Topic;
+use crate::modules::entities;
 use crate::server::ApiTags;
+use crate::server::topic::without_quarantined;
 use crate::state::AppState;
 
+const MEETINGS_LIMIT: usize = 32;
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct PMApi;
 
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PMMeetingsResponse {
+    /// Calls that haven't started yet, soonest first
+    pub upcoming: Vec<PMMeeting>,
+    /// Calls that already started, most recent first
+    pub past: Vec<PMMeeting>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PMIssueTopicsResponse {
+    pub issue_number: u32,
+    /// Meeting the issue belongs to, from the ethereum/pm meeting mapping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meeting: Option<PMMeetingData>,
+    /// Forum topics linking to the issue
+    pub topics: Vec<Topic>,
+}
+
 #[OpenApi]
 impl PMApi {
+    /// /pm/meetings
+    ///
+    /// List upcoming and past protocol calls with their linked ethereum/pm issues and forum topics
+    #[oai(path = "/pm/meetings", method = "get", tag = "ApiTags::Events")]
+    async fn meetings(&self, state: Data<&AppState>) -> Result<Json<PMMeetingsResponse>> {
+        let meetings = state
+            .pm
+            .get_meetings_from_cache(&state)
+            .await
+            .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::BAD_GATEWAY))?;
+
+        let now = Utc::now();
+        let (upcoming, past): (Vec<_>, Vec<_>) = meetings
+            .into_iter()
+            .filter(|meeting| meeting.start.is_some())
+            .partition(|meeting| meeting.start.is_some_and(|start| start >= now));
+
+        Ok(Json(PMMeetingsResponse {
+            upcoming: upcoming.into_iter().take(MEETINGS_LIMIT).collect(),
+            past: past.into_iter().rev().take(MEETINGS_LIMIT).collect(),
+        }))
+    }
+
     /// /pm
     ///
     /// Get PM data
@@ -32,4 +79,55 @@ impl PMApi {
         info!("PM data: {:?}", pm);
         Ok(Json(pm))
     }
+
+    /// /pm/:issue_id/topics
+    ///
+    /// Forum topics linked to a PM issue, alongside the meeting the issue belongs to
+    #[oai(path = "/pm/:issue_id/topics", method = "get", tag = "ApiTags::Events")]
+    async fn get_topics_by_issue(
+        &self,
+        state: Data<&AppState>,
+        #[oai(style = "simple")] issue_id: Path<u32>,
+    ) -> Result<Json<PMIssueTopicsResponse>> {
+        let issue_number = i32::try_from(issue_id.0)
+            .map_err(|_| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        let topics = Topic::get_by_pm_issue(issue_number, &state)
+            .await
+            .map_err(|e| {
+                error!("Error getting topics for PM issue {}: {:?}", issue_id.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+        let topics = without_quarantined(topics, &state).await?;
+
+        let meeting = state.pm.get_by_issue_id(issue_id.0).await.ok();
+
+        if topics.is_empty() && meeting.is_none() {
+            return Err(poem::Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        Ok(Json(PMIssueTopicsResponse {
+            issue_number: issue_id.0,
+            meeting,
+            topics,
+        }))
+    }
+
+    /// /entities/eip/:number/issues
+    ///
+    /// Protocol calls whose ethereum/pm issue title mentions an EIP, most recent first
+    #[oai(path = "/entities/eip/:number/issues", method = "get", tag = "ApiTags::Events")]
+    async fn get_eip_issues(
+        &self,
+        state: Data<&AppState>,
+        #[oai(style = "simple")] number: Path<u32>,
+    ) -> Result<Json<Vec<PMMeeting>>> {
+        let meetings = state
+            .pm
+            .meetings_mentioning(entities::EIP, &number.0.to_string(), &state)
+            .await
+            .map_err(|e| poem::Error::from_string(e.to_string(), StatusCode::BAD_GATEWAY))?;
+
+        Ok(Json(meetings))
+    }
 }
@@ -0,0 +1,96 @@
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, http::HeaderValue};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest client supplied request id we accept, longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of the current request, available to handlers as `Data<&RequestId>` and included in the
+/// body of server errors so users can report them
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Reuse the client's id when it is sane, otherwise generate one
+    fn from_header(value: Option<&str>) -> Self {
+        match value {
+            Some(id)
+                if !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LEN
+                    && id.bytes().all(|b| b.is_ascii_graphic()) =>
+            {
+                Self(id.to_string())
+            }
+            _ => Self(Uuid::new_v4().to_string()),
+        }
+    }
+}
+
+/// Tags every request with an id, logs it in the request span and echoes it in the response
+pub struct RequestIdMiddleware;
+
+impl<E: Endpoint> Middleware<E> for RequestIdMiddleware {
+    type Output = RequestIdMiddlewareImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequestIdMiddlewareImpl { ep }
+    }
+}
+
+pub struct RequestIdMiddlewareImpl<E> {
+    ep: E,
+}
+
+impl<E: Endpoint> Endpoint for RequestIdMiddlewareImpl<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> poem::Result<Self::Output> {
+        let request_id = RequestId::from_header(
+            req.headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        );
+        req.extensions_mut().insert(request_id.clone());
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id.0,
+            method = %req.method(),
+            path = %req.uri().path(),
+        );
+
+        let mut response = match self.ep.call(req).instrument(span).await {
+            Ok(response) => response.into_response(),
+            Err(e) if e.status().is_server_error() => Response::builder()
+                .status(e.status())
+                .body(format!("{} (request id {})", e, request_id.0)),
+            Err(e) => e.into_response(),
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_from_header() {
+        assert_eq!(RequestId::from_header(Some("abc-123")).0, "abc-123");
+
+        let generated = RequestId::from_header(Some("has spaces"));
+        assert!(Uuid::parse_str(&generated.0).is_ok());
+
+        let generated = RequestId::from_header(Some(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+        assert!(Uuid::parse_str(&generated.0).is_ok());
+
+        assert!(Uuid::parse_str(&RequestId::from_header(None).0).is_ok());
+    }
+}
@@ -1,10 +1,17 @@
+use meilisearch_sdk::search::Selectors;
 use poem::{web::Data, Result};
-use poem_openapi::{payload::Json, Object, OpenApi};
+use poem_openapi::{param::Query, payload::Json, Object, OpenApi};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use super::ApiTags;
 
 use crate::state::AppState;
 
+/// Upper bound on suggestions returned for a single keystroke
+const MAX_SUGGESTIONS: usize = 10;
+/// Queries longer than this are truncated before hitting Meilisearch
+const MAX_SUGGEST_QUERY_CHARS: usize = 100;
+
 pub struct SearchApi;
 
 #[derive(Clone, Serialize, Deserialize, Object)]
@@ -12,6 +19,14 @@ pub struct SearchResponse {
 
 }
 
+#[derive(Clone, Serialize, Deserialize, Object)]
+pub struct SearchSuggestion {
+    pub discourse_id: Option<String>,
+    pub topic_id: Option<i32>,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+}
+
 #[OpenApi]
 impl SearchApi {
 
@@ -25,4 +40,48 @@ impl SearchApi {
     ) -> Result<Json<SearchResponse>> {
         todo!()
     }
+
+    /// /search/suggest
+    ///
+    /// Lightweight typeahead suggestions, matching topic titles only
+    #[oai(path = "/search/suggest", method = "get", tag = "ApiTags::Search")]
+    async fn suggest(
+        &self,
+        state: Data<&AppState>,
+        q: Query<String>,
+        limit: Query<Option<usize>>,
+    ) -> Result<Json<Vec<SearchSuggestion>>> {
+        let Some(meili) = &state.meili else {
+            return Ok(Json(vec![]));
+        };
+
+        let query: String = q.0.trim().chars().take(MAX_SUGGEST_QUERY_CHARS).collect();
+        if query.is_empty() {
+            return Ok(Json(vec![]));
+        }
+
+        let limit = limit.0.unwrap_or(5).clamp(1, MAX_SUGGESTIONS);
+
+        let results = meili
+            .index("forum")
+            .search()
+            .with_query(&query)
+            .with_filter("entity_type = topic")
+            .with_attributes_to_search_on(&["title"])
+            .with_attributes_to_retrieve(Selectors::Some(&[
+                "discourse_id",
+                "topic_id",
+                "title",
+                "slug",
+            ]))
+            .with_limit(limit)
+            .execute::<SearchSuggestion>()
+            .await
+            .map_err(|e| {
+                tracing::error!("Error fetching search suggestions: {:?}", e);
+                poem::Error::from_status(StatusCode::BAD_GATEWAY)
+            })?;
+
+        Ok(Json(results.hits.into_iter().map(|hit| hit.result).collect()))
+    }
 }
@@ -1,12 +1,29 @@
+use chrono::Utc;
+use futures::{StreamExt, stream, stream::BoxStream};
+use meilisearch_sdk::search::Selectors;
 use poem::{Result, web::Data};
-use poem_openapi::param::{Path, Query};
-use poem_openapi::{Object, OpenApi, payload::Json};
+use regex::RegexBuilder;
+use poem_openapi::param::{Header, Path, Query};
+use poem_openapi::payload::{EventStream, Json};
+use poem_openapi::{ApiResponse, Object, OpenApi};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::models::topics::{post::Post, Topic, TopicSummary};
+use crate::database::{Paginated, page_offset};
+use crate::models::topics::changes::{ChangesCursor, TopicChanges};
+use crate::models::topics::diff::{DiffGranularity, SummaryDiff};
+use crate::models::topics::entity::TopicEntity;
+use crate::models::topics::quarantine::QuarantinedTopic;
+use crate::models::topics::redirect::TopicRedirect;
+use crate::models::topics::{
+    post::Post, Topic, TopicSort, TopicSummary, TopicSummaryStatus, TopicSummaryVersion, TrendingWindow,
+};
+use crate::modules::workshop::WorkshopService;
+use crate::modules::workshop::prompts::StreamingEntryType;
+use crate::modules::{entities, excerpt};
 use crate::server::ApiTags;
+use crate::server::discourse_id::DiscourseId;
 use crate::state::AppState;
 
 #[derive(Debug, Serialize, Deserialize, Object)]
@@ -16,16 +33,288 @@ pub struct TopicApi;
 pub struct PostsResponse {
     pub posts: Vec<Post>,
     pub has_more: bool,
+    /// Number of posts in the topic
+    pub total: i64,
+}
+
+/// Topics per page of the topic listings
+const TOPICS_PAGE_SIZE: i64 = 20;
+
+/// Maximum number of topics, posts and deletions each returned by one changes request
+const MAX_CHANGES: usize = 500;
+
+/// Maximum number of posts returned by an in-topic search
+const MAX_TOPIC_SEARCH_RESULTS: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicSearchHit {
+    pub post_id: i32,
+    pub post_number: Option<i32>,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    pub cooked: Option<String>,
+    /// Excerpt of the post with matches wrapped in `<em>`
+    pub highlight: Option<String>,
+    /// Plaintext preview of the post
+    pub excerpt: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopicSearchDocument {
+    post_id: i32,
+    post_number: Option<i32>,
+    user_id: Option<i32>,
+    username: Option<String>,
+    cooked: Option<String>,
+}
+
+/// Quarantined topics are gone for good as far as clients are concerned
+pub(crate) async fn ensure_not_quarantined(discourse_id: &str, topic_id: i32, state: &AppState) -> Result<()> {
+    let quarantined = QuarantinedTopic::is_quarantined(discourse_id, topic_id, state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error checking topic quarantine: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+    if quarantined {
+        return Err(poem::Error::from_status(StatusCode::GONE));
+    }
+
+    Ok(())
+}
+
+/// Drop quarantined topics from a listing
+pub(crate) async fn without_quarantined(topics: Vec<Topic>, state: &AppState) -> Result<Vec<Topic>> {
+    let quarantined = QuarantinedTopic::keys(state).await.map_err(|e| {
+        tracing::error!("Error getting quarantined topics: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    Ok(topics
+        .into_iter()
+        .filter(|topic| !quarantined.contains(&(topic.discourse_id.clone(), topic.topic_id)))
+        .collect())
+}
+
+/// A past summary of the topic, 404 when it doesn't exist
+async fn find_summary_version(
+    discourse_id: &str,
+    topic_id: i32,
+    version_id: i64,
+    state: &AppState,
+) -> Result<TopicSummaryVersion> {
+    TopicSummaryVersion::get(discourse_id, topic_id, version_id, state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting topic summary version: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?
+        .ok_or_else(|| {
+            poem::Error::from_string(
+                format!("Summary version {} not found", version_id),
+                StatusCode::NOT_FOUND,
+            )
+        })
+}
+
+/// Whether the latest summary covers the last post, errors count as current and are left to
+/// the summary lookup
+async fn summary_is_current(topic: &Topic, based_on: i64, state: &AppState) -> bool {
+    match TopicSummary::latest(&topic.discourse_id, topic.topic_id, state).await {
+        Ok(summary) => summary.is_some_and(|summary| summary.based_on.timestamp() == based_on),
+        Err(e) => {
+            tracing::error!("Error getting topic summary: {:?}", e);
+            true
+        }
+    }
+}
+
+/// How often and how many times to look for a summary the generating task is about to store
+const SUMMARY_SAVE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+const SUMMARY_SAVE_POLLS: usize = 60;
+
+/// The summary a finished generation stored, it is saved a moment after the prompt completes
+/// so the previous summary may still be the latest at first
+async fn wait_for_stored_summary(topic: &Topic, based_on: i64, state: &AppState) -> Option<TopicSummary> {
+    for _ in 0..SUMMARY_SAVE_POLLS {
+        match TopicSummary::latest(&topic.discourse_id, topic.topic_id, state).await {
+            Ok(Some(summary)) if summary.based_on.timestamp() >= based_on => return Some(summary),
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Error getting topic summary: {:?}", e);
+                return None;
+            }
+        }
+        async_std::task::sleep(SUMMARY_SAVE_POLL_INTERVAL).await;
+    }
+
+    tracing::warn!(
+        "Summary of topic {} on {} was not stored after its generation finished",
+        topic.topic_id,
+        topic.discourse_id
+    );
+    None
+}
+
+/// Excerpt of `text` around the first case-insensitive match of `query`, with the match wrapped in `<em>`
+fn highlight_excerpt(text: &str, query: &str, context_chars: usize) -> Option<String> {
+    if query.is_empty() {
+        return None;
+    }
+
+    // matched on the original text, so the offsets are always char boundaries of it
+    let pattern = RegexBuilder::new(&regex::escape(query))
+        .case_insensitive(true)
+        .build()
+        .ok()?;
+    let found = pattern.find(text)?;
+    let (start, end) = (found.start(), found.end());
+
+    let before_chars = text[..start].chars().count();
+    let before: String = text[..start]
+        .chars()
+        .skip(before_chars.saturating_sub(context_chars))
+        .collect();
+    let after: String = text[end..].chars().take(context_chars).collect();
+
+    Some(format!(
+        "…{}<em>{}</em>{}…",
+        escape_html(&before),
+        escape_html(&text[start..end]),
+        escape_html(&after)
+    ))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Maximum number of topics that can be requested in a single batch
+const MAX_BATCH_SIZE: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicRef {
+    pub discourse_id: String,
+    pub topic_id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicsBatchRequest {
+    pub topics: Vec<TopicRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicBatchEntry {
+    pub discourse_id: String,
+    pub topic_id: i32,
+    pub found: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<Topic>,
+}
+
+/// One event of a summary stream, the last one has `is_complete` set
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct SummaryStreamEvent {
+    /// Text generated since the previous event
+    pub content: String,
+    pub is_complete: bool,
+    /// The stored summary, only on the last event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<TopicSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(ApiResponse)]
+pub enum TopicResponse {
+    #[oai(status = 200)]
+    Ok(Json<Topic>, #[oai(header = "ETag")] String),
+    #[oai(status = 304)]
+    NotModified,
+    /// The topic was merged or moved on Discourse, `Location` points at its new topic
+    #[oai(status = 301)]
+    Moved(#[oai(header = "Location")] String),
+}
+
+#[derive(ApiResponse)]
+pub enum PostsPageResponse {
+    #[oai(status = 200)]
+    Ok(Json<PostsResponse>, #[oai(header = "ETag")] String),
+    #[oai(status = 304)]
+    NotModified,
+}
+
+/// Weak ETag for a topic, changes whenever the topic receives new activity
+fn topic_etag(topic: &Topic) -> String {
+    format!(
+        "W/\"t-{}-{}-{}-{}\"",
+        topic.topic_id,
+        topic.post_count,
+        topic.bumped_at.map(|dt| dt.timestamp()).unwrap_or_default(),
+        topic.last_post_at.map(|dt| dt.timestamp()).unwrap_or_default(),
+    )
+}
+
+/// Weak ETag for a page of posts, based on the most recent edit within the page
+fn posts_etag(topic_id: i32, page: i32, posts: &[Post], has_more: bool) -> String {
+    let last_updated = posts
+        .iter()
+        .filter_map(|post| post.updated_at)
+        .max()
+        .map(|dt| dt.timestamp())
+        .unwrap_or_default();
+
+    format!(
+        "W/\"p-{}-{}-{}-{}-{}\"",
+        topic_id,
+        page,
+        posts.len(),
+        last_updated,
+        has_more as u8,
+    )
+}
+
+/// Weak comparison of an If-None-Match header against an ETag
+pub fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let Some(if_none_match) = if_none_match else {
+        return false;
+    };
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip_weak(etag);
+
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || strip_weak(tag) == etag)
 }
 
 #[OpenApi]
 impl TopicApi {
     /// /topics
     ///
-    /// List topics by latest activity
+    /// List topics, by latest activity unless ?sort=created|views|posts is given
+    /// Topics with fewer than ?min_posts posts are left out
+    /// This endpoint is paginated, and uses ?page=1 as the first page
     #[oai(path = "/topics", method = "get", tag = "ApiTags::Topic")]
-    async fn list(&self, state: Data<&AppState>) -> Result<Json<Vec<Topic>>> {
-        let topics = Topic::get_by_latest_post_at(&state).await.map_err(|e| {
+    async fn list(
+        &self,
+        state: Data<&AppState>,
+        page: Query<Option<i64>>,
+        sort: Query<Option<TopicSort>>,
+        min_posts: Query<Option<i32>>,
+    ) -> Result<Json<Paginated<Topic>>> {
+        let topics = Topic::list(
+            None,
+            sort.0.unwrap_or_default(),
+            min_posts.0.unwrap_or(0),
+            TOPICS_PAGE_SIZE,
+            page_offset(page.0.unwrap_or(1), TOPICS_PAGE_SIZE),
+            &state,
+        )
+        .await
+        .map_err(|e| {
             tracing::error!("Error getting topics: {:?}", e);
             poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
         })?;
@@ -35,17 +324,184 @@ impl TopicApi {
 
     /// /topics/trending
     ///
-    /// List trending topics
+    /// List trending topics, by activity in the last ?window=24h|7d|14d|30d (14d by default)
     #[oai(path = "/topics/trending", method = "get", tag = "ApiTags::Topic")]
-    async fn trending(&self, state: Data<&AppState>) -> Result<Json<Vec<Topic>>> {
-        let topics = Topic::get_by_trending(&state).await.map_err(|e| {
+    async fn trending(
+        &self,
+        state: Data<&AppState>,
+        window: Query<Option<TrendingWindow>>,
+    ) -> Result<Json<Vec<Topic>>> {
+        let window = window.0.unwrap_or_default();
+        let topics = Topic::get_by_trending(window, &state).await.map_err(|e| {
             tracing::error!("Error getting trending topics: {:?}", e);
             poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
         })?;
 
+        Ok(Json(without_quarantined(topics, &state).await?))
+    }
+
+    /// /d/:discourse_id/topics
+    ///
+    /// List topics of a single instance, sorted and filtered like /topics
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(path = "/d/:discourse_id/topics", method = "get", tag = "ApiTags::Topic")]
+    async fn list_instance(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        page: Query<Option<i64>>,
+        sort: Query<Option<TopicSort>>,
+        min_posts: Query<Option<i32>>,
+    ) -> Result<Json<Paginated<Topic>>> {
+        let topics = Topic::list(
+            Some(&discourse_id),
+            sort.0.unwrap_or_default(),
+            min_posts.0.unwrap_or(0),
+            TOPICS_PAGE_SIZE,
+            page_offset(page.0.unwrap_or(1), TOPICS_PAGE_SIZE),
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting topics of {}: {:?}", discourse_id.0, e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
         Ok(Json(topics))
     }
 
+    /// /d/:discourse_id/topics/trending
+    ///
+    /// List trending topics of a single instance, by activity in the last ?window=24h|7d|14d|30d
+    #[oai(path = "/d/:discourse_id/topics/trending", method = "get", tag = "ApiTags::Topic")]
+    async fn trending_instance(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        window: Query<Option<TrendingWindow>>,
+    ) -> Result<Json<Vec<Topic>>> {
+        let topics = Topic::get_by_trending_in(&discourse_id, window.0.unwrap_or_default(), &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting trending topics of {}: {:?}", discourse_id.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(without_quarantined(topics, &state).await?))
+    }
+
+    /// /topics/changes
+    ///
+    /// Get topics (and with ?include_posts=true posts) written here after `since`, plus topics to drop
+    /// Start with any old RFC 3339 timestamp and keep passing the returned `next_since`,
+    /// unquarantined topics come back as changes
+    #[oai(path = "/topics/changes", method = "get", tag = "ApiTags::Topic")]
+    async fn changes(
+        &self,
+        state: Data<&AppState>,
+        since: Query<String>,
+        include_posts: Query<Option<bool>>,
+        limit: Query<Option<usize>>,
+    ) -> Result<Json<TopicChanges>> {
+        let limit = limit.0.unwrap_or(100).clamp(1, MAX_CHANGES);
+        let cursor = ChangesCursor::parse(&since.0)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::BAD_REQUEST))?;
+
+        let changes = TopicChanges::since(cursor, include_posts.0.unwrap_or(false), limit, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting topic changes: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(changes))
+    }
+
+    /// /topics/batch
+    ///
+    /// Get multiple topics in one request
+    /// Topics that could not be found are returned with `found: false`
+    #[oai(path = "/topics/batch", method = "post", tag = "ApiTags::Topic")]
+    async fn batch(
+        &self,
+        state: Data<&AppState>,
+        request: Json<TopicsBatchRequest>,
+    ) -> Result<Json<Vec<TopicBatchEntry>>> {
+        let requested = request.0.topics;
+
+        if requested.len() > MAX_BATCH_SIZE {
+            return Err(poem::Error::from_string(
+                format!("At most {} topics can be requested at once", MAX_BATCH_SIZE),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let ids: Vec<(String, i32)> = requested
+            .iter()
+            .map(|topic| (topic.discourse_id.clone(), topic.topic_id))
+            .collect();
+
+        let topics = Topic::get_by_topic_ids(&ids, &state).await.map_err(|e| {
+            tracing::error!("Error getting topics batch: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+        let topics = without_quarantined(topics, &state).await?;
+
+        let entries = requested
+            .into_iter()
+            .map(|requested| {
+                let topic = topics
+                    .iter()
+                    .find(|topic| {
+                        topic.discourse_id == requested.discourse_id
+                            && topic.topic_id == requested.topic_id
+                    })
+                    .cloned();
+
+                TopicBatchEntry {
+                    discourse_id: requested.discourse_id,
+                    topic_id: requested.topic_id,
+                    found: topic.is_some(),
+                    topic,
+                }
+            })
+            .collect();
+
+        Ok(Json(entries))
+    }
+
+    /// /topics/summaries/status
+    ///
+    /// Whether each topic has a summary, if it is stale and when it was generated
+    /// Meant for summary badges on topic lists, the summaries themselves aren't included
+    #[oai(path = "/topics/summaries/status", method = "post", tag = "ApiTags::Topic")]
+    async fn summary_statuses(
+        &self,
+        state: Data<&AppState>,
+        request: Json<TopicsBatchRequest>,
+    ) -> Result<Json<Vec<TopicSummaryStatus>>> {
+        let requested = request.0.topics;
+
+        if requested.len() > MAX_BATCH_SIZE {
+            return Err(poem::Error::from_string(
+                format!("At most {} topics can be requested at once", MAX_BATCH_SIZE),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let ids: Vec<(String, i32)> = requested
+            .into_iter()
+            .map(|topic| (topic.discourse_id, topic.topic_id))
+            .collect();
+
+        let statuses = TopicSummary::statuses(&ids, &state).await.map_err(|e| {
+            tracing::error!("Error getting summary statuses: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(statuses))
+    }
+
     /// /t/:discourse_id/:topic_id
     ///
     /// Get information about a topic
@@ -58,18 +514,43 @@ impl TopicApi {
     async fn get_topic(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
-    ) -> Result<Json<Topic>> {
+        #[oai(name = "If-None-Match")] if_none_match: Header<Option<String>>,
+    ) -> Result<TopicResponse> {
         let discourse_id = discourse_id.0;
-        let topic = Topic::get_by_topic_id(&discourse_id, topic_id.0, &state)
-            .await
-            .map_err(|e| {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let topic = match Topic::get_by_topic_id(&discourse_id, topic_id.0, &state).await {
+            Ok(topic) => topic,
+            Err(sqlx::Error::RowNotFound) => {
+                let moved_to = TopicRedirect::moved_to(&discourse_id, topic_id.0, &state)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Error getting topic redirect: {:?}", e);
+                        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+                    })?;
+
+                return match moved_to {
+                    Some(moved_to) => Ok(TopicResponse::Moved(format!(
+                        "/api/t/{}/{}",
+                        discourse_id.0, moved_to
+                    ))),
+                    None => Err(poem::Error::from_status(StatusCode::NOT_FOUND)),
+                };
+            }
+            Err(e) => {
                 tracing::error!("Error getting topic: {:?}", e);
-                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-            })?;
+                return Err(poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+        };
+
+        let etag = topic_etag(&topic);
+        if etag_matches(if_none_match.0.as_deref(), &etag) {
+            return Ok(TopicResponse::NotModified);
+        }
 
-        Ok(Json(topic))
+        Ok(TopicResponse::Ok(Json(topic), etag))
     }
 
     /// /t/:discourse_id/:topic_id
@@ -84,9 +565,11 @@ impl TopicApi {
     async fn refresh_topic(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
     ) -> Result<Json<serde_json::Value>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
         info!("Refreshing topic: {} on {}", topic_id.0, discourse_id.0);
         state.discourse.enqueue(&discourse_id, topic_id.0, 1).await;
 
@@ -106,23 +589,194 @@ impl TopicApi {
     async fn get_posts(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
         #[oai(style = "simple")] page: Query<i32>,
         #[oai(style = "simple")] size: Query<Option<i32>>,
-    ) -> Result<Json<PostsResponse>> {
+        #[oai(name = "If-None-Match")] if_none_match: Header<Option<String>>,
+    ) -> Result<PostsPageResponse> {
         let discourse_id = discourse_id.0;
         let topic_id = topic_id.0;
         let page = page.0;
+        ensure_not_quarantined(&discourse_id, topic_id, &state).await?;
 
-        let (posts, has_more) = Post::find_by_topic_id(&discourse_id, topic_id, page, size.0, &state)
+        let posts = Post::find_by_topic_id(&discourse_id, topic_id, page, size.0, &state)
             .await
             .map_err(|e| {
                 tracing::error!("Error finding posts: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
-        Ok(Json(PostsResponse { posts, has_more }))
+        let etag = posts_etag(topic_id, page, &posts.items, posts.has_more);
+        if etag_matches(if_none_match.0.as_deref(), &etag) {
+            return Ok(PostsPageResponse::NotModified);
+        }
+
+        Ok(PostsPageResponse::Ok(
+            Json(PostsResponse {
+                posts: posts.items,
+                has_more: posts.has_more,
+                total: posts.total,
+            }),
+            etag,
+        ))
+    }
+
+    /// /t/:discourse_id/:topic_id/p/:post_number
+    ///
+    /// Get a post by its number within the topic, as used in Discourse links
+    #[oai(
+        path = "/t/:discourse_id/:topic_id/p/:post_number",
+        method = "get",
+        operation_id = "get_post_by_number",
+        tag = "ApiTags::Topic"
+    )]
+    async fn get_post_by_number(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        #[oai(style = "simple")] post_number: Path<i32>,
+    ) -> Result<Json<Post>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let post = Post::get_by_post_number(&discourse_id.0, topic_id.0, post_number.0, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting post by number: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(post))
+    }
+
+    /// /t/:discourse_id/:topic_id/search
+    ///
+    /// Search the posts of a topic
+    /// Uses Meilisearch when configured, falling back to a plain database search otherwise
+    #[oai(
+        path = "/t/:discourse_id/:topic_id/search",
+        method = "get",
+        operation_id = "search_posts_in_topic",
+        tag = "ApiTags::Topic"
+    )]
+    async fn search_posts_in_topic(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        q: Query<String>,
+        limit: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> Result<Json<Vec<TopicSearchHit>>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let query = q.0.trim().to_string();
+        if query.is_empty() {
+            return Ok(Json(vec![]));
+        }
+
+        let limit = limit.0.unwrap_or(20).clamp(1, MAX_TOPIC_SEARCH_RESULTS);
+        let offset = offset.0.unwrap_or(0);
+
+        if let Some(meili) = &state.meili {
+            let filter = format!(
+                "entity_type = post AND discourse_id = \"{}\" AND topic_id = {}",
+                discourse_id.0, topic_id.0
+            );
+
+            let results = meili
+                .index("forum")
+                .search()
+                .with_query(&query)
+                .with_filter(&filter)
+                .with_attributes_to_highlight(Selectors::Some(&["cooked"]))
+                .with_attributes_to_crop(Selectors::Some(&[("cooked", None)]))
+                .with_crop_length(40)
+                .with_highlight_pre_tag("<em>")
+                .with_highlight_post_tag("</em>")
+                .with_limit(limit)
+                .with_offset(offset)
+                .execute::<TopicSearchDocument>()
+                .await;
+
+            match results {
+                Ok(results) => {
+                    let hits = results
+                        .hits
+                        .into_iter()
+                        .map(|hit| {
+                            let highlight = hit
+                                .formatted_result
+                                .as_ref()
+                                .and_then(|formatted| formatted.get("cooked"))
+                                .and_then(|cooked| cooked.as_str())
+                                .map(String::from);
+
+                            TopicSearchHit {
+                                post_id: hit.result.post_id,
+                                post_number: hit.result.post_number,
+                                user_id: hit.result.user_id,
+                                username: hit.result.username,
+                                excerpt: hit
+                                    .result
+                                    .cooked
+                                    .as_deref()
+                                    .map(|cooked| excerpt::excerpt(cooked, excerpt::max_chars())),
+                                cooked: hit.result.cooked,
+                                highlight,
+                            }
+                        })
+                        .collect();
+
+                    return Ok(Json(hits));
+                }
+                Err(e) => {
+                    tracing::warn!("Meilisearch topic search failed, falling back to database: {:?}", e);
+                }
+            }
+        }
+
+        let posts = Post::search_in_topic(
+            &discourse_id,
+            topic_id.0,
+            &query,
+            limit as i64,
+            offset as i64,
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error searching posts: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        let hits = posts
+            .into_iter()
+            .map(|post| TopicSearchHit {
+                post_id: post.post_id,
+                post_number: Some(post.post_number),
+                user_id: Some(post.user_id),
+                username: post
+                    .extra
+                    .as_ref()
+                    .and_then(|extra| extra.get("username"))
+                    .and_then(|username| username.as_str())
+                    .map(String::from),
+                highlight: post
+                    .cooked
+                    .as_deref()
+                    .and_then(|cooked| highlight_excerpt(&excerpt::plaintext(cooked), &query, 120)),
+                excerpt: post
+                    .cooked
+                    .as_deref()
+                    .map(|cooked| excerpt::excerpt(cooked, excerpt::max_chars())),
+                cooked: post.cooked,
+            })
+            .collect();
+
+        Ok(Json(hits))
     }
 
     /// /t/:discourse_id/:topic_id/summary
@@ -137,10 +791,11 @@ impl TopicApi {
     async fn get_summary(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
     ) -> Result<Json<TopicSummary>> {
         let topic_id = topic_id.0;
+        ensure_not_quarantined(&discourse_id, topic_id, &state).await?;
 
         let summary = Topic::get_summary_by_topic_id(&discourse_id, topic_id, &state)
             .await
@@ -151,4 +806,226 @@ impl TopicApi {
 
         Ok(Json(summary))
     }
+
+    /// /t/:discourse_id/:topic_id/summary/stream
+    ///
+    /// SSE variant of the summary endpoint
+    /// While a summary is being generated its tokens are streamed as they arrive, the last event
+    /// carries the stored summary. A missing or outdated summary is generated and streamed the
+    /// same way, a fresh summary is sent right away as the only event.
+    #[oai(
+        path = "/t/:discourse_id/:topic_id/summary/stream",
+        method = "get",
+        operation_id = "stream_summary",
+        tag = "ApiTags::Topic"
+    )]
+    async fn stream_summary(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+    ) -> Result<EventStream<BoxStream<'static, SummaryStreamEvent>>> {
+        let discourse_id = discourse_id.0;
+        let topic_id = topic_id.0;
+        ensure_not_quarantined(&discourse_id, topic_id, &state).await?;
+
+        let topic = Topic::get_by_topic_id(&discourse_id, topic_id, &state)
+            .await
+            .map_err(|_| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+        // what the generated summary will be based on, generation uses the time it is saved
+        // for topics without a last post
+        let based_on = topic
+            .last_post_at
+            .map_or_else(|| Utc::now().timestamp(), |at| at.timestamp());
+
+        let state = state.0.clone();
+        // finished prompts stay registered, only a running one has tokens left to stream
+        let ongoing = match state
+            .workshop
+            .get_ongoing_summary_prompt(&discourse_id, topic_id)
+            .await
+        {
+            Some(prompt) if !prompt.is_complete().await => Some(prompt),
+            _ if summary_is_current(&topic, based_on, &state).await => None,
+            // like the workshop summary stream, under the same key so other readers join it
+            _ => match WorkshopService::create_workshop_summary_streaming(&topic, &state).await {
+                Ok(prompt) => {
+                    WorkshopService::store_summary_when_complete(
+                        topic.clone(),
+                        prompt.clone(),
+                        state.clone(),
+                    );
+                    Some(prompt)
+                }
+                Err(e) => {
+                    tracing::error!("Error starting summary generation: {:?}", e);
+                    None
+                }
+            },
+        };
+
+        let tokens = match &ongoing {
+            Some(prompt) => prompt
+                .get_stream()
+                .await
+                .filter_map(|result| async move {
+                    match result {
+                        Ok(entry) if entry.entry_type == StreamingEntryType::Content => {
+                            Some(SummaryStreamEvent {
+                                content: entry.content,
+                                is_complete: false,
+                                summary: None,
+                                error: None,
+                            })
+                        }
+                        Ok(_) => None,
+                        // the last event falls back to generating without streaming
+                        Err(e) => {
+                            tracing::warn!("Summary stream error: {}", e);
+                            None
+                        }
+                    }
+                })
+                .boxed(),
+            None => stream::empty().boxed(),
+        };
+
+        // a fresh summary is returned as is, when streaming couldn't start a missing or outdated
+        // one is generated here and only sent once done
+        let done = stream::once(async move {
+            let summary = match ongoing {
+                Some(prompt) => {
+                    let _ = prompt.await_completion().await;
+                    wait_for_stored_summary(&topic, based_on, &state).await
+                }
+                None => Topic::get_summary_by_topic_id(&discourse_id, topic_id, &state)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Error getting topic summary: {:?}", e);
+                    })
+                    .ok(),
+            };
+
+            SummaryStreamEvent {
+                content: String::new(),
+                is_complete: true,
+                error: summary
+                    .is_none()
+                    .then(|| "Error generating summary".to_string()),
+                summary,
+            }
+        });
+
+        Ok(EventStream::new(tokens.chain(done).boxed()))
+    }
+
+    /// /t/:discourse_id/:topic_id/summary/history
+    ///
+    /// Get past summaries of a topic, newest first
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(
+        path = "/t/:discourse_id/:topic_id/summary/history",
+        method = "get",
+        operation_id = "get_summary_history",
+        tag = "ApiTags::Topic"
+    )]
+    async fn get_summary_history(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<Vec<TopicSummaryVersion>>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(20).clamp(1, 100);
+
+        let versions = TopicSummaryVersion::list(&discourse_id, topic_id.0, page, size, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting topic summary history: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(versions))
+    }
+
+    /// /t/:discourse_id/:topic_id/summary/diff
+    ///
+    /// Compare two past summaries of a topic, by `version_id` from the summary history
+    /// Returns hunks of inserted, deleted and unchanged lines (or sentences with ?granularity=sentence)
+    #[oai(
+        path = "/t/:discourse_id/:topic_id/summary/diff",
+        method = "get",
+        operation_id = "get_summary_diff",
+        tag = "ApiTags::Topic"
+    )]
+    async fn get_summary_diff(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        from: Query<i64>,
+        to: Query<i64>,
+        granularity: Query<Option<DiffGranularity>>,
+    ) -> Result<Json<SummaryDiff>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let from = find_summary_version(&discourse_id, topic_id.0, from.0, &state).await?;
+        let to = find_summary_version(&discourse_id, topic_id.0, to.0, &state).await?;
+
+        Ok(Json(SummaryDiff::new(
+            from,
+            to,
+            granularity.0.unwrap_or_default(),
+        )))
+    }
+
+    /// /entities/eip/:number/topics
+    ///
+    /// Get topics with a post mentioning an EIP, most recently active first
+    /// This endpoint is paginated, and uses ?page=1 as the first page
+    #[oai(
+        path = "/entities/eip/:number/topics",
+        method = "get",
+        operation_id = "get_eip_topics",
+        tag = "ApiTags::Topic"
+    )]
+    async fn get_eip_topics(
+        &self,
+        state: Data<&AppState>,
+        #[oai(style = "simple")] number: Path<u32>,
+        page: Query<Option<i64>>,
+        size: Query<Option<i64>>,
+    ) -> Result<Json<Vec<Topic>>> {
+        let page = page.0.unwrap_or(1).max(1);
+        let size = size.0.unwrap_or(20).clamp(1, 100);
+
+        let topics = TopicEntity::find_topics(entities::EIP, &number.0.to_string(), page, size, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting topics mentioning EIP-{}: {:?}", number.0, e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(without_quarantined(topics, &state).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_excerpt_multibyte_case() {
+        assert_eq!(
+            highlight_excerpt("Raise the Gas limit", "gas", 4).as_deref(),
+            Some("…the <em>Gas</em> lim…")
+        );
+        // lowercasing keeps the byte length here, but the match isn't where it lands
+        assert_eq!(highlight_excerpt("İẞ", "ß", 5).as_deref(), Some("…İ<em>ẞ</em>…"));
+        assert_eq!(highlight_excerpt("<b>", "", 5), None);
+    }
 }
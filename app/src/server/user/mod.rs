@@ -2,19 +2,40 @@ use poem::web::Data;
 use poem::Result;
 use poem_openapi::param::{Path, Query};
 use poem_openapi::payload::Json;
-use poem_openapi::{Object, OpenApi};
+use poem_openapi::{ApiResponse, Object, OpenApi};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use crate::models::discourse::user::{DiscourseUserProfile, DiscourseUserSummaryResponse};
-use crate::modules::discourse::LResult;
+use crate::models::topics::{Topic, subscription::TopicSubscription};
+use crate::models::user::link::UserDiscourseLink;
+use crate::models::workshop::usage::{UserTokenAllowance, get_user_usage_stats};
+use crate::modules::discourse::{DiscourseService, LResult};
+use crate::modules::notifications;
 use crate::modules::sso::{AuthResponse, UserInfo};
 use crate::state::AppState;
 use crate::server::ApiTags;
 use crate::server::auth::AuthUser;
+use crate::server::discourse_id::DiscourseId;
 
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct UserApi;
 
+/// Maximum number of users in one batch request
+const MAX_USER_BATCH_SIZE: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiscourseUsersBatchRequest {
+    pub usernames: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiscourseUserBatchEntry {
+    pub username: String,
+    pub found: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<DiscourseUserProfile>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct TokenValidationResponse {
     pub valid: bool,
@@ -32,6 +53,81 @@ pub struct LoginResponse {
     pub redirect_url: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct LogoutResponse {
+    pub logged_out: bool,
+}
+
+#[derive(ApiResponse)]
+pub enum SSOCallbackResponse {
+    /// Logged in, the session cookie is set alongside the token
+    #[oai(status = 200)]
+    Ok(Json<AuthResponse>, #[oai(header = "Set-Cookie")] String),
+}
+
+#[derive(ApiResponse)]
+pub enum SSOLogoutResponse {
+    #[oai(status = 200)]
+    Ok(Json<LogoutResponse>, #[oai(header = "Set-Cookie")] String),
+}
+
+/// Error returned when an SSO endpoint is hit without SSO configured
+fn sso_not_configured() -> poem::Error {
+    tracing::warn!("SSO endpoint called but SSO is not configured");
+    poem::Error::from_string("SSO is not configured", StatusCode::NOT_IMPLEMENTED)
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct MeResponse {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    /// Verified Discourse accounts linked to this user
+    pub discourse: Vec<UserDiscourseLink>,
+    pub usage: UserTokenAllowance,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiscourseLinkRequest {
+    pub discourse_id: String,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct TopicSubscriptionRequest {
+    /// https endpoint that receives a `topic.new_posts` POST when the topic gets new posts
+    pub webhook_url: String,
+}
+
+/// Upper bound on topics a single user can watch
+const MAX_SUBSCRIPTIONS_PER_USER: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct DiscourseLinkStartResponse {
+    /// Place this code in the bio of the Discourse profile, then call the verify endpoint
+    pub verification_code: String,
+    pub link: UserDiscourseLink,
+}
+
+/// Fetch a Discourse profile bypassing the cache, so a freshly edited bio is seen
+async fn fetch_discourse_profile(
+    state: &AppState,
+    discourse_id: &str,
+    username: &str,
+) -> Result<DiscourseUserProfile> {
+    let discourse_url = state
+        .discourse
+        .get_discourse_url(discourse_id)
+        .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+    DiscourseService::fetch_discourse_user(&discourse_url, username)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching discourse user {}: {:?}", username, e);
+            poem::Error::from_string("Discourse user not found", StatusCode::NOT_FOUND)
+        })
+}
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct UserProfileResponse {
     pub user_id: String,
@@ -66,8 +162,8 @@ impl UserApi {
         _state: Data<&AppState>,
         auth_user: AuthUser,
     ) -> Result<Json<UserProfileResponse>> {
-        let user = &auth_user.0.user;
-        let claims = &auth_user.0.claims;
+        let user = &auth_user.user;
+        let claims = &auth_user.claims;
 
         Ok(Json(UserProfileResponse {
             user_id: user.user_id.to_string(),
@@ -76,11 +172,285 @@ impl UserApi {
             avatar_url: user.avatar_url.clone(),
             created_at: user.created_at.to_rfc3339(),
             provider: claims.provider.clone(),
-            expires_at: auth_user.0.expires_at(),
-            token_expiring_soon: auth_user.0.is_token_expiring_soon(),
+            expires_at: auth_user.expires_at(),
+            token_expiring_soon: auth_user.is_token_expiring_soon(),
+        }))
+    }
+
+    /// /me
+    ///
+    /// Get the logged in user along with their token usage
+    #[oai(path = "/me", method = "get", tag = "ApiTags::User")]
+    async fn me(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+    ) -> Result<Json<MeResponse>> {
+        let user = &auth_user.user;
+
+        let stats = get_user_usage_stats(user.user_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user usage stats: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let discourse = UserDiscourseLink::find_verified_by_user(user.user_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting discourse links: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(MeResponse {
+            user_id: user.user_id.to_string(),
+            display_name: user.display_name.clone(),
+            avatar_url: user.avatar_url.clone(),
+            discourse,
+            usage: UserTokenAllowance::new(&stats, state.workshop.token_budget),
         }))
     }
 
+    /// /me/discourse
+    ///
+    /// Start linking a Discourse account, returns a one-time code to place in the Discourse bio
+    #[oai(path = "/me/discourse", method = "post", tag = "ApiTags::User")]
+    async fn start_discourse_link(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        request: Json<DiscourseLinkRequest>,
+    ) -> Result<Json<DiscourseLinkStartResponse>> {
+        let user_id = auth_user.user_id();
+        let discourse_id = request.0.discourse_id;
+
+        let profile = fetch_discourse_profile(&state, &discourse_id, &request.0.username).await?;
+
+        let claimed = UserDiscourseLink::is_claimed_by_other(user_id, &discourse_id, profile.user.id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error checking discourse link: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+        if claimed {
+            return Err(poem::Error::from_string(
+                "This Discourse account is already linked to another user",
+                StatusCode::CONFLICT,
+            ));
+        }
+
+        let verification_code = format!("ethereum-forum-{}", uuid::Uuid::new_v4().simple());
+
+        let link = UserDiscourseLink::create_pending(
+            user_id,
+            &discourse_id,
+            profile.user.id,
+            &profile.user.username,
+            &verification_code,
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating discourse link: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(DiscourseLinkStartResponse {
+            verification_code,
+            link,
+        }))
+    }
+
+    /// /me/discourse/:discourse_id/verify
+    ///
+    /// Verify a pending Discourse link by checking the code in the Discourse bio
+    #[oai(path = "/me/discourse/:discourse_id/verify", method = "post", tag = "ApiTags::User")]
+    async fn verify_discourse_link(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        discourse_id: DiscourseId,
+    ) -> Result<Json<UserDiscourseLink>> {
+        let user_id = auth_user.user_id();
+
+        let link = UserDiscourseLink::find(user_id, &discourse_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting discourse link: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        if link.is_verified() {
+            return Ok(Json(link));
+        }
+
+        let profile = fetch_discourse_profile(&state, &discourse_id, &link.username).await?;
+
+        let code_present = profile
+            .user
+            .bio_raw
+            .as_deref()
+            .is_some_and(|bio| bio.contains(&link.verification_code));
+        if profile.user.id != link.discourse_user_id || !code_present {
+            return Err(poem::Error::from_string(
+                "Verification code not found in the Discourse profile bio",
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ));
+        }
+
+        let link = link.mark_verified(&state).await.map_err(|e| {
+            if let sqlx::Error::Database(db_error) = &e {
+                if db_error.is_unique_violation() {
+                    return poem::Error::from_string(
+                        "This Discourse account is already linked to another user",
+                        StatusCode::CONFLICT,
+                    );
+                }
+            }
+            tracing::error!("Error verifying discourse link: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        tracing::info!(
+            "Linked user {} to {} on {}",
+            user_id,
+            link.username,
+            link.discourse_id
+        );
+
+        Ok(Json(link))
+    }
+
+    /// /me/discourse/:discourse_id
+    ///
+    /// Unlink the Discourse account on an instance
+    #[oai(path = "/me/discourse/:discourse_id", method = "delete", tag = "ApiTags::User")]
+    async fn unlink_discourse(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        discourse_id: DiscourseId,
+    ) -> Result<Json<serde_json::Value>> {
+        let deleted = UserDiscourseLink::delete(auth_user.user_id(), &discourse_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting discourse link: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if !deleted {
+            return Err(poem::Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        Ok(Json(serde_json::json!({})))
+    }
+
+    /// /me/subscriptions
+    ///
+    /// List the topics the authenticated user is subscribed to
+    #[oai(path = "/me/subscriptions", method = "get", tag = "ApiTags::User")]
+    async fn list_subscriptions(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+    ) -> Result<Json<Vec<TopicSubscription>>> {
+        let subscriptions = TopicSubscription::find_by_user(auth_user.user_id(), &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error listing topic subscriptions: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(subscriptions))
+    }
+
+    /// /me/subscriptions/:discourse_id/:topic_id
+    ///
+    /// Subscribe to new posts on a topic, delivered to the given webhook (best-effort, with retries)
+    #[oai(
+        path = "/me/subscriptions/:discourse_id/:topic_id",
+        method = "put",
+        tag = "ApiTags::User"
+    )]
+    async fn subscribe_topic(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+        request: Json<TopicSubscriptionRequest>,
+    ) -> Result<Json<TopicSubscription>> {
+        let user_id = auth_user.user_id();
+
+        let webhook_url = notifications::validate_webhook_url(&request.0.webhook_url)
+            .map_err(|e| poem::Error::from_string(e, StatusCode::BAD_REQUEST))?;
+
+        Topic::get_by_topic_id(&discourse_id, topic_id.0, &state)
+            .await
+            .map_err(|_| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        let existing = TopicSubscription::find_by_user(user_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error listing topic subscriptions: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+        let resubscribe = existing
+            .iter()
+            .any(|subscription| subscription.discourse_id == discourse_id.0 && subscription.topic_id == topic_id.0);
+        if !resubscribe && existing.len() >= MAX_SUBSCRIPTIONS_PER_USER {
+            return Err(poem::Error::from_string(
+                "Too many topic subscriptions",
+                StatusCode::CONFLICT,
+            ));
+        }
+
+        let subscription = TopicSubscription::upsert(
+            user_id,
+            &discourse_id,
+            topic_id.0,
+            webhook_url.as_str(),
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating topic subscription: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(subscription))
+    }
+
+    /// /me/subscriptions/:discourse_id/:topic_id
+    ///
+    /// Unsubscribe from a topic
+    #[oai(
+        path = "/me/subscriptions/:discourse_id/:topic_id",
+        method = "delete",
+        tag = "ApiTags::User"
+    )]
+    async fn unsubscribe_topic(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        discourse_id: DiscourseId,
+        #[oai(style = "simple")] topic_id: Path<i32>,
+    ) -> Result<Json<serde_json::Value>> {
+        let deleted = TopicSubscription::delete(auth_user.user_id(), &discourse_id, topic_id.0, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting topic subscription: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if !deleted {
+            return Err(poem::Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        Ok(Json(serde_json::json!({})))
+    }
+
     /// /du/:discourse_id/:username
     ///
     /// Get user profile
@@ -88,7 +458,7 @@ impl UserApi {
     async fn get_user(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] username: Path<String>,
     ) -> Result<Json<DiscourseUserProfile>> {
         let user = match state.discourse.fetch_discourse_user_cached(&discourse_id, &username).await {
@@ -106,6 +476,57 @@ impl UserApi {
         Ok(Json(user))
     }
 
+    /// /du/:discourse_id/batch
+    ///
+    /// Get the profiles of several users in one request, e.g. the participants of a topic
+    /// Users that could not be found are returned with `found: false`
+    #[oai(path = "/du/:discourse_id/batch", method = "post", tag = "ApiTags::User")]
+    async fn get_users_batch(
+        &self,
+        state: Data<&AppState>,
+        discourse_id: DiscourseId,
+        request: Json<DiscourseUsersBatchRequest>,
+    ) -> Result<Json<Vec<DiscourseUserBatchEntry>>> {
+        let mut usernames = request.0.usernames;
+        let mut seen = std::collections::HashSet::new();
+        usernames.retain(|username| seen.insert(username.to_lowercase()));
+
+        if usernames.len() > MAX_USER_BATCH_SIZE {
+            return Err(poem::Error::from_string(
+                format!("At most {} users can be requested at once", MAX_USER_BATCH_SIZE),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let users = state
+            .discourse
+            .fetch_discourse_users_cached(&discourse_id, &usernames)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error fetching users: {:?}", e);
+                poem::Error::from_status(StatusCode::NOT_FOUND)
+            })?;
+
+        let entries = usernames
+            .into_iter()
+            .zip(users)
+            .map(|(username, user)| {
+                let user = match user {
+                    LResult::Success(user) => Some(user),
+                    LResult::Failed(_) => None,
+                };
+
+                DiscourseUserBatchEntry {
+                    username,
+                    found: user.is_some(),
+                    user,
+                }
+            })
+            .collect();
+
+        Ok(Json(entries))
+    }
+
     /// /du/:discourse_id/:username/summary
     ///
     /// Get user summary
@@ -113,7 +534,7 @@ impl UserApi {
     async fn get_user_summary(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] username: Path<String>,
     ) -> Result<Json<DiscourseUserSummaryResponse>> {
         let summary = match state.discourse.fetch_discourse_user_summary_cached(&discourse_id, &username).await {
@@ -157,11 +578,7 @@ impl UserApi {
         state: Data<&AppState>,
         #[oai(style = "simple")] sso_id: Path<String>,
     ) -> Result<Json<LoginResponse>> {
-        let sso_service = state.sso.as_ref()
-            .ok_or_else(|| {
-                tracing::error!("SSO service not configured");
-                poem::Error::from_status(StatusCode::SERVICE_UNAVAILABLE)
-            })?;
+        let sso_service = state.sso.as_ref().ok_or_else(sso_not_configured)?;
 
         let (auth_url, session) = sso_service.get_authorization_url(&sso_id)
             .map_err(|e| {
@@ -169,8 +586,8 @@ impl UserApi {
                 poem::Error::from_status(StatusCode::BAD_REQUEST)
             })?;
 
-        // In a real implementation, you'd store the session somewhere (Redis, database, etc.)
-        tracing::info!("Generated SSO session for provider {}: {:?}", &*sso_id, session);
+        // Keep the session until the provider redirects back with the same state
+        sso_service.store_pending_session(session).await;
 
         Ok(Json(LoginResponse {
             redirect_url: auth_url
@@ -179,29 +596,26 @@ impl UserApi {
 
     /// /user/sso/:sso_id/callback
     /// 
-    /// SSO callback endpoint - exchanges code for JWT token
+    /// SSO callback endpoint - exchanges code for JWT token and sets the session cookie
     #[oai(path = "/user/sso/:sso_id/callback", method = "get", tag = "ApiTags::User")]
     async fn sso_callback(
         &self,
         state: Data<&AppState>,
         #[oai(style = "simple")] sso_id: Path<String>,
         #[oai(style = "simple")] code: Query<String>,
-        #[oai(style = "simple")] _state_param: Query<Option<String>>,
-    ) -> Result<Json<AuthResponse>> {
-        let sso_service = state.sso.as_ref()
+        #[oai(name = "state", style = "simple")] csrf_state: Query<String>,
+    ) -> Result<SSOCallbackResponse> {
+        let sso_service = state.sso.as_ref().ok_or_else(sso_not_configured)?;
+
+        let session = sso_service
+            .take_pending_session(&csrf_state)
+            .await
+            .filter(|session| session.provider_id == *sso_id)
             .ok_or_else(|| {
-                tracing::error!("SSO service not configured");
-                poem::Error::from_status(StatusCode::SERVICE_UNAVAILABLE)
+                tracing::warn!("SSO callback for {} with unknown or expired state", &*sso_id);
+                poem::Error::from_string("Invalid or expired login state", StatusCode::BAD_REQUEST)
             })?;
 
-        // In a real implementation, you'd retrieve the session from storage
-        let session = crate::modules::sso::AuthSession {
-            csrf_token: "dummy".to_string(),
-            nonce: "dummy".to_string(),
-            provider_id: sso_id.clone(),
-            redirect_uri: "http://localhost:3000/auth/callback".to_string(),
-        };
-
         let (jwt_token, user) = sso_service.exchange_code_for_token(&sso_id, &code, &session, &state.database.pool).await
             .map_err(|e| {
                 tracing::error!("Error exchanging code for token: {:?}", e);
@@ -215,11 +629,29 @@ impl UserApi {
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
-        Ok(Json(AuthResponse {
-            token: jwt_token,
-            user,
-            expires_at: claims.exp,
-        }))
+        let cookie = sso_service.session_cookie(&jwt_token, &sso_id);
+
+        Ok(SSOCallbackResponse::Ok(
+            Json(AuthResponse {
+                token: jwt_token,
+                user,
+                expires_at: claims.exp,
+            }),
+            cookie,
+        ))
+    }
+
+    /// /user/sso/logout
+    /// 
+    /// Logout - clears the session cookie
+    #[oai(path = "/user/sso/logout", method = "post", tag = "ApiTags::User")]
+    async fn logout(&self, state: Data<&AppState>) -> Result<SSOLogoutResponse> {
+        let sso_service = state.sso.as_ref().ok_or_else(sso_not_configured)?;
+
+        Ok(SSOLogoutResponse::Ok(
+            Json(LogoutResponse { logged_out: true }),
+            sso_service.clear_session_cookie(),
+        ))
     }
 
     /// /user/token/validate
@@ -231,11 +663,7 @@ impl UserApi {
         state: Data<&AppState>,
         token: Query<String>,
     ) -> Result<Json<TokenValidationResponse>> {
-        let sso_service = state.sso.as_ref()
-            .ok_or_else(|| {
-                tracing::error!("SSO service not configured");
-                poem::Error::from_status(StatusCode::SERVICE_UNAVAILABLE)
-            })?;
+        let sso_service = state.sso.as_ref().ok_or_else(sso_not_configured)?;
 
         match sso_service.validate_jwt_token(&token) {
             Ok(claims) => {
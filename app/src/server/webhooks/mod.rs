@@ -2,17 +2,47 @@ use discourse_webhooks::{
     PostWebhookEvent, TopicWebhookEvent, WebhookError, WebhookEventHandler, WebhookProcessor,
     async_trait,
 };
-use poem::{Result, web::Data};
+use hmac::{Hmac, Mac};
+use poem::{Body, Result, error::ReadBodyError, web::Data};
 use poem_openapi::param::Header;
 use poem_openapi::{Object, OpenApi};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use tracing::info;
 
 use crate::models::topics::Topic;
 use crate::models::topics::post::Post;
+use crate::modules::discourse::page_for_post_number;
+use crate::modules::meili;
 use crate::server::ApiTags;
 use crate::state::AppState;
 
+/// Largest webhook body we are willing to buffer, Discourse payloads are far smaller
+const DEFAULT_WEBHOOK_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+fn webhook_max_body_bytes() -> usize {
+    std::env::var("WEBHOOK_MAX_BODY_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_WEBHOOK_MAX_BODY_BYTES)
+}
+
+/// Check a `sha256=<hex>` Discourse signature against the raw request body
+fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(expected) = signature
+        .strip_prefix("sha256=")
+        .and_then(|signature| hex::decode(signature).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct WebhookPayload {
     #[serde(default)]
@@ -50,17 +80,39 @@ impl DiscourseEventHandler {
         }
     }
 
+    async fn delete_topic_from_event(
+        &mut self,
+        event: &TopicWebhookEvent,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let topic_id = event.topic.id;
+        info!("Topic {} on {} was deleted", topic_id, self.instance);
+
+        self.state
+            .cache
+            .invalidate_topic_responses(&self.instance, topic_id);
+
+        if let Some(meili) = &self.state.meili {
+            let forum = meili.index("forum");
+            if let Err(e) = meili::delete_topic_documents(&forum, &self.instance, topic_id).await {
+                info!("Error deleting topic documents: {:?}", e);
+                return Err("Failed to delete topic documents".into());
+            }
+        }
+
+        Ok(())
+    }
+
     async fn upsert_post_from_event(
         &mut self,
         event: &PostWebhookEvent,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let posts_per_page = 20; // Discourse fetches posts in pages of 20 by default
         let instance = self.instance.clone();
-        let page = ((event.post.post_number.max(1) - 1) / posts_per_page) + 1;
+        let posts_per_page = self.state.discourse.get_posts_per_page(&instance);
+        let page = page_for_post_number(event.post.post_number as i64, posts_per_page);
         match self
             .state
             .discourse
-            .enqueue(instance.as_str(), event.post.topic_id, page as u32)
+            .enqueue(instance.as_str(), event.post.topic_id, page)
             .await
             .map_err(|e| anyhow::anyhow!(e))
         {
@@ -84,6 +136,10 @@ impl WebhookEventHandler for DiscourseEventHandler {
         self.upsert_topic_from_event(event).await
     }
 
+    async fn handle_topic_destroyed(&mut self, event: &TopicWebhookEvent) -> Result<(), Self::Error> {
+        self.delete_topic_from_event(event).await
+    }
+
     async fn handle_post_created(&mut self, event: &PostWebhookEvent) -> Result<(), Self::Error> {
         self.upsert_post_from_event(event).await
     }
@@ -110,10 +166,11 @@ impl WebhookApi {
         &self,
         state: Data<&AppState>,
         // Oddly enough we have to use Binary here because otherwise Poem won't accept JSON body
-        body: poem_openapi::payload::Binary<Vec<u8>>,
+        body: poem_openapi::payload::Binary<Body>,
         #[oai(name = "X-Discourse-Instance")] discourse_id: Header<String>,
         #[oai(name = "X-Discourse-Event")] discourse_event: Header<String>,
         #[oai(name = "X-Discourse-Event-Signature")] signature: Header<String>,
+        #[oai(name = "X-Discourse-Event-Id")] event_id: Header<Option<String>>,
     ) -> Result<poem_openapi::payload::PlainText<String>> {
         let secret = std::env::var("DISCOURSE_WEBHOOK_SECRET");
 
@@ -123,7 +180,8 @@ impl WebhookApi {
                 poem::http::StatusCode::INTERNAL_SERVER_ERROR,
             ));
         }
-        let processor = WebhookProcessor::new().with_secret(secret.as_ref().unwrap());
+        // The signature is verified here over the raw bytes, so the processor doesn't need the secret
+        let processor = WebhookProcessor::new();
 
         let instance = match discourse_id.0.as_str() {
             "http://localhost" => "magicians",
@@ -138,17 +196,56 @@ impl WebhookApi {
 
         let discourse_event = discourse_event.0;
 
+        // Read the body with a cap so an oversized POST can't exhaust memory
+        let body = match body.0.into_bytes_limit(webhook_max_body_bytes()).await {
+            Ok(body) => body,
+            Err(ReadBodyError::PayloadTooLarge) => {
+                return Err(poem::Error::from_string(
+                    "Webhook body is too large",
+                    poem::http::StatusCode::PAYLOAD_TOO_LARGE,
+                ));
+            }
+            Err(e) => {
+                return Err(poem::Error::from_string(
+                    format!("Failed to read webhook body: {}", e),
+                    poem::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+        };
+
         let mut handler = DiscourseEventHandler::new(instance.to_string(), state.0.clone());
 
-        let body_str = String::from_utf8_lossy(&body.0);
+        if !verify_signature(secret.as_ref().unwrap(), &body, &signature.0) {
+            return Err(poem::Error::from_string(
+                "Read the code at https://github.com/v3xlabs/ethereum-forum/blob/master/app/src/server/webhooks/mod.rs before trying that again :)",
+                poem::http::StatusCode::FORBIDDEN,
+            ));
+        }
+
+        // Retried deliveries share the event id, or failing that the signature of an identical body
+        let delivery_key = format!(
+            "{}:{}",
+            instance,
+            event_id.0.as_deref().unwrap_or(signature.0.as_str())
+        );
+        let delivery = state
+            .cache
+            .webhook_deliveries
+            .entry(delivery_key.clone())
+            .or_insert(())
+            .await;
+        if !delivery.is_fresh() {
+            info!("Skipping duplicate webhook delivery {}", delivery_key);
+            return Ok(poem_openapi::payload::PlainText(
+                "Webhook already processed".to_string(),
+            ));
+        }
+
+        // Only decode once the signature over the original bytes checked out
+        let body_str = String::from_utf8_lossy(&body);
 
         match processor
-            .process(
-                &mut handler,
-                discourse_event.as_str(),
-                &body_str,
-                Some(signature.0.as_str()),
-            )
+            .process(&mut handler, discourse_event.as_str(), &body_str, None)
             .await
         {
             Ok(_) => Ok(poem_openapi::payload::PlainText(
@@ -160,6 +257,8 @@ impl WebhookApi {
             )),
             Err(e) => {
                 println!("Error processing webhook: {:?}", e);
+                // let Discourse's retry go through
+                state.cache.webhook_deliveries.invalidate(&delivery_key).await;
                 Err(poem::Error::from_string(
                     format!("Error processing webhook"),
                     poem::http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -168,3 +267,23 @@ impl WebhookApi {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature_over_raw_bytes() {
+        // not valid UTF-8, a lossy round-trip would change the signed bytes
+        let body = b"{\"title\":\"\xff\xfe\"}";
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature("secret", body, &signature));
+        assert!(!verify_signature("other", body, &signature));
+        assert!(!verify_signature("secret", body, "sha256=zz"));
+        assert!(!verify_signature("secret", String::from_utf8_lossy(body).as_bytes(), &signature));
+    }
+}
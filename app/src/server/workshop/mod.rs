@@ -1,21 +1,25 @@
-use crate::models::topics::Topic;
+use crate::models::topics::{Topic, TopicSummary};
 use crate::models::workshop::snapshot::{CreateChatSnapshotPayload, WorkshopSnapshotResponse};
 use crate::models::workshop::usage::{get_user_daily_usage, get_user_usage_by_model, get_user_usage_stats};
 use crate::models::workshop::{
     chat::WorkshopChat,
+    context::WorkshopChatContext,
     message::WorkshopMessage,
     snapshot::WorkshopSnapshot,
     usage::{DailyUsage, ModelUsage, UserUsageOverview, UserUsageStats},
 };
 use crate::modules::workshop::WorkshopService;
+use crate::modules::workshop::breaker::AiUnavailable;
 use crate::modules::workshop::prompts::{
-    StreamingEntryType as PromptsStreamingEntryType, ToolCallEntry as PromptsToolCallEntry,
+    StreamingEntryType as PromptsStreamingEntryType,
+    StreamingUsage as PromptsStreamingUsage, ToolCallEntry as PromptsToolCallEntry,
     ToolCallStatus as PromptsToolCallStatus,
 };
 use crate::server::ApiTags;
 use crate::server::auth::AuthUser;
+use crate::server::discourse_id::DiscourseId;
+use crate::server::topic::ensure_not_quarantined;
 use crate::state::AppState;
-use async_std::task;
 use futures::{StreamExt, stream::BoxStream};
 use poem::Request;
 use poem::Result;
@@ -27,6 +31,8 @@ use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub mod socket;
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct WorkshopApi;
 
@@ -60,6 +66,12 @@ pub struct AdminUsageResponse {
     pub users: Vec<UserUsageOverview>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct AttachContextRequest {
+    pub discourse_id: String,
+    pub topic_id: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct StreamingResponse {
     pub content: String,
@@ -70,6 +82,9 @@ pub struct StreamingResponse {
     pub entry_type: StreamingEntryType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call: Option<ToolCallEntry>,
+    /// Only set on the final `usage` entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<StreamingUsage>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Enum)]
@@ -79,6 +94,21 @@ pub enum StreamingEntryType {
     ToolCallStart,
     ToolCallResult,
     ToolCallError,
+    Usage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct StreamingUsage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Object)]
@@ -107,6 +137,8 @@ pub struct AvailableModel {
     pub name: String,
     pub provider: String,
     pub is_default: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Object)]
@@ -122,6 +154,17 @@ fn convert_entry_type(entry_type: PromptsStreamingEntryType) -> StreamingEntryTy
         PromptsStreamingEntryType::ToolCallStart => StreamingEntryType::ToolCallStart,
         PromptsStreamingEntryType::ToolCallResult => StreamingEntryType::ToolCallResult,
         PromptsStreamingEntryType::ToolCallError => StreamingEntryType::ToolCallError,
+        PromptsStreamingEntryType::Usage => StreamingEntryType::Usage,
+    }
+}
+
+fn convert_usage(usage: PromptsStreamingUsage) -> StreamingUsage {
+    StreamingUsage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        model: usage.model,
+        provider: usage.provider,
     }
 }
 
@@ -144,6 +187,15 @@ fn convert_tool_call_status(status: PromptsToolCallStatus) -> ToolCallStatus {
     }
 }
 
+/// 503 while the AI circuit breaker is open, 500 for anything else
+fn prompt_error(e: &(dyn std::error::Error + Send + Sync + 'static)) -> poem::Error {
+    if e.is::<AiUnavailable>() {
+        poem::Error::from_string(e.to_string(), StatusCode::SERVICE_UNAVAILABLE)
+    } else {
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
 #[OpenApi]
 impl WorkshopApi {
     /// /ws/t/:discourse_id/:topic_id/summary/to-chat
@@ -158,10 +210,12 @@ impl WorkshopApi {
         &self,
         state: Data<&AppState>,
         auth_user: AuthUser,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
     ) -> Result<Json<WorkshopMessage>> {
-        let user_id = auth_user.0.user.user_id;
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
+        let user_id = auth_user.user.user_id;
         let user_prompt = format!("Summarize ethereum.forum topic #{}", topic_id.0);
 
         let message =
@@ -203,7 +257,7 @@ impl WorkshopApi {
         state: Data<&AppState>,
         auth_user: AuthUser,
     ) -> Result<Json<Vec<WorkshopChat>>> {
-        let user_id = auth_user.0.user.user_id;
+        let user_id = auth_user.user.user_id;
         let chats = WorkshopChat::find_by_user_id(user_id, &state.0)
             .await
             .map_err(|e| {
@@ -217,72 +271,89 @@ impl WorkshopApi {
     /// /ws/models
     ///
     /// Get available models for the user
+    /// Lists the allowed models the provider currently serves, falling back to the static list on provider errors
     #[oai(path = "/ws/models", method = "get", tag = "ApiTags::Workshop")]
     async fn get_available_models(
         &self,
-        _state: Data<&AppState>,
+        state: Data<&AppState>,
         _auth_user: AuthUser,
     ) -> Result<Json<AvailableModelsResponse>> {
-        // For now, return a hardcoded list of available models
-        // In the future, this could be dynamically fetched from the LLM provider
-        // or based on user permissions/subscription level
-        let models = vec![
-            AvailableModel {
-                id: "google/gemini-2.5-flash-preview-05-20".to_string(),
-                name: "Gemini 2.5 Flash Preview".to_string(),
-                provider: "Google".to_string(),
-                is_default: true,
-            },
-            AvailableModel {
-                id: "google/gemini-2.0-flash-001".to_string(),
-                name: "Gemini 2.0 Flash".to_string(),
-                provider: "Google".to_string(),
-                is_default: false,
-            },
-            AvailableModel {
-                id: "google/gemini-2.5-pro-preview".to_string(),
-                name: "Gemini 2.5 Pro Preview".to_string(),
-                provider: "Google".to_string(),
-                is_default: false,
-            },
-            AvailableModel {
-                id: "anthropic/claude-sonnet-4".to_string(),
-                name: "Claude Sonnet 4".to_string(),
-                provider: "Anthropic".to_string(),
-                is_default: false,
-            },
-            AvailableModel {
-                id: "openai/gpt-4o-mini".to_string(),
-                name: "OpenAI o4 Mini".to_string(),
-                provider: "OpenAI".to_string(),
-                is_default: false,
-            },
-            AvailableModel {
-                id: "mistralai/mistral-nemo".to_string(),
-                name: "Mistral Nemo".to_string(),
-                provider: "Mistral AI".to_string(),
-                is_default: false,
-            },
-            // AvailableModel {
-            //     id: "mistralai/mistral-7b-instruct:free".to_string(),
-            //     name: "Mistral 7B Instruct (Free)".to_string(),
-            //     provider: "Mistral AI".to_string(),
-            //     is_default: false,
-            // },
-            // AvailableModel {
-            //     id: "deepseek/deepseek-r1-0528:free".to_string(),
-            //     name: "DeepSeek R1 0528".to_string(),
-            //     provider: "DeepSeek".to_string(),
-            //     is_default: false,
-            // },
-        ];
+        let catalog = &state.workshop.models;
+        let default_model = catalog.default_model();
+
+        let models = catalog
+            .list(&state.workshop.providers.primary().client)
+            .await
+            .into_iter()
+            .map(|model| AvailableModel {
+                is_default: model.id == default_model,
+                id: model.id,
+                name: model.name,
+                provider: model.provider,
+                context_window: model.context_window,
+            })
+            .collect();
 
         Ok(Json(AvailableModelsResponse {
-            default_model: "google/gemini-2.5-flash-preview-05-20".to_string(),
+            default_model,
             models,
         }))
     }
 
+    /// /ws/chat/:chat_id/context
+    ///
+    /// Attach a forum topic to a chat, the topic is available to the model for the rest of the conversation
+    #[oai(path = "/ws/chat/:chat_id/context", method = "post", tag = "ApiTags::Workshop")]
+    async fn attach_chat_context(
+        &self,
+        state: Data<&AppState>,
+        auth_user: AuthUser,
+        #[oai(style = "simple")] chat_id: Path<Uuid>,
+        request: Json<AttachContextRequest>,
+    ) -> Result<Json<WorkshopChatContext>> {
+        let user_id = auth_user.user.user_id;
+
+        let chat = WorkshopChat::find_by_id(*chat_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error finding chat: {:?}", e);
+                poem::Error::from_status(StatusCode::NOT_FOUND)
+            })?;
+
+        if chat.user_id != user_id {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let topic = Topic::get_by_topic_id(&request.discourse_id, request.topic_id, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting topic: {:?}", e);
+                poem::Error::from_status(StatusCode::NOT_FOUND)
+            })?;
+
+        let context = WorkshopService::build_topic_context(&topic, &state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error building topic context: {:?}", e);
+                prompt_error(e.as_ref())
+            })?;
+
+        let attached = WorkshopChatContext::attach(
+            chat.chat_id,
+            &topic.discourse_id,
+            topic.topic_id,
+            &context,
+            &state,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error attaching chat context: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(attached))
+    }
+
     /// /ws/chat/:chat_id
     ///
     /// Get a chat
@@ -293,7 +364,7 @@ impl WorkshopApi {
         auth_user: AuthUser,
         #[oai(style = "simple")] chat_id: Path<Uuid>,
     ) -> Result<Json<WorkshopChatPayload>> {
-        let user_id = auth_user.0.user.user_id;
+        let user_id = auth_user.user.user_id;
 
         // First verify that the chat belongs to the authenticated user
         let chat = WorkshopChat::find_by_id(*chat_id, &state)
@@ -341,7 +412,7 @@ impl WorkshopApi {
         #[oai(style = "simple")] chat_id: Path<String>,
         #[oai(style = "simple")] parent_message: Query<Option<Uuid>>,
     ) -> Result<Json<WorkshopMessage>> {
-        let user_id = auth_user.0.user.user_id;
+        let user_id = auth_user.user.user_id;
         let message = payload.message.clone();
 
         let chat_id = if chat_id.eq("new") {
@@ -406,7 +477,7 @@ impl WorkshopApi {
         .await
         .map_err(|e| {
             tracing::error!("Error processing next message: {:?}", e);
-            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            prompt_error(e.as_ref())
         })?;
 
         // Return the system response message that's being generated
@@ -537,6 +608,7 @@ impl WorkshopApi {
                     error: None,
                     entry_type: convert_entry_type(entry.entry_type),
                     tool_call: entry.tool_call.map(convert_tool_call_entry),
+                    usage: entry.usage.map(convert_usage),
                 },
                 Err(err) => {
                     tracing::error!("Stream error: {}", err);
@@ -546,6 +618,7 @@ impl WorkshopApi {
                         error: Some(err),
                         entry_type: StreamingEntryType::ToolCallError,
                         tool_call: None,
+                        usage: None,
                     }
                 }
             })
@@ -566,9 +639,11 @@ impl WorkshopApi {
     async fn start_topic_summary_stream(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
     ) -> Result<Json<serde_json::Value>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
         let topic = Topic::get_by_topic_id(&discourse_id, topic_id.0, &state)
             .await
             .map_err(|e| {
@@ -577,14 +652,7 @@ impl WorkshopApi {
             })?;
 
         // First check if we already have a recent summary
-        if let Ok(existing_summary) = sqlx::query_as!(
-            crate::models::topics::TopicSummary,
-            "SELECT * FROM topic_summaries WHERE topic_id = $1 ORDER BY based_on DESC LIMIT 1",
-            topic_id.0
-        )
-        .fetch_optional(&state.database.pool)
-        .await
-        {
+        if let Ok(existing_summary) = TopicSummary::latest(&discourse_id, topic_id.0, &state).await {
             if let Some(summary) = existing_summary {
                 let based_on = topic
                     .last_post_at
@@ -602,68 +670,30 @@ impl WorkshopApi {
             }
         }
 
-        // Check if there's already an ongoing stream
-        if let Some(_existing_prompt) = state
+        // Check if there's already an ongoing stream, finished ones stay registered
+        let ongoing = match state
             .workshop
             .get_ongoing_summary_prompt(&discourse_id, topic_id.0)
             .await
         {
+            Some(existing_prompt) => !existing_prompt.is_complete().await,
+            None => false,
+        };
+        if ongoing {
             return Ok(Json(serde_json::json!({
                 "status": "ongoing",
                 "topic_id": topic_id.0
             })));
         }
 
-        // Start the summary generation (or get existing ongoing prompt)
-        let _ongoing_prompt = WorkshopService::create_workshop_summary_streaming(&topic, &state)
+        // Start the summary generation and store it once complete
+        let ongoing_prompt = WorkshopService::create_workshop_summary_streaming(&topic, &state)
             .await
             .map_err(|e| {
                 tracing::error!("Error starting summary generation: {:?}", e);
-                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+                prompt_error(e.as_ref())
             })?;
-
-        // Spawn a task to handle completion and update the topic summary
-        let topic_clone = topic.clone();
-        let state_clone = state.clone();
-
-        task::spawn(async move {
-            if let Some(ongoing_prompt) = state_clone
-                .workshop
-                .get_ongoing_summary_prompt(&discourse_id, topic_clone.topic_id)
-                .await
-            {
-                match ongoing_prompt.await_completion().await {
-                    Ok(content) => {
-                        // Update the topic summary in the database
-                        let based_on = topic_clone
-                            .last_post_at
-                            .map(|dt| dt.timestamp())
-                            .unwrap_or_else(|| chrono::Utc::now().timestamp());
-
-                        let based_on_datetime =
-                            chrono::DateTime::from_timestamp(based_on as i64, 0)
-                                .unwrap_or_else(|| chrono::Utc::now());
-
-                        if let Err(e) = sqlx::query!(
-                            "INSERT INTO topic_summaries (discourse_id, topic_id, based_on, summary_text, created_at) VALUES ($1, $2, $3, $4, NOW())",
-                            topic_clone.discourse_id,
-                            topic_clone.topic_id,
-                            based_on_datetime,
-                            content
-                        )
-                        .execute(&state_clone.database.pool)
-                        .await {
-                            tracing::error!("Error saving topic summary: {:?}", e);
-                        } else {
-                            tracing::info!("Saved new summary for topic_id: {}", topic_clone.topic_id);
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error in summary completion: {:?}", e);
-                    }
-                }
-            }
-        });
+        WorkshopService::store_summary_when_complete(topic, ongoing_prompt, state.0.clone());
 
         Ok(Json(serde_json::json!({
             "status": "started",
@@ -683,9 +713,11 @@ impl WorkshopApi {
     async fn stream_topic_summary(
         &self,
         state: Data<&AppState>,
-        #[oai(style = "simple")] discourse_id: Path<String>,
+        discourse_id: DiscourseId,
         #[oai(style = "simple")] topic_id: Path<i32>,
     ) -> Result<EventStream<BoxStream<'static, StreamingResponse>>> {
+        ensure_not_quarantined(&discourse_id, topic_id.0, &state).await?;
+
         tracing::info!(
             "Summary stream request for topic: {} on {}",
             topic_id.0,
@@ -720,6 +752,7 @@ impl WorkshopApi {
                     error: None,
                     entry_type: convert_entry_type(entry.entry_type),
                     tool_call: entry.tool_call.map(convert_tool_call_entry),
+                    usage: entry.usage.map(convert_usage),
                 },
                 Err(err) => {
                     tracing::error!("Summary stream error: {}", err);
@@ -729,6 +762,7 @@ impl WorkshopApi {
                         error: Some(err),
                         entry_type: StreamingEntryType::ToolCallError,
                         tool_call: None,
+                        usage: None,
                     }
                 }
             })
@@ -747,7 +781,7 @@ impl WorkshopApi {
         auth_user: AuthUser,
         #[oai(style = "simple")] days: Query<Option<i32>>,
     ) -> Result<Json<UserUsageResponse>> {
-        let user_id = auth_user.0.user.user_id;
+        let user_id = auth_user.user.user_id;
         let days = days.0.unwrap_or(30); // Default to 30 days
 
         // Get overall stats
@@ -791,7 +825,7 @@ impl WorkshopApi {
         auth_user: AuthUser,
         payload: Json<CreateChatSnapshotPayload>,
     ) -> Result<Json<WorkshopSnapshot>> {
-        let user = auth_user.0.user_id();
+        let user = auth_user.user_id();
 
         let snapshot = WorkshopSnapshot::create(payload.chat_id, payload.message_id, user, &state)
             .await
@@ -860,7 +894,7 @@ impl WorkshopApi {
         auth_user: AuthUser,
         #[oai(style = "simple")] chat_id: Path<Uuid>,
     ) -> Result<Json<serde_json::Value>> {
-        let user_id = auth_user.0.user.user_id;
+        let user_id = auth_user.user.user_id;
 
         let chat = WorkshopChat::find_by_id(*chat_id, &state)
             .await
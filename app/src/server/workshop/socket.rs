@@ -0,0 +1,198 @@
+//! WebSocket transport for prompt streaming
+//!
+//! Mirrors the SSE stream of `/ws/chat/:chat_id/:message_id/stream`, sending each
+//! `StreamingResponse` as a JSON text frame, while also accepting control messages
+//! from the client:
+//!
+//! ```json
+//! { "type": "cancel" }
+//! ```
+//!
+//! Browsers cannot set headers on a WebSocket, so besides the usual header and session cookie
+//! the token may be offered as a subprotocol, `new WebSocket(url, ["bearer", token])`.
+
+use futures::{SinkExt, StreamExt, stream};
+use poem::web::websocket::{Message, WebSocket};
+use poem::http::header::SEC_WEBSOCKET_PROTOCOL;
+use poem::web::{Data, Path, Query};
+use poem::{IntoResponse, Request, Result, handler};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::{
+    StreamingEntryType, StreamingResponse, convert_entry_type, convert_tool_call_entry,
+    convert_usage,
+};
+use crate::models::workshop::chat::WorkshopChat;
+use crate::server::auth::{authenticate_token, extract_user_from_request};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct SocketParams {
+    /// Defaults to the last message of the chat
+    pub message_id: Option<Uuid>,
+}
+
+/// Subprotocol offered next to the token, the only one the server accepts
+const AUTH_PROTOCOL: &str = "bearer";
+
+/// The token offered as a subprotocol next to [`AUTH_PROTOCOL`], kept out of URLs and logs
+fn protocol_token(req: &Request) -> Option<String> {
+    let protocols: Vec<&str> = req
+        .headers()
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    if !protocols.contains(&AUTH_PROTOCOL) {
+        return None;
+    }
+
+    protocols
+        .into_iter()
+        .find(|protocol| *protocol != AUTH_PROTOCOL && !protocol.is_empty())
+        .map(str::to_string)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientControl {
+    Cancel,
+}
+
+enum SocketEvent {
+    Entry(StreamingResponse),
+    Done,
+    Client(Option<Message>),
+}
+
+/// /ws/chat/:chat_id/ws
+///
+/// Stream a prompt response over a WebSocket
+#[handler]
+pub async fn chat_socket(
+    req: &Request,
+    ws: WebSocket,
+    state: Data<&AppState>,
+    Path(chat_id): Path<Uuid>,
+    Query(params): Query<SocketParams>,
+) -> Result<impl IntoResponse> {
+    let user = match protocol_token(req) {
+        Some(token) => authenticate_token(req, &token).await,
+        None => extract_user_from_request(req).await.ok().flatten(),
+    }
+    .ok_or_else(|| poem::Error::from_status(StatusCode::UNAUTHORIZED))?;
+
+    let chat = WorkshopChat::find_by_id(chat_id, &state).await.map_err(|e| {
+        tracing::error!("Error finding chat: {:?}", e);
+        poem::Error::from_status(StatusCode::NOT_FOUND)
+    })?;
+
+    if chat.user_id != user.user_id() {
+        tracing::warn!(
+            "User {} attempted to open a socket on chat {} owned by {}",
+            user.user_id(),
+            chat_id,
+            chat.user_id
+        );
+        return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+    }
+
+    let message_id = params
+        .message_id
+        .or(chat.last_message_id)
+        .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+    let prompt = state
+        .workshop
+        .get_ongoing_prompt(chat_id, message_id)
+        .await
+        .ok_or_else(|| {
+            tracing::error!(
+                "No ongoing prompt found for chat {} message {}",
+                chat_id,
+                message_id
+            );
+            poem::Error::from_status(StatusCode::NOT_FOUND)
+        })?;
+
+    let entries = prompt.get_stream().await;
+
+    Ok(ws.protocols([AUTH_PROTOCOL]).on_upgrade(move |socket| async move {
+        let (mut sink, incoming) = socket.split();
+
+        let entries = entries
+            .map(|result| {
+                SocketEvent::Entry(match result {
+                    Ok(entry) => StreamingResponse {
+                        content: entry.content,
+                        is_complete: false,
+                        error: None,
+                        entry_type: convert_entry_type(entry.entry_type),
+                        tool_call: entry.tool_call.map(convert_tool_call_entry),
+                        usage: entry.usage.map(convert_usage),
+                    },
+                    Err(err) => StreamingResponse {
+                        content: String::new(),
+                        is_complete: true,
+                        error: Some(err),
+                        entry_type: StreamingEntryType::ToolCallError,
+                        tool_call: None,
+                        usage: None,
+                    },
+                })
+            })
+            .chain(stream::once(async { SocketEvent::Done }));
+        let controls = incoming
+            .map(|message| SocketEvent::Client(message.ok()))
+            .chain(stream::once(async { SocketEvent::Client(None) }));
+
+        let mut events = stream::select(entries, controls);
+
+        while let Some(event) = events.next().await {
+            match event {
+                SocketEvent::Entry(response) => {
+                    let frame = serde_json::to_string(&response).unwrap_or_default();
+                    if sink.send(Message::Text(frame)).await.is_err() {
+                        break;
+                    }
+                }
+                SocketEvent::Done => {
+                    let frame = serde_json::to_string(&StreamingResponse {
+                        content: String::new(),
+                        is_complete: true,
+                        error: None,
+                        entry_type: StreamingEntryType::Content,
+                        tool_call: None,
+                        usage: None,
+                    })
+                    .unwrap_or_default();
+                    let _ = sink.send(Message::Text(frame)).await;
+                    let _ = sink.close().await;
+                    break;
+                }
+                SocketEvent::Client(Some(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientControl>(&text) {
+                        Ok(ClientControl::Cancel) => {
+                            tracing::info!("Client cancelled prompt for chat {}", chat_id);
+                            prompt.cancel().await;
+                        }
+                        Err(e) => {
+                            tracing::debug!("Ignoring unknown socket message: {}", e);
+                        }
+                    }
+                }
+                SocketEvent::Client(Some(Message::Close(_))) | SocketEvent::Client(None) => break,
+                SocketEvent::Client(Some(_)) => {}
+            }
+        }
+
+        // Dropping the stream closes our receiver, make sure the prompt forgets about it
+        drop(events);
+        prompt.prune_closed_senders().await;
+    }))
+}
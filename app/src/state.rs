@@ -4,7 +4,9 @@ use crate::{
         discourse::{self, DiscourseService},
         ical::{self, ICalConfig},
         meili,
+        metrics::Metrics,
         pm::PMModule,
+        reindex::ReindexService,
         sso::SSOService,
         workshop::WorkshopService,
     },
@@ -30,6 +32,8 @@ pub struct AppStateInner {
     pub workshop: WorkshopService,
     pub cache: CacheService,
     pub meili: Option<meili::Client>,
+    pub reindex: ReindexService,
+    pub metrics: Metrics,
 }
 
 impl AppStateInner {
@@ -45,14 +49,16 @@ impl AppStateInner {
 
         let database = Database::init(&database_config).await;
 
-        let workshop = WorkshopService::init().await;
+        let metrics = Metrics::init();
+
+        let workshop = WorkshopService::init(&metrics).await;
 
         let cache = CacheService::default();
 
         let ical = ical::init_ical(Figment::new()).await;
 
         let discourse_configs = discourse::create_discourse_configs();
-        let discourse = DiscourseService::new(discourse_configs);
+        let discourse = DiscourseService::new(discourse_configs, &metrics);
 
         let pm = PMModule::default();
 
@@ -81,6 +87,8 @@ impl AppStateInner {
             workshop,
             sso,
             meili,
+            reindex: ReindexService::new(Figment::new()),
+            metrics,
         }
     }
 }
@@ -1,13 +1,28 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use moka::future::Cache;
+use poem::http::{HeaderMap, StatusCode};
+use tracing::warn;
 
 use crate::models::ical::CalendarEvent;
-use crate::models::pm::PMData;
+use crate::models::pm::{PMData, PMMeeting};
 
 pub struct CacheService {
     pub ical_cache: Cache<String, Vec<CalendarEvent>>,
     pub pm_data_cache: Cache<String, PMData>,
+    /// Calendar events correlated with ethereum/pm issues and forum topics
+    pub pm_meetings_cache: Cache<String, Vec<PMMeeting>>,
+    pub response_cache: Cache<String, CachedResponse>,
+    /// Recently handled webhook deliveries, so retried deliveries aren't processed twice
+    pub webhook_deliveries: Cache<String, ()>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    pub expires_at: Instant,
 }
 
 impl Default for CacheService {
@@ -15,6 +30,34 @@ impl Default for CacheService {
         Self {
             ical_cache: Cache::builder().time_to_live(Duration::from_secs(60 * 60)).build(),
             pm_data_cache: Cache::builder().time_to_live(Duration::from_secs(60 * 60)).build(),
+            pm_meetings_cache: Cache::builder().time_to_live(Duration::from_secs(15 * 60)).build(),
+            response_cache: Cache::builder()
+                .max_capacity(1000)
+                .time_to_live(Duration::from_secs(60 * 60))
+                .support_invalidation_closures()
+                .build(),
+            webhook_deliveries: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(Duration::from_secs(15 * 60))
+                .build(),
+        }
+    }
+}
+
+impl CacheService {
+    /// Drop cached responses that may include the given topic, including the topic listings
+    pub fn invalidate_topic_responses(&self, discourse_id: &str, topic_id: i32) {
+        let topic_prefix = format!("/t/{}/{}", discourse_id, topic_id);
+
+        let result = self.response_cache.invalidate_entries_if(move |key, _| {
+            key.starts_with("/topics")
+                || key == &topic_prefix
+                || key.starts_with(&format!("{}/", topic_prefix))
+                || key.starts_with(&format!("{}?", topic_prefix))
+        });
+
+        if let Err(e) = result {
+            warn!("Failed to invalidate cached responses: {}", e);
         }
     }
 }
@@ -29,7 +29,7 @@ export interface paths {
                         [name: string]: unknown;
                     };
                     content: {
-                        "application/json; charset=utf-8": components["schemas"]["Topic"][];
+                        "application/json; charset=utf-8": components["schemas"]["PaginatedTopic"];
                     };
                 };
             };
@@ -1787,6 +1787,17 @@ export interface components {
             post_url?: string;
             extra?: unknown;
         };
+        /** PaginatedTopic */
+        PaginatedTopic: {
+            items: components["schemas"]["Topic"][];
+            /** Format: int64 */
+            total: number;
+            /** Format: int64 */
+            limit: number;
+            /** Format: int64 */
+            offset: number;
+            has_more: boolean;
+        };
         /** PostsResponse */
         PostsResponse: {
             posts: components["schemas"]["Post"][];
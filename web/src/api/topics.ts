@@ -22,7 +22,7 @@ export const getTopics = () =>
         queryFn: async () => {
             const response = await useApi('/topics', 'get', {});
 
-            return response.data;
+            return response.data.items;
         },
     });
 